@@ -0,0 +1,187 @@
+//! Writer for the single file database container.
+//!
+//! The format must match `dict_data::Container`:
+//!
+//! ```
+//!     Database {
+//!         Magic:        [u8; 8] = "KOTOBADB",
+//!         Version:      u32_le,
+//!         SectionCount: u32_le,
+//!         Sections:     [Section; SectionCount],
+//!         Data:         [u8],
+//!     }
+//!
+//!     Section {
+//!         Name:   [u8; 32],
+//!         Offset: u32_le,
+//!         Length: u32_le,
+//!     }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::raw::RawUint32;
+
+const MAGIC: &[u8; 8] = b"KOTOBADB";
+
+const HEADER_SIZE: usize = 16;
+
+const SECTION_NAME_SIZE: usize = 32;
+
+pub const SECTION_ENTRY_SIZE: usize = SECTION_NAME_SIZE + 8;
+
+/// Alignment for the section data, so that sections can be used in place
+/// from a memory mapped file.
+const SECTION_ALIGNMENT: usize = 8;
+
+/// Database container being generated. Sections are kept in memory until the
+/// container is written with `finish`.
+pub struct Container {
+	sections: BTreeMap<String, Vec<u8>>,
+	current: Option<String>,
+}
+
+impl Container {
+	pub fn new() -> Container {
+		Container {
+			sections: BTreeMap::new(),
+			current: None,
+		}
+	}
+
+	/// Starts a new section (e.g. `dict/index`). Data written to the container
+	/// is appended to the last started section.
+	pub fn start_file<S: Into<String>>(&mut self, name: S) {
+		let name = name.into();
+		assert!(name.len() <= SECTION_NAME_SIZE && !name.contains('\0'));
+		assert!(!self.sections.contains_key(&name));
+		self.sections.insert(name.clone(), Vec::new());
+		self.current = Some(name);
+	}
+
+	/// Returns the sections with a name starting with `prefix`, in the order
+	/// they are written to the file.
+	pub fn sections<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = (&'a str, &'a [u8])> {
+		self.sections
+			.iter()
+			.filter(move |(name, _)| name.starts_with(prefix))
+			.map(|(name, data)| (name.as_str(), data.as_slice()))
+	}
+
+	/// Returns the data for a section.
+	pub fn section(&self, name: &str) -> Option<&[u8]> {
+		self.sections.get(name).map(|x| x.as_slice())
+	}
+
+	/// Number of sections in the container.
+	pub fn count(&self) -> usize {
+		self.sections.len()
+	}
+
+	/// Writes the container to `path`, returning the file size.
+	///
+	/// The file is written to a temporary file and then renamed, so that an
+	/// existing database is never modified while it may be mapped by a running
+	/// process.
+	pub fn finish<P: AsRef<Path>>(self, path: P) -> usize {
+		let path = path.as_ref();
+		let mut temp_path = path.as_os_str().to_owned();
+		temp_path.push(".tmp");
+
+		let table_size = self.sections.len() * SECTION_ENTRY_SIZE;
+		let mut offset = align(HEADER_SIZE + table_size);
+		let mut table = Vec::with_capacity(table_size);
+		for (name, data) in self.sections.iter() {
+			let mut name_bytes = [0u8; SECTION_NAME_SIZE];
+			name_bytes[..name.len()].copy_from_slice(name.as_bytes());
+			table.extend_from_slice(&name_bytes);
+			table.extend_from_slice(&RawUint32::from(offset).bytes());
+			table.extend_from_slice(&RawUint32::from(data.len()).bytes());
+			offset = align(offset + data.len());
+		}
+		assert!(offset <= u32::MAX as usize);
+
+		let mut output = BufWriter::new(File::create(&temp_path).unwrap());
+		output.write_all(MAGIC).unwrap();
+		output
+			.write_all(&RawUint32::from(crate::DATABASE_VERSION).bytes())
+			.unwrap();
+		output
+			.write_all(&RawUint32::from(self.sections.len()).bytes())
+			.unwrap();
+		output.write_all(&table).unwrap();
+
+		let mut position = HEADER_SIZE + table_size;
+		for data in self.sections.values() {
+			let padding = align(position) - position;
+			output
+				.write_all(&[0u8; SECTION_ALIGNMENT][..padding])
+				.unwrap();
+			output.write_all(data).unwrap();
+			position = align(position) + data.len();
+		}
+		output.flush().unwrap();
+		drop(output);
+
+		fs::rename(&temp_path, path).unwrap();
+		position
+	}
+}
+
+impl Write for Container {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		let name = self.current.as_ref().expect("no section started");
+		self.sections.get_mut(name).unwrap().extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
+fn align(offset: usize) -> usize {
+	offset.div_ceil(SECTION_ALIGNMENT) * SECTION_ALIGNMENT
+}
+
+/// Reads sections from a database file generated by `Container`.
+pub struct Reader {
+	data: Vec<u8>,
+	sections: BTreeMap<String, (usize, usize)>,
+}
+
+impl Reader {
+	pub fn open<P: AsRef<Path>>(path: P) -> Reader {
+		let data = fs::read(path).unwrap();
+		assert!(&data[..8] == MAGIC);
+		let uint = |offset: usize| -> usize {
+			let bytes = [
+				data[offset],
+				data[offset + 1],
+				data[offset + 2],
+				data[offset + 3],
+			];
+			u32::from_le_bytes(bytes) as usize
+		};
+
+		let mut sections = BTreeMap::new();
+		for n in 0..uint(12) {
+			let entry = HEADER_SIZE + n * SECTION_ENTRY_SIZE;
+			let name = &data[entry..entry + SECTION_NAME_SIZE];
+			let name = String::from_utf8_lossy(name);
+			let name = name.trim_end_matches('\0').to_string();
+			let offset = uint(entry + SECTION_NAME_SIZE);
+			let length = uint(entry + SECTION_NAME_SIZE + 4);
+			sections.insert(name, (offset, length));
+		}
+		Reader { data, sections }
+	}
+
+	pub fn section(&self, name: &str) -> Option<&[u8]> {
+		let &(offset, length) = self.sections.get(name)?;
+		Some(&self.data[offset..offset + length])
+	}
+}
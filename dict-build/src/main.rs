@@ -1,30 +1,43 @@
-#![feature(vec_into_raw_parts)]
-
+extern crate fst;
 extern crate regex;
 extern crate serde;
 extern crate serde_json;
-extern crate zip;
+extern crate sha2;
+
+#[cfg(test)]
+extern crate dict_data;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::io::Read;
 use std::io::Write;
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use std::path::{Path, PathBuf};
+
+mod container;
+use container::Container;
 
-use std::fs::File;
-use std::io::{BufReader, BufWriter};
-use std::path::PathBuf;
-use zip::{ZipArchive, ZipWriter};
+mod postings;
 
 mod raw;
 use raw::RawUint32;
 
 /// Directory with files imported by `dict-import`, relative to `Cargo.toml`.
-const IMPORTED_DATA_DIRECTORY: &'static str = "../build/imported";
+const IMPORTED_DATA_DIRECTORY: &str = "../build/imported";
 
-/// Directory where to build the database files.
-const OUTPUT_DATA_DIRECTORY: &'static str = "../data/database";
+/// Directory where to build the database file.
+const OUTPUT_DATA_DIRECTORY: &str = "../data/database";
+
+/// Name of the generated database file. This must match
+/// `dict_data::DATABASE_FILE`.
+const DATABASE_FILE: &str = "kotoba.db";
+
+/// Version of the database format, written to the database file header. This
+/// must match `dict_data::DATABASE_VERSION`.
+const DATABASE_VERSION: u32 = 4;
 
 fn main() {
 	generate();
@@ -37,20 +50,18 @@ fn load_and_test() {
 
 	let start = Instant::now();
 
-	let mut chars_path = db_dir.clone();
-	chars_path.push("chars.zip");
+	let mut db_path = db_dir.clone();
+	db_path.push(DATABASE_FILE);
 
-	let chars = File::open(chars_path).unwrap();
-	let chars = BufReader::new(chars);
-	let mut chars = ZipArchive::new(chars).unwrap();
-	println!("Loaded zip file in {:?}", start.elapsed());
+	let db = container::Reader::open(db_path);
+	println!("Loaded database file in {:?}", start.elapsed());
 
 	let start = Instant::now();
 
-	let q1 = query_chars(&mut chars, '\u{3046}');
-	let q2 = query_chars(&mut chars, '\u{3093}');
-	let q3 = query_chars(&mut chars, '\u{3057}');
-	let q4 = query_chars(&mut chars, '\u{3044}');
+	let q1 = query_chars(&db, '\u{3046}');
+	let q2 = query_chars(&db, '\u{3093}');
+	let q3 = query_chars(&db, '\u{3057}');
+	let q4 = query_chars(&db, '\u{3044}');
 
 	println!("Query finished in {:?}", start.elapsed());
 
@@ -64,14 +75,14 @@ fn load_and_test() {
 
 fn intersect(mut ls: Vec<&[u32]>) -> Vec<u32> {
 	let mut out = Vec::new();
-	if ls.len() == 0 {
+	if ls.is_empty() {
 		return out;
 	} else if ls.len() == 1 {
 		out.extend_from_slice(ls[0]);
 		return out;
 	}
 
-	ls.sort_by(|a, b| a.len().cmp(&b.len()));
+	ls.sort_by_key(|a| a.len());
 	for &next in ls[0].iter() {
 		let mut included = true;
 		for i in 1..ls.len() {
@@ -95,50 +106,87 @@ fn intersect(mut ls: Vec<&[u32]>) -> Vec<u32> {
 	out
 }
 
-fn query_chars<T: std::io::Read + std::io::Seek>(file: &mut ZipArchive<T>, chr: char) -> Vec<u32> {
-	let filename = format!("{:06X}", chr as u32);
+fn query_chars(db: &container::Reader, chr: char) -> Vec<u32> {
+	println!("- Loading {:06X}...", chr as u32);
+	let start = Instant::now();
+	let chars = db.section("chars/chars").unwrap();
+	let index = db.section("chars/index").unwrap();
+	println!(
+		"- Loaded buffers with size {} in {:?}",
+		chars.len() + index.len(),
+		start.elapsed()
+	);
 
-	println!("- Loading {}...", filename);
 	let start = Instant::now();
+	let position = chars
+		.chunks_exact(4)
+		.position(|x| u32::from_le_bytes([x[0], x[1], x[2], x[3]]) == chr as u32);
 	let mut out = Vec::new();
-	if let Ok(mut file) = file.by_name(filename.as_str()) {
-		let mut buffer: Vec<u8> = Vec::with_capacity(file.size() as usize);
-		file.read_to_end(&mut buffer).expect("zip read failed");
-		println!(
-			"- Loaded buffer with size {} in {:?}",
-			buffer.len(),
-			start.elapsed()
-		);
-
-		let start = Instant::now();
+	if let Some(position) = position {
+		out = postings::decode_index(index).swap_remove(position);
+	}
+	println!("- Loaded {} indexes in {:?}", out.len(), start.elapsed());
 
-		let (ptr, len, cap) = buffer.into_raw_parts();
-		let indexes = unsafe {
-			let ptr = ptr as *mut RawUint32;
-			let len = len / 4;
-			let cap = cap / 4;
-			Vec::from_raw_parts(ptr, len, cap)
-		};
+	out
+}
 
-		out.reserve(indexes.len());
+/// Returns the size of a posting list in the previous format.
+type ListSize = fn(&[u32]) -> usize;
+
+/// Posting index sections and the size of each list in the previous format,
+/// used for the size report.
+const POSTING_SECTIONS: &[(&str, ListSize)] = &[
+	("text/terms_index", uint_list_size),
+	("text/search_index", uint_list_size),
+	("text/english_index", uint_list_size),
+	("chars/index", range_list_size),
+	("names/names_index", uint_list_size),
+];
+
+/// Size of a list as a `(Offset, Length)` index entry and `u32` values.
+fn uint_list_size(list: &[u32]) -> usize {
+	8 + list.len() * 4
+}
 
-		let mut indexes = indexes.into_iter().map(|x| Into::<u32>::into(x));
-		while let Some(index) = indexes.next() {
-			if index & 0x8000_0000 != 0 {
-				let sta = index & 0x0FFF_FFFF;
-				let end = indexes.next().unwrap();
-				assert!(sta < end);
-				for index in sta..=end {
-					out.push(index);
-				}
-			} else {
-				out.push(index);
-			}
+/// Size of a list stored in its own section as `u32` values, where consecutive
+/// runs are stored as a `(start, end)` pair.
+fn range_list_size(list: &[u32]) -> usize {
+	let mut size = container::SECTION_ENTRY_SIZE;
+	for (n, &value) in list.iter().enumerate() {
+		if n < 2 || list[n - 1] + 1 != value || list[n - 2] + 1 != list[n - 1] {
+			size += 4;
 		}
-		println!("- Loaded {} indexes in {:?}", out.len(), start.elapsed());
 	}
+	size.div_ceil(8) * 8
+}
 
-	out
+/// Prints the size of the posting index sections compared to the size of the
+/// same lists in the previous uncompressed format.
+fn report_postings(db: &Container) {
+	println!("\nPosting lists (previous format => compressed):");
+	let (mut total_before, mut total_after) = (0, 0);
+	for &(name, list_size) in POSTING_SECTIONS.iter() {
+		let data = db.section(name).unwrap();
+		let lists = postings::decode_index(data);
+		let before: usize = 4 + lists.iter().map(|x| list_size(x)).sum::<usize>();
+		let after = data.len();
+		println!(
+			"- {:20} {:>10} => {:>10} bytes ({:.1}%)",
+			name,
+			before,
+			after,
+			after as f64 * 100.0 / before as f64
+		);
+		total_before += before;
+		total_after += after;
+	}
+	println!(
+		"- {:20} {:>10} => {:>10} bytes ({:.1}%)\n",
+		"total",
+		total_before,
+		total_after,
+		total_after as f64 * 100.0 / total_before as f64
+	);
 }
 
 #[allow(dead_code)]
@@ -165,11 +213,45 @@ fn generate() {
 	let output_dir = std::fs::canonicalize(output_dir).unwrap();
 	println!("Generating data to {}...", output_dir.to_string_lossy());
 
-	generate_dict(input_dir.clone(), output_dir.clone());
-	generate_text(input_dir.clone(), output_dir.clone());
-	generate_meta(input_dir.clone(), output_dir.clone());
-	generate_kanji(input_dir.clone(), output_dir.clone());
-	generate_chars(input_dir.clone(), output_dir.clone());
+	let mut db_output = output_dir;
+	db_output.push(DATABASE_FILE);
+	build_database(input_dir, &db_output);
+}
+
+/// Generates the database file at `output` from the files in `input_dir`.
+fn build_database(input_dir: PathBuf, output: &Path) {
+	let mut db = Container::new();
+	let entries = generate_dict(input_dir.clone(), &mut db);
+	generate_text(input_dir.clone(), &mut db);
+	generate_meta(input_dir.clone(), &mut db);
+	let kanji = generate_kanji(input_dir.clone(), &mut db);
+	generate_chars(input_dir.clone(), &mut db);
+	let names = generate_names(input_dir.clone(), &mut db);
+	let examples = generate_examples(input_dir.clone(), &mut db);
+	let strokes = generate_strokes(input_dir.clone(), &mut db);
+
+	// The manifest must be generated last, since it has the checksums for all
+	// the other sections.
+	let counts = ManifestCounts {
+		entries,
+		kanji,
+		names,
+		examples,
+		strokes,
+	};
+	generate_manifest(input_dir, &mut db, counts);
+	report_postings(&db);
+
+	let start = Instant::now();
+	let sections = db.count();
+	let size = db.finish(output);
+	println!(
+		"Wrote {} in {:?} ({} sections, {} bytes)",
+		DATABASE_FILE,
+		start.elapsed(),
+		sections,
+		size
+	);
 }
 
 #[derive(Deserialize)]
@@ -182,14 +264,9 @@ struct DictDefinition {
 	tags_text: Vec<u32>,
 }
 
-fn generate_dict(input_dir: PathBuf, output_dir: PathBuf) {
+fn generate_dict(input_dir: PathBuf, dict: &mut Container) -> usize {
 	let start = Instant::now();
 
-	let mut dict_output = output_dir;
-	dict_output.push("dict.zip");
-
-	let dict = BufWriter::new(File::create(dict_output).unwrap());
-	let mut dict = ZipWriter::new(dict);
 
 	let mut dict_index = Vec::new();
 
@@ -203,8 +280,8 @@ fn generate_dict(input_dir: PathBuf, output_dir: PathBuf) {
 		dict_index.extend(fields);
 	}
 
-	dict.start_file("index", Default::default()).unwrap();
-	write_zip(&mut dict, unsafe { vec_bytes(&dict_index) });
+	dict.start_file("dict/index");
+	write_data(dict, unsafe { vec_bytes(&dict_index) });
 
 	let mut dict_data_path = input_dir.clone();
 	dict_data_path.push("dictionary_data.txt");
@@ -214,30 +291,24 @@ fn generate_dict(input_dir: PathBuf, output_dir: PathBuf) {
 	let mut cur_file_data: Vec<RawUint32> = Vec::new();
 	let mut cur_file_index: Vec<RawUint32> = Vec::new();
 
-	fn push_file(
-		zip: &mut ZipWriter<BufWriter<File>>,
-		number: i32,
-		data: Vec<RawUint32>,
-		index: Vec<RawUint32>,
-	) {
+	fn push_file(output: &mut Container, number: i32, data: Vec<RawUint32>, index: Vec<RawUint32>) {
 		if number >= 0 {
-			zip.start_file(format!("{:04}", number), Default::default())
-				.unwrap();
-			write_zip(zip, unsafe {
+			output.start_file(format!("dict/{:04}", number));
+			write_data(output, unsafe {
 				vec_bytes(&vec![
 					RawUint32::from(index.len()),
 					RawUint32::from(data.len()),
 				])
 			});
-			write_zip(zip, unsafe { vec_bytes(&index) });
-			write_zip(zip, unsafe { vec_bytes(&data) });
+			write_data(output, unsafe { vec_bytes(&index) });
+			write_data(output, unsafe { vec_bytes(&data) });
 		}
 	}
 
 	for (index, line) in dict_data_text.lines().enumerate() {
 		let file_num = (index / 1000) as i32;
 		if file_num != cur_file {
-			push_file(&mut dict, cur_file, cur_file_data, cur_file_index);
+			push_file(dict, cur_file, cur_file_data, cur_file_index);
 			cur_file_data = Vec::new();
 			cur_file_index = Vec::new();
 			cur_file = file_num;
@@ -266,76 +337,52 @@ fn generate_dict(input_dir: PathBuf, output_dir: PathBuf) {
 		cur_file_data.append(&mut data);
 	}
 
-	push_file(&mut dict, cur_file, cur_file_data, cur_file_index);
+	push_file(dict, cur_file, cur_file_data, cur_file_index);
 
-	println!("Wrote dict.zip in {:?}", start.elapsed());
+	println!("Generated dict in {:?}", start.elapsed());
+	dict_index.len() / 4
 }
 
-fn generate_text(input_dir: PathBuf, output_dir: PathBuf) {
+fn generate_text(input_dir: PathBuf, text: &mut Container) {
 	let start = Instant::now();
 
-	let mut text_output = output_dir;
-	text_output.push("text.zip");
-
-	let text = BufWriter::new(File::create(text_output).unwrap());
-	let mut text = ZipWriter::new(text);
 
 	let mut glossary_input_path = input_dir.clone();
 	glossary_input_path.push("glossary.txt");
 	let glossary = fs::read_to_string(glossary_input_path).unwrap();
-	text.start_file("glossary", Default::default()).unwrap();
-	generate_text_data_file(&mut text, &glossary);
+	text.start_file("text/glossary");
+	generate_text_data_file(text, &glossary);
 
 	let mut english_input_path = input_dir.clone();
 	english_input_path.push("english.txt");
 	let english = fs::read_to_string(english_input_path).unwrap();
 
-	let mut english_index = Vec::new();
-	let mut english_data = Vec::new();
-	let mut english_list = Vec::new();
+	// Each line is `word,entry,entry,...`. The words are written as a text
+	// section, with the entry lists in a posting index section.
+	let mut english_words = String::new();
+	let mut english_lists = Vec::new();
 	for line in english.lines() {
 		let mut fields = line.split(',');
-		let word = fields.next().unwrap();
-		let (text_offset, text_length) = (english_data.len(), word.len());
-		english_data.extend_from_slice(word.as_bytes());
+		english_words.push_str(fields.next().unwrap());
+		english_words.push('\n');
+		english_lists.push(fields.map(|x| x.parse::<u32>().unwrap()).collect());
+	}
 
-		let mut list: Vec<_> = fields
-			.map(|x| RawUint32::from(x.parse::<u32>().unwrap()))
-			.collect();
-		let (list_offset, list_length) = (english_list.len(), list.len());
-		english_list.append(&mut list);
-
-		english_index.push((
-			RawUint32::from(text_offset),
-			RawUint32::from(text_length),
-			RawUint32::from(list_offset),
-			RawUint32::from(list_length),
-		));
-	}
-
-	let english_index_bytes = unsafe { vec_bytes(&english_index) };
-	let english_list_bytes = unsafe { vec_bytes(&english_list) };
-	text.start_file("glossary_index", Default::default())
-		.unwrap();
-	write_zip(&mut text, unsafe {
-		vec_bytes(&vec![
-			RawUint32::from(english_index_bytes.len()),
-			RawUint32::from(english_data.len()),
-			RawUint32::from(english_list_bytes.len()),
-		])
-	});
-	write_zip(&mut text, english_index_bytes);
-	write_zip(&mut text, &english_data);
-	write_zip(&mut text, &english_list_bytes);
-
-	generate_text_index(&mut text, input_dir.clone(), "terms");
-	generate_text_index(&mut text, input_dir.clone(), "search");
-
-	println!("Wrote text.zip in {:?}", start.elapsed());
+	text.start_file("text/english_text");
+	generate_text_data_file(text, &english_words);
+	text.start_file("text/english_index");
+	generate_posting_file(text, &english_lists);
+
+	generate_text_index(text, input_dir.clone(), "text", "terms");
+	generate_text_index(text, input_dir.clone(), "text", "search");
+	generate_term_index(text, input_dir.clone(), "text", "terms");
+	generate_term_index(text, input_dir.clone(), "text", "search");
+
+	println!("Generated text in {:?}", start.elapsed());
 }
 
-fn generate_text_data_file(zip: &mut ZipWriter<BufWriter<File>>, text: &str) {
-	let mut count = 0 as u32;
+fn generate_text_data_file(output: &mut Container, text: &str) {
+	let mut count = 0_u32;
 	let mut index = Vec::new();
 	let mut data = Vec::new();
 	for line in text.lines() {
@@ -348,126 +395,169 @@ fn generate_text_data_file(zip: &mut ZipWriter<BufWriter<File>>, text: &str) {
 	}
 
 	let count = RawUint32::from(count);
-	write_zip(zip, &count.bytes());
-	write_zip(zip, unsafe { vec_bytes(&index) });
-	write_zip(zip, &data);
+	write_data(output, &count.bytes());
+	write_data(output, unsafe { vec_bytes(&index) });
+	write_data(output, &data);
 }
 
-fn generate_text_index(zip: &mut ZipWriter<BufWriter<File>>, input_dir: PathBuf, name: &str) {
+fn generate_text_index(output: &mut Container, input_dir: PathBuf, part: &str, name: &str) {
 	let mut text_input_path = input_dir.clone();
 	text_input_path.push(format!("{}.txt", name));
 	let text = fs::read_to_string(text_input_path).unwrap();
 
-	zip.start_file(format!("{}_text", name), Default::default())
-		.unwrap();
-	generate_text_data_file(zip, &text);
+	output.start_file(format!("{}/{}_text", part, name));
+	generate_text_data_file(output, &text);
 
 	let mut index_input_path = input_dir.clone();
 	index_input_path.push(format!("{}_index.txt", name));
 	let index = fs::read_to_string(index_input_path).unwrap();
 
-	let mut index_count = 0 as u32;
-	let mut index_index = Vec::new();
-	let mut index_data = Vec::new();
-	for line in index.lines() {
-		let mut items: Vec<RawUint32> = line
-			.split(',')
-			.map(|x| x.parse::<u32>().unwrap().into())
-			.collect();
-		index_count += 1;
-
-		let (offset, length) = (index_data.len(), items.len());
-		let offset = RawUint32::from(offset);
-		let length = RawUint32::from(length);
-		index_index.push((offset, length));
-		index_data.append(&mut items);
-	}
+	let index: Vec<Vec<u32>> = index
+		.lines()
+		.map(|line| line.split(',').map(|x| x.parse::<u32>().unwrap()).collect())
+		.collect();
 
-	let index_count = RawUint32::from(index_count);
-	zip.start_file(format!("{}_index", name), Default::default())
-		.unwrap();
-	write_zip(zip, &index_count.bytes());
-	write_zip(zip, unsafe { vec_bytes(&index_index) });
-	write_zip(zip, unsafe { vec_bytes(&index_data) });
+	output.start_file(format!("{}/{}_index", part, name));
+	generate_posting_file(output, &index);
 
 	let mut reverse_input_path = input_dir.clone();
 	reverse_input_path.push(format!("{}_index_reverse.txt", name));
 	let reverse = fs::read_to_string(reverse_input_path).unwrap();
 
-	let mut reverse_data = vec![RawUint32::from(0 as u32)];
+	let mut reverse_data = vec![RawUint32::from(0_u32)];
 	for line in reverse.lines() {
 		let item: RawUint32 = line.parse::<u32>().unwrap().into();
 		reverse_data.push(item);
 	}
 
-	zip.start_file(format!("{}_reverse", name), Default::default())
-		.unwrap();
-	write_zip(zip, unsafe { vec_bytes(&reverse_data) });
+	output.start_file(format!("{}/{}_reverse", part, name));
+	write_data(output, unsafe { vec_bytes(&reverse_data) });
 }
 
-fn generate_kanji(input_dir: PathBuf, output_dir: PathBuf) {
-	let start = Instant::now();
+/// Writes the FST term index section for a sorted text file, which maps each
+/// line to its position (i.e. the position of its list in the index).
+fn generate_term_index(output: &mut Container, input_dir: PathBuf, part: &str, name: &str) {
+	let mut text_input_path = input_dir.clone();
+	text_input_path.push(format!("{}.txt", name));
+	let text = fs::read_to_string(text_input_path).unwrap();
 
-	let mut kanji_output = output_dir;
-	kanji_output.push("kanji.zip");
+	let mut builder = fst::MapBuilder::memory();
+	for (index, line) in text.lines().enumerate() {
+		builder.insert(line, index as u64).unwrap();
+	}
+
+	output.start_file(format!("{}/{}_fst", part, name));
+	write_data(output, &builder.into_inner().unwrap());
+}
+
+/// Writes an index section, which is a list of number lists:
+///
+/// ```
+///     Index {
+///         Count: u32_le,
+///         Index: [(Offset: u32_le, Length: u32_le); Count],
+///         Data:  [u32_le],
+///     }
+/// ```
+fn generate_index_file(output: &mut Container, lists: &[Vec<u32>]) {
+	let mut index_index = Vec::new();
+	let mut index_data = Vec::new();
+	for items in lists.iter() {
+		let (offset, length) = (index_data.len(), items.len());
+		let offset = RawUint32::from(offset);
+		let length = RawUint32::from(length);
+		index_index.push((offset, length));
+		index_data.extend(items.iter().map(|&x| RawUint32::from(x)));
+	}
+
+	let index_count = RawUint32::from(lists.len());
+	write_data(output, &index_count.bytes());
+	write_data(output, unsafe { vec_bytes(&index_index) });
+	write_data(output, unsafe { vec_bytes(&index_data) });
+}
+
+/// Writes a posting index section, which is a list of sorted number lists
+/// compressed by `postings::encode`:
+///
+/// ```
+///     PostingIndex {
+///         Count: u32_le,
+///         Index: [(Offset: u32_le, Length: u32_le); Count],
+///         Data:  [u8],
+///     }
+/// ```
+///
+/// `Offset` is the position of the list in `Data` in bytes and `Length` is the
+/// number of values in the list.
+fn generate_posting_file(output: &mut Container, lists: &[Vec<u32>]) {
+	let mut index_index = Vec::new();
+	let mut index_data = Vec::new();
+	for items in lists.iter() {
+		let (offset, length) = (index_data.len(), items.len());
+		let offset = RawUint32::from(offset);
+		let length = RawUint32::from(length);
+		index_index.push((offset, length));
+		postings::encode(items, &mut index_data);
+	}
+
+	let index_count = RawUint32::from(lists.len());
+	write_data(output, &index_count.bytes());
+	write_data(output, unsafe { vec_bytes(&index_index) });
+	write_data(output, &index_data);
+}
+
+fn generate_kanji(input_dir: PathBuf, kanji: &mut Container) -> usize {
+	let start = Instant::now();
 
-	let kanji = BufWriter::new(File::create(kanji_output).unwrap());
-	let mut kanji = ZipWriter::new(kanji);
 
 	let mut kanji_input_path = input_dir;
 	kanji_input_path.push("kanji.json");
 
+	// Validate and re-encode the pretty printed kanji data in compact form.
 	let kanji_input = fs::read_to_string(kanji_input_path).unwrap();
-	kanji.start_file("kanji.json", Default::default()).unwrap();
+	let kanji_input: serde_json::Value = serde_json::from_str(&kanji_input).unwrap();
+	let kanji_count = kanji_input.as_array().map(|x| x.len()).unwrap_or_default();
+	let kanji_input = serde_json::to_string(&kanji_input).unwrap();
+	kanji.start_file("kanji/kanji.json");
 
 	let bytes = kanji_input.as_bytes();
-	write_zip(&mut kanji, &bytes);
+	write_data(kanji, bytes);
 
-	kanji.finish().unwrap();
-	println!("Wrote kanji.zip in {:?}", start.elapsed());
+	println!("Generated kanji in {:?}", start.elapsed());
+	kanji_count
 }
 
-fn generate_meta(input_dir: PathBuf, output_dir: PathBuf) {
+fn generate_meta(input_dir: PathBuf, meta: &mut Container) {
 	let start = Instant::now();
 
-	let mut meta_output = output_dir;
-	meta_output.push("meta.zip");
-
-	let meta = BufWriter::new(File::create(meta_output).unwrap());
-	let mut meta = ZipWriter::new(meta);
 
 	let mut tags_path = input_dir.clone();
 	tags_path.push("tags.json");
 
-	meta.start_file("tags.json", Default::default()).unwrap();
-	write_zip(&mut meta, &fs::read(tags_path).unwrap());
+	meta.start_file("meta/tags.json");
+	write_data(meta, &fs::read(tags_path).unwrap());
 
 	let mut sources_path = input_dir.clone();
 	sources_path.push("sources.txt");
 
-	meta.start_file("sources.txt", Default::default()).unwrap();
-	write_zip(&mut meta, &fs::read(&sources_path).unwrap());
+	meta.start_file("meta/sources.txt");
+	write_data(meta, &fs::read(&sources_path).unwrap());
 
-	meta.finish().unwrap();
-	println!("Wrote meta.zip in {:?}", start.elapsed());
+	println!("Generated meta in {:?}", start.elapsed());
 }
 
-fn generate_chars(input_dir: PathBuf, output_dir: PathBuf) {
+fn generate_chars(input_dir: PathBuf, chars: &mut Container) {
 	let start = Instant::now();
 
-	let mut chars_output = output_dir;
-	chars_output.push("chars.zip");
-
-	let chars = BufWriter::new(File::create(chars_output).unwrap());
-	let mut chars = ZipWriter::new(chars);
 
 	let mut chars_index_path = input_dir;
 	chars_index_path.push("chars_index.txt");
 
 	let chars_index = fs::read_to_string(chars_index_path).unwrap();
+	let mut chars_lists = Vec::new();
 	for line in chars_index.lines() {
 		let line = line.trim();
-		if line.len() > 0 {
+		if !line.is_empty() {
 			let mut fields = line.split(',');
 
 			let character = fields.next().unwrap();
@@ -475,6 +565,7 @@ fn generate_chars(input_dir: PathBuf, output_dir: PathBuf) {
 			assert!(character.len() == 1);
 			let character = character[0];
 
+			// Entry indexes are either a single index or a `start-end` range.
 			let count = fields.next().unwrap().parse::<usize>().unwrap();
 			let mut indexes = Vec::with_capacity(count);
 			for index in fields {
@@ -485,33 +576,371 @@ fn generate_chars(input_dir: PathBuf, output_dir: PathBuf) {
 				} else {
 					sta
 				};
+				indexes.extend(sta..=end);
+			}
+			assert!(indexes.len() == count);
 
-				if end > sta {
-					assert!(sta <= 0x0FFF_FFFF);
-					indexes.push(RawUint32::from(sta + 0x8000_0000));
-					indexes.push(RawUint32::from(end));
-				} else {
-					indexes.push(RawUint32::from(sta));
+			chars_lists.push((character as u32, indexes));
+		}
+	}
+
+	// The `chars` section is the sorted list of characters, with the entries
+	// for each character in the posting index section at the same position.
+	chars_lists.sort_by_key(|x| x.0);
+	let chars_list: Vec<_> = chars_lists.iter().map(|x| RawUint32::from(x.0)).collect();
+	let chars_lists: Vec<_> = chars_lists.into_iter().map(|x| x.1).collect();
+
+	chars.start_file("chars/chars");
+	write_data(chars, unsafe { vec_bytes(&chars_list) });
+	chars.start_file("chars/index");
+	generate_posting_file(chars, &chars_lists);
+
+	println!("Generated chars in {:?}", start.elapsed());
+}
+
+/// Name types from JMnedict, in the order of their bits in the `names/index`
+/// section. Unknown name types are mapped to the last bit (`other`).
+const NAME_TYPES: &[&str] = &[
+	"surname",
+	"given",
+	"place",
+	"company",
+	"person",
+	"masc",
+	"fem",
+	"organization",
+	"product",
+	"work",
+	"station",
+	"unclass",
+	"other",
+];
+
+fn generate_names(input_dir: PathBuf, names: &mut Container) -> usize {
+	let start = Instant::now();
+
+
+	// The `index` file contains the `(expression, reading, types)` for each
+	// name, where `types` is a bit mask of `NAME_TYPES`.
+	let mut names_main_path = input_dir.clone();
+	names_main_path.push("names_main.txt");
+	let names_main = fs::read_to_string(names_main_path).unwrap();
+
+	let mut names_index = Vec::new();
+	for line in names_main.lines() {
+		let mut fields = line.splitn(3, ',');
+		let expr = fields.next().unwrap().parse::<u32>().unwrap();
+		let read = fields.next().unwrap().parse::<u32>().unwrap();
+		let mut mask = 0_u32;
+		for name_type in fields.next().unwrap_or_default().split(' ') {
+			if !name_type.is_empty() {
+				let bit = NAME_TYPES
+					.iter()
+					.position(|&x| x == name_type)
+					.unwrap_or(NAME_TYPES.len() - 1);
+				mask |= 1 << bit;
+			}
+		}
+		names_index.push(RawUint32::from(expr));
+		names_index.push(RawUint32::from(read));
+		names_index.push(RawUint32::from(mask));
+	}
+
+	names.start_file("names/index");
+	write_data(names, unsafe { vec_bytes(&names_index) });
+
+	let mut glossary_input_path = input_dir.clone();
+	glossary_input_path.push("names_glossary.txt");
+	let glossary = fs::read_to_string(glossary_input_path).unwrap();
+	names.start_file("names/glossary");
+	generate_text_data_file(names, &glossary);
+
+	generate_text_index(names, input_dir.clone(), "names", "names");
+
+	println!("Generated names in {:?}", start.elapsed());
+	names_index.len() / 3
+}
+
+/// Maximum length in characters of a dictionary term when matching terms in
+/// example sentences without a word index.
+const MAX_EXAMPLE_TERM_LENGTH: usize = 12;
+
+fn generate_examples(input_dir: PathBuf, examples: &mut Container) -> usize {
+	let start = Instant::now();
+
+
+	// Load the dictionary terms so we can map the sentence words to entries.
+
+	let mut terms_path = input_dir.clone();
+	terms_path.push("terms.txt");
+	let terms_text = fs::read_to_string(terms_path).unwrap();
+	let terms: Vec<&str> = terms_text.lines().collect();
+
+	let mut dict_main_path = input_dir.clone();
+	dict_main_path.push("dictionary_main.txt");
+	let dict_main_text = fs::read_to_string(dict_main_path).unwrap();
+
+	// Map of `(expression, reading)` and of either expression or reading to
+	// the entry indexes (zero-based).
+	let mut entries_by_pair: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+	let mut entries_by_term: HashMap<&str, Vec<usize>> = HashMap::new();
+	let mut entries_freq: Vec<u32> = Vec::new();
+	for (index, line) in dict_main_text.lines().enumerate() {
+		let fields: Vec<usize> = line.split(',').map(|x| x.parse().unwrap()).collect();
+		let get_term = |n: usize| if n == 0 { "" } else { terms[n - 1] };
+		let (expr, read) = (get_term(fields[0]), get_term(fields[1]));
+		entries_by_pair.entry((expr, read)).or_default().push(index);
+		entries_by_term.entry(expr).or_default().push(index);
+		if !read.is_empty() && read != expr {
+			entries_by_term.entry(read).or_default().push(index);
+		}
+		entries_freq.push(fields[3] as u32);
+	}
+
+	let mut examples_path = input_dir.clone();
+	examples_path.push("examples.txt");
+	let examples_text = fs::read_to_string(examples_path).unwrap();
+
+	let mut sentences = String::new();
+	let mut entry_examples: Vec<Vec<(f64, u32)>> = vec![Vec::new(); entries_freq.len()];
+	let mut linked = 0;
+	for (index, line) in examples_text.lines().enumerate() {
+		let mut fields = line.split('\t');
+		let japanese = fields.next().unwrap_or_default();
+		let english = fields.next().unwrap_or_default();
+		let words = fields.next().unwrap_or_default();
+		sentences.push_str(&format!("{}\t{}\n", japanese, english));
+
+		let mut entries = Vec::new();
+		if !words.is_empty() {
+			// Use the sentence word index, each word is `expression(reading)`.
+			for word in words.split(' ') {
+				let (expr, read) = match word.find('(') {
+					Some(pos) => (&word[..pos], word[pos + 1..].trim_end_matches(')')),
+					None => (word, ""),
+				};
+				if let Some(ls) = entries_by_pair.get(&(expr, read)) {
+					entries.extend_from_slice(ls);
+				} else if let Some(ls) = entries_by_term.get(expr) {
+					entries.extend_from_slice(ls);
+				}
+			}
+		} else {
+			// Without an index, match the longest dictionary term at each
+			// position of the sentence.
+			let chars: Vec<(usize, char)> = japanese.char_indices().collect();
+			let mut pos = 0;
+			while pos < chars.len() {
+				let mut matched = 1;
+				for len in (1..=MAX_EXAMPLE_TERM_LENGTH.min(chars.len() - pos)).rev() {
+					let sta = chars[pos].0;
+					let end = chars.get(pos + len).map(|x| x.0).unwrap_or(japanese.len());
+					let term = &japanese[sta..end];
+					// Single kana are mostly particles, which would link the
+					// sentence to unrelated entries.
+					let is_kana = |c: char| ('\u{3041}'..='\u{30FF}').contains(&c);
+					if len == 1 && is_kana(chars[pos].1) {
+						break;
+					}
+					if let Some(ls) = entries_by_term.get(term) {
+						entries.extend_from_slice(ls);
+						matched = len;
+						break;
+					}
 				}
+				pos += matched;
 			}
+		}
 
-			let filename = format!("{:06X}", character as u32);
-			chars
-				.start_file(&filename, zip::write::FileOptions::default())
-				.unwrap();
+		entries.sort();
+		entries.dedup();
+		if entries.is_empty() {
+			continue;
+		}
+		linked += 1;
+
+		// Score the sentence so that shorter sentences with more frequent
+		// vocabulary come first. The least frequent word in the sentence
+		// determines its vocabulary score.
+		let length = japanese.chars().count() as f64;
+		let min_freq = entries.iter().map(|&x| entries_freq[x]).min().unwrap();
+		let score = ((min_freq + 1) as f64).ln() / length.max(1.0).sqrt();
+		for entry in entries {
+			entry_examples[entry].push((score, index as u32 + 1));
+		}
+	}
+
+	examples.start_file("examples/sentences");
+	generate_text_data_file(examples, &sentences);
+
+	let index: Vec<Vec<u32>> = entry_examples
+		.into_iter()
+		.map(|mut ls| {
+			ls.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap().then(a.1.cmp(&b.1)));
+			ls.into_iter().map(|x| x.1).collect()
+		})
+		.collect();
+	examples.start_file("examples/index");
+	generate_index_file(examples, &index);
+
+	println!(
+		"Generated examples in {:?} ({} of {} sentences linked)",
+		start.elapsed(),
+		linked,
+		examples_text.lines().count()
+	);
+	examples_text.lines().count()
+}
 
-			let bytes = unsafe { vec_bytes(&indexes) };
-			write_zip(&mut chars, &bytes);
+fn generate_strokes(input_dir: PathBuf, strokes: &mut Container) -> usize {
+	let start = Instant::now();
+
+
+	// For each kanji, the `(first, count)` ranges for its entries in the
+	// `strokes` and `groups` files. The input files are sorted by character.
+	let mut kanji: BTreeMap<char, [u32; 4]> = BTreeMap::new();
+
+	let mut strokes_path = input_dir.clone();
+	strokes_path.push("strokes.txt");
+	let strokes_input = fs::read_to_string(strokes_path).unwrap();
+
+	let mut strokes_text = String::new();
+	for (index, line) in strokes_input.lines().enumerate() {
+		let mut fields = line.splitn(2, '\t');
+		let chr = fields.next().unwrap().chars().next().unwrap();
+		strokes_text.push_str(fields.next().unwrap_or_default());
+		strokes_text.push('\n');
+
+		let range = kanji.entry(chr).or_insert([index as u32, 0, 0, 0]);
+		range[1] += 1;
+	}
+
+	let mut groups_path = input_dir.clone();
+	groups_path.push("stroke_groups.txt");
+	let groups_input = fs::read_to_string(groups_path).unwrap();
+
+	let mut groups_text = String::new();
+	for (index, line) in groups_input.lines().enumerate() {
+		let mut fields = line.splitn(2, '\t');
+		let chr = fields.next().unwrap().chars().next().unwrap();
+		groups_text.push_str(fields.next().unwrap_or_default());
+		groups_text.push('\n');
+
+		let range = kanji.entry(chr).or_insert([0, 0, 0, 0]);
+		if range[3] == 0 {
+			range[2] = index as u32;
 		}
+		range[3] += 1;
+	}
+
+	// The `index` file contains `(character, strokes_first, strokes_count,
+	// groups_first, groups_count)` for each kanji, sorted by character.
+	let mut index = Vec::new();
+	for (chr, range) in kanji.iter() {
+		index.push(RawUint32::from(*chr as u32));
+		index.extend(range.iter().map(|&x| RawUint32::from(x)));
 	}
 
-	chars.finish().unwrap();
+	strokes.start_file("strokes/index");
+	write_data(strokes, unsafe { vec_bytes(&index) });
 
-	println!("Wrote chars.zip in {:?}", start.elapsed());
+	strokes.start_file("strokes/strokes");
+	generate_text_data_file(strokes, &strokes_text);
+
+	strokes.start_file("strokes/groups");
+	generate_text_data_file(strokes, &groups_text);
+
+	println!(
+		"Generated strokes in {:?} ({} kanji)",
+		start.elapsed(),
+		kanji.len()
+	);
+	kanji.len()
+}
+
+/// Database parts listed in the manifest, in generation order.
+const MANIFEST_PARTS: &[&str] = &[
+	"dict", "text", "meta", "kanji", "chars", "names", "examples", "strokes",
+];
+
+/// Contents of the `manifest.json` section, which identifies the database.
+///
+/// This must match `dict_data::Manifest`.
+#[derive(Serialize)]
+struct Manifest {
+	version: u32,
+	created: u64,
+	parts: Vec<ManifestPart>,
+	counts: ManifestCounts,
+	sources: Vec<ManifestSource>,
+}
+
+#[derive(Serialize)]
+struct ManifestPart {
+	name: String,
+	size: u64,
+	sha256: String,
+}
+
+#[derive(Serialize)]
+struct ManifestCounts {
+	entries: usize,
+	kanji: usize,
+	names: usize,
+	examples: usize,
+	strokes: usize,
+}
+
+#[derive(Deserialize, Serialize)]
+struct ManifestSource {
+	title: String,
+	revision: String,
+}
+
+fn generate_manifest(input_dir: PathBuf, output: &mut Container, counts: ManifestCounts) {
+	let start = Instant::now();
+
+	// The checksum for a part is of all its sections, in name order.
+	let mut parts = Vec::new();
+	for name in MANIFEST_PARTS {
+		let prefix = format!("{}/", name);
+		let mut size = 0;
+		let mut hasher = Sha256::new();
+		for (_, data) in output.sections(&prefix) {
+			size += data.len();
+			hasher.update(data);
+		}
+		parts.push(ManifestPart {
+			name: name.to_string(),
+			size: size as u64,
+			sha256: format!("{:x}", hasher.finalize()),
+		});
+	}
+
+	let mut dictionaries_path = input_dir.clone();
+	dictionaries_path.push("dictionaries.json");
+	let dictionaries = fs::read_to_string(dictionaries_path).unwrap();
+	let sources: Vec<ManifestSource> = serde_json::from_str(&dictionaries).unwrap();
+
+	let manifest = Manifest {
+		version: DATABASE_VERSION,
+		created: SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap()
+			.as_secs(),
+		parts,
+		counts,
+		sources,
+	};
+
+	output.start_file("manifest.json");
+	serde_json::to_writer_pretty(output, &manifest).unwrap();
+
+	println!("Generated manifest.json in {:?}", start.elapsed());
 }
 
-fn write_zip(output: &mut ZipWriter<BufWriter<File>>, mut bytes: &[u8]) {
-	while bytes.len() > 0 {
+fn write_data(output: &mut Container, mut bytes: &[u8]) {
+	while !bytes.is_empty() {
 		let written = output.write(bytes).unwrap();
 		assert!(written > 0);
 		bytes = &bytes[written..];
@@ -523,3 +952,246 @@ unsafe fn vec_bytes<T: Sized>(value: &Vec<T>) -> &[u8] {
 	let start = value.as_slice().as_ptr();
 	std::slice::from_raw_parts(start as *const u8, std::mem::size_of::<T>() * value.len())
 }
+
+#[cfg(test)]
+mod tests {
+	use std::sync::OnceLock;
+
+	use dict_data::{Database, Error, Query, SearchMode};
+
+	use super::*;
+
+	/// Directory with a small import of common words by `dict-import`,
+	/// relative to `Cargo.toml`. It must be imported again when the format of
+	/// the imported files changes.
+	const TEST_DATA_DIRECTORY: &str = "testdata/imported";
+
+	/// Returns the database built from the test data, loaded with `dict_data`.
+	fn database() -> &'static Database {
+		static DATABASE: OnceLock<Database> = OnceLock::new();
+		DATABASE.get_or_init(build_test_database)
+	}
+
+	fn build_test_database() -> Database {
+		let mut output = std::env::temp_dir();
+		output.push(format!("dict-build-test-{}.db", std::process::id()));
+		let input_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(TEST_DATA_DIRECTORY);
+		build_database(input_dir, &output);
+		let bytes = fs::read(&output).unwrap();
+		fs::remove_file(&output).unwrap();
+		Database::from_bytes(bytes).unwrap()
+	}
+
+	#[test]
+	fn test_database() {
+		let db = database();
+		db.verify().unwrap();
+
+		let counts = &db.info().counts;
+		assert_eq!(31, counts.entries);
+		assert_eq!(31, db.dictionary().count());
+		assert_eq!(30, db.kanji().count());
+		assert_eq!(8, db.names().count());
+		assert_eq!(5, db.examples().count());
+		assert_eq!(2, db.strokes().count());
+	}
+
+	fn query(text: &str) -> Vec<String> {
+		query_limit(text, 100)
+	}
+
+	fn query_limit(text: &str, limit: usize) -> Vec<String> {
+		let query = Query::parse(text).unwrap();
+		match database().dictionary().search_query(&query, limit) {
+			Ok(entries) => entries
+				.into_iter()
+				.map(|x| format!("{}[{}]", x.expression, x.reading))
+				.collect(),
+			Err(err) => panic!("query `{}`: {}", text, err),
+		}
+	}
+
+	#[test]
+	fn test_query() {
+		assert_eq!(
+			vec!["食べる[たべる]", "食べ物[たべもの]", "食事[しょくじ]"],
+			query("食")
+		);
+		assert_eq!(
+			vec!["食べる[たべる]", "食べ物[たべもの]"],
+			query_limit("食", 2)
+		);
+		assert_eq!(vec!["今日[きょう]", "今日[こんにち]"], query("\"今日\""));
+		assert_eq!(vec!["東京[とうきょう]"], query("?京"));
+		assert_eq!(vec!["食べる[たべる]"], query("ta*ru"));
+		assert_eq!(
+			vec!["食べる[たべる]", "食べ物[たべもの]", "高い[たかい]"],
+			query("romaji:ta")
+		);
+		assert_eq!(vec!["食べる[たべる]", "食事[しょくじ]"], query("eat"));
+		assert_eq!(vec!["食べる[たべる]", "食事[しょくじ]"], query("to eat"));
+		assert_eq!(vec!["食事[しょくじ]"], query("\"eat a meal\""));
+		assert_eq!(Vec::<String>::new(), query("\"eat to\""));
+
+		// All the clauses must match.
+		assert_eq!(
+			vec!["食べる[たべる]", "食べ物[たべもの]"],
+			query("食 romaji:ta")
+		);
+		assert_eq!(vec!["食事[しょくじ]"], query("食 meal"));
+		assert_eq!(Vec::<String>::new(), query("食 water"));
+	}
+
+	#[test]
+	fn test_query_filters() {
+		assert_eq!(vec!["食べる[たべる]"], query("食 tag:v1"));
+		assert_eq!(
+			vec!["食べ物[たべもの]", "食事[しょくじ]"],
+			query("食 -tag:v1")
+		);
+		assert_eq!(vec!["食事[しょくじ]"], query("eat tag:n"));
+		assert_eq!(vec!["飲む[のむ]", "読む[よむ]"], query("romaji:*mu tag:v5"));
+		assert_eq!(3, query("食 source:JMdict").len());
+		assert_eq!(0, query("食 -source:JMdict").len());
+		assert_eq!(
+			vec!["食べる[たべる]", "食べ物[たべもの]"],
+			query("食 freq>=4900")
+		);
+		assert_eq!(vec!["食事[しょくじ]"], query("食 freq<4900"));
+		assert_eq!(vec!["食べ物[たべもの]"], query("食 freq:4900"));
+
+		// Negated text clauses only filter the entries.
+		assert_eq!(vec!["食べる[たべる]", "食事[しょくじ]"], query("食 -food"));
+		assert_eq!(vec!["今日[きょう]"], query("今日 -こんにち"));
+		assert_eq!(vec!["食べる[たべる]"], query("食 -\"食べ物\" -meal"));
+	}
+
+	#[test]
+	fn test_search_indexes() {
+		let dictionary = database().dictionary();
+		let indexes = dictionary.search_indexes(SearchMode::Query, "食 -tag:v1");
+		assert_eq!(vec![1, 2], indexes.unwrap());
+		for text in ["tag:v1", "-食", "freq>0"].iter() {
+			match dictionary.search_indexes(SearchMode::Query, text) {
+				Err(Error::InvalidQuery(_)) => {}
+				Err(err) => panic!("query `{}`: unexpected error: {}", text, err),
+				Ok(_) => panic!("query `{}`: expected an error", text),
+			}
+		}
+	}
+
+	#[test]
+	fn test_query_errors() {
+		let dictionary = database().dictionary();
+		let check = |text: &str, expected: &str| {
+			let query = Query::parse(text).unwrap();
+			match dictionary.search_query(&query, 100) {
+				Err(err) => assert_eq!(expected, err.to_string(), "query `{}`", text),
+				Ok(_) => panic!("query `{}`: expected an error", text),
+			}
+		};
+
+		let message = "invalid query: no words to search besides filters and negated clauses";
+		check("tag:v1", message);
+		check("-食", message);
+		check("-eat tag:v1 freq>100", message);
+		check(
+			"食 tag:xyz",
+			"invalid query at position 2: unknown tag `xyz`",
+		);
+		check(
+			"食 source:xyz",
+			"invalid query at position 2: unknown source `xyz`",
+		);
+	}
+
+	/// Returns the tokens of the analyzed text as `text:base`, followed by the
+	/// unknown spans as `?text`.
+	fn analyze(text: &str) -> Vec<String> {
+		let analysis = database().dictionary().analyze(text).unwrap();
+		let tokens = analysis.sentences.iter().flat_map(|x| x.tokens.iter());
+		tokens
+			.map(|x| format!("{}:{}", x.text, x.base))
+			.chain(analysis.unknown.iter().map(|x| format!("?{}", x.text)))
+			.collect()
+	}
+
+	#[test]
+	fn test_analyze() {
+		// Longest match at each position.
+		assert_eq!(
+			vec!["日本語:日本語", "新聞:新聞", "読んだ:読む", "?の", "?を"],
+			analyze("日本語の新聞を読んだ。")
+		);
+		assert_eq!(
+			vec!["今日は:今日は", "先生:先生", "来ます:来る", "?が"],
+			analyze("今日は先生が来ます")
+		);
+		assert_eq!(
+			vec!["経済的:経済的", "食べ物:食べ物", "?な"],
+			analyze("経済的な食べ物")
+		);
+
+		// Inflections, including suru verbs listed as nouns.
+		assert_eq!(
+			vec!["猫:猫", "水:水", "飲まなかった:飲む", "?が", "?を"],
+			analyze("猫が水を飲まなかった")
+		);
+		assert_eq!(
+			vec!["東京:東京", "行って:行く", "食事した:食事する", "?へ"],
+			analyze("東京へ行って、食事した！")
+		);
+
+		// Unknown characters are merged into a single span, and other
+		// characters are skipped.
+		assert_eq!(vec!["日本:日本", "?ぬぬぬ"], analyze("ぬぬぬ日本"));
+		assert_eq!(vec!["?ぬ", "?ぬ"], analyze("ぬ abc ぬ"));
+		assert_eq!(Vec::<String>::new(), analyze("abc, 123"));
+	}
+
+	#[test]
+	fn test_analyze_tokens() {
+		let analysis = database()
+			.dictionary()
+			.analyze("猫を見た。 東京へ行って！")
+			.unwrap();
+		let sentences: Vec<_> = analysis
+			.sentences
+			.iter()
+			.map(|x| (x.start, x.end, x.text.as_str()))
+			.collect();
+		assert_eq!(
+			vec![(0, 5, "猫を見た。"), (5, 13, " 東京へ行って！")],
+			sentences
+		);
+
+		let token = &analysis.sentences[0].tokens[1];
+		assert_eq!(
+			(2, 4, "見た", "見る"),
+			(
+				token.start,
+				token.end,
+				token.text.as_str(),
+				token.base.as_str()
+			)
+		);
+		assert_eq!("みた", token.reading);
+		assert_eq!(vec!["past"], token.inflections);
+		let furigana: Vec<_> = token
+			.furigana
+			.iter()
+			.map(|x| (x.text.as_str(), x.reading.as_str()))
+			.collect();
+		assert_eq!(vec![("見", "み"), ("た", "")], furigana);
+		let entries: Vec<_> = token
+			.entries
+			.iter()
+			.map(|x| x.expression.as_str())
+			.collect();
+		assert_eq!(vec!["見る"], entries);
+		assert!(!token.entries[0].definitions[0].glossary.is_empty());
+
+		let unknown: Vec<_> = analysis.unknown.iter().map(|x| (x.start, x.end)).collect();
+		assert_eq!(vec![(1, 2), (8, 9)], unknown);
+	}
+}
@@ -0,0 +1,107 @@
+//! Compressed posting lists, the format must match `dict_data::PostingIndex`.
+//!
+//! A posting list is a strictly increasing list of non-zero values, stored as
+//! the difference from the previous value (from zero for the first value)
+//! encoded as a LEB128 varint.
+
+/// Appends the encoded `list` to `output`.
+pub fn encode(list: &[u32], output: &mut Vec<u8>) {
+	let mut last = 0;
+	for &value in list.iter() {
+		assert!(value > last, "posting list is not sorted");
+		let mut delta = value - last;
+		while delta >= 0x80 {
+			output.push((delta & 0x7F) as u8 | 0x80);
+			delta >>= 7;
+		}
+		output.push(delta as u8);
+		last = value;
+	}
+}
+
+/// Decodes a posting list from `data`, which must contain only the list.
+pub fn decode(mut data: &[u8]) -> Vec<u32> {
+	let mut output = Vec::new();
+	let mut last = 0;
+	while !data.is_empty() {
+		let mut delta = 0;
+		let mut shift = 0;
+		loop {
+			let byte = data[0];
+			data = &data[1..];
+			delta |= ((byte & 0x7F) as u32) << shift;
+			shift += 7;
+			if byte & 0x80 == 0 {
+				break;
+			}
+		}
+		last += delta;
+		output.push(last);
+	}
+	output
+}
+
+/// Returns the list for each entry of an encoded posting index section.
+pub fn decode_index(section: &[u8]) -> Vec<Vec<u32>> {
+	let uint = |offset: usize| -> usize {
+		let bytes = [
+			section[offset],
+			section[offset + 1],
+			section[offset + 2],
+			section[offset + 3],
+		];
+		u32::from_le_bytes(bytes) as usize
+	};
+	let count = uint(0);
+	let data = &section[4 + count * 8..];
+	(0..count)
+		.map(|n| {
+			let offset = uint(4 + n * 8);
+			let end = if n + 1 < count {
+				uint(4 + (n + 1) * 8)
+			} else {
+				data.len()
+			};
+			decode(&data[offset..end])
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_round_trip() {
+		let lists: &[&[u32]] = &[
+			&[],
+			&[1],
+			&[1, 2, 3, 4],
+			&[127, 128, 255, 256, 16_383, 16_384],
+			&[5, 1 << 14, 1 << 21, 1 << 28, u32::MAX - 1, u32::MAX],
+			&[u32::MAX],
+		];
+		for &list in lists.iter() {
+			let mut data = Vec::new();
+			encode(list, &mut data);
+			assert_eq!(list, decode(&data).as_slice());
+
+			let postings = dict_data::Postings::new(&data, list.len());
+			assert_eq!(list, postings.collect::<Vec<_>>().as_slice());
+		}
+	}
+
+	#[test]
+	fn test_decode_index() {
+		let lists: &[&[u32]] = &[&[1, 300], &[], &[70_000]];
+		let mut section = (lists.len() as u32).to_le_bytes().to_vec();
+		let mut data = Vec::new();
+		for list in lists.iter() {
+			section.extend_from_slice(&(data.len() as u32).to_le_bytes());
+			section.extend_from_slice(&(list.len() as u32).to_le_bytes());
+			encode(list, &mut data);
+		}
+		section.extend_from_slice(&data);
+		assert_eq!(lists, decode_index(&section).as_slice());
+	}
+}
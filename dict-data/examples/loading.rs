@@ -4,7 +4,7 @@ fn main() {
 	println!("=> Loading database ~ v{}", dict_data::version());
 
 	let start = std::time::Instant::now();
-	dict_data::load();
+	dict_data::load().unwrap();
 	println!("-> Loaded in {:?}", start.elapsed());
 	println!();
 }
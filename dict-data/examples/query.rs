@@ -10,6 +10,9 @@ use rustyline::Editor;
 pub fn main() {
 	println!("\nDatabase (version {})\n", dict_data::version());
 
+	let db = dict_data::Database::embedded().unwrap();
+	let dict = db.dictionary();
+
 	let mut rl = Editor::<()>::new();
 	loop {
 		let input = rl.readline(">> ");
@@ -20,6 +23,15 @@ pub fn main() {
 				println!();
 				println!("-> {}", line);
 				println!();
+				match dict_data::Query::parse(line).and_then(|x| dict.search_query(&x, 20)) {
+					Ok(entries) => {
+						for entry in entries {
+							println!("   {} [{}]", entry.expression, entry.reading);
+						}
+						println!();
+					}
+					Err(err) => println!("   Error: {}\n", err),
+				}
 			}
 			Err(ReadlineError::Interrupted | ReadlineError::Eof) => {
 				println!("\nBye!\n");
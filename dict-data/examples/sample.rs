@@ -6,7 +6,7 @@ fn main() {
 	println!("==============");
 
 	let start = std::time::Instant::now();
-	dict_data::load();
+	dict_data::load().unwrap();
 	println!("-> Executed in {:?}", start.elapsed());
 
 	println!("Bye!");
@@ -0,0 +1,423 @@
+use serde::Serialize;
+
+use crate::deinflect::{deinflect, Deinflection};
+use crate::dictionary::{Dictionary, Entry, SearchMode};
+use crate::error::Result;
+
+/// Maximum length in characters of a token.
+pub const MAX_TOKEN_LENGTH: usize = 12;
+
+/// Maximum number of candidate entries for a token.
+pub const MAX_TOKEN_ENTRIES: usize = 5;
+
+/// Result of `Dictionary::analyze`.
+///
+/// All positions are character offsets in the analyzed text, with `end` being
+/// exclusive.
+#[derive(Serialize)]
+pub struct Analysis {
+	pub sentences: Vec<Sentence>,
+
+	/// Runs of Japanese text without any dictionary match.
+	pub unknown: Vec<Span>,
+}
+
+/// Sentence in the analyzed text, including its final punctuation.
+#[derive(Serialize)]
+pub struct Sentence {
+	pub start: usize,
+	pub end: usize,
+	pub text: String,
+
+	/// Words found in the sentence, in order.
+	pub tokens: Vec<Token>,
+}
+
+/// Word in the analyzed text, matched to the dictionary.
+#[derive(Serialize)]
+pub struct Token {
+	pub start: usize,
+	pub end: usize,
+	pub text: String,
+
+	/// Dictionary form for the token (e.g. `食べる` for `食べた`).
+	pub base: String,
+
+	/// Hiragana reading for the token text, from the first entry.
+	pub reading: String,
+
+	/// Token text split into kanji and kana runs, with the reading for the
+	/// kanji runs.
+	pub furigana: Vec<Furigana>,
+
+	/// Inflections from the base form to the token text (e.g. `past`).
+	pub inflections: Vec<String>,
+
+	/// Candidate entries for the base form.
+	pub entries: Vec<Entry>,
+}
+
+/// Part of a token text, with the reading for kanji.
+#[derive(Serialize)]
+pub struct Furigana {
+	pub text: String,
+
+	/// Hiragana reading, empty for kana.
+	pub reading: String,
+}
+
+/// Span of text in the analyzed text.
+#[derive(Serialize)]
+pub struct Span {
+	pub start: usize,
+	pub end: usize,
+	pub text: String,
+}
+
+impl Dictionary {
+	/// Splits `text` into sentences and words, matching each word to its
+	/// dictionary entries.
+	///
+	/// Words are found by the longest match at each position, including
+	/// inflected verbs and adjectives (see `deinflect`). Characters that are
+	/// not Japanese letters are skipped.
+	pub fn analyze(&self, text: &str) -> Result<Analysis> {
+		let chars: Vec<char> = text.chars().collect();
+		let mut analysis = Analysis {
+			sentences: Vec::new(),
+			unknown: Vec::new(),
+		};
+		let mut start = 0;
+		while start < chars.len() {
+			let mut end = start;
+			while end < chars.len() && !is_sentence_end(chars[end]) {
+				end += 1;
+			}
+			while end < chars.len() && is_sentence_end(chars[end]) {
+				end += 1;
+			}
+			let sentence = self.analyze_sentence(&chars, start, end, &mut analysis.unknown)?;
+			if !sentence.text.trim().is_empty() {
+				analysis.sentences.push(sentence);
+			}
+			start = end;
+		}
+		Ok(analysis)
+	}
+
+	fn analyze_sentence(
+		&self,
+		chars: &[char],
+		start: usize,
+		end: usize,
+		unknown: &mut Vec<Span>,
+	) -> Result<Sentence> {
+		let mut tokens = Vec::new();
+		let mut position = start;
+		while position < end {
+			if !is_word_char(chars[position]) {
+				position += 1;
+				continue;
+			}
+
+			let mut run = position;
+			while run < end && run - position < MAX_TOKEN_LENGTH && is_word_char(chars[run]) {
+				run += 1;
+			}
+
+			let mut token = None;
+			for length in (1..=run - position).rev() {
+				token = self.match_token(chars, position, position + length)?;
+				if token.is_some() {
+					break;
+				}
+			}
+
+			match token {
+				Some(token) => {
+					position = token.end;
+					tokens.push(token);
+				}
+				None => {
+					match unknown.last_mut() {
+						Some(span) if span.end == position => {
+							span.end += 1;
+							span.text.push(chars[position]);
+						}
+						_ => unknown.push(Span {
+							start: position,
+							end: position + 1,
+							text: chars[position].to_string(),
+						}),
+					}
+					position += 1;
+				}
+			}
+		}
+		Ok(Sentence {
+			start,
+			end,
+			text: chars[start..end].iter().collect(),
+			tokens,
+		})
+	}
+
+	/// Returns the token for the text from `start` to `end`, if any of its
+	/// dictionary forms has an entry.
+	fn match_token(&self, chars: &[char], start: usize, end: usize) -> Result<Option<Token>> {
+		let text: String = chars[start..end].iter().collect();
+		for form in deinflect(&text) {
+			let indexes = self.match_entries(&form)?;
+			if indexes.is_empty() {
+				continue;
+			}
+			let entries = indexes
+				.into_iter()
+				.filter_map(|x| self.get_entry(x).transpose())
+				.collect::<Result<Vec<Entry>>>()?;
+			let reading = token_reading(&text, &form.text, &entries[0]);
+			let furigana = furigana(&text, &reading);
+			return Ok(Some(Token {
+				start,
+				end,
+				text,
+				base: form.text,
+				reading,
+				furigana,
+				inflections: form.reasons.iter().map(|x| x.to_string()).collect(),
+				entries,
+			}));
+		}
+		Ok(None)
+	}
+
+	/// Returns the indexes of the entries for a dictionary form, checking that
+	/// their word class matches the inflection.
+	///
+	/// Most forms are not words, so the term index is searched first, and the
+	/// entries are only loaded (without glossaries) to check their rules.
+	fn match_entries(&self, form: &Deinflection) -> Result<Vec<usize>> {
+		let mut indexes = self.filter_rules(form, &form.text)?;
+
+		// Suru verbs are listed as nouns (e.g. `勉強` for `勉強する`).
+		if indexes.is_empty() && !form.reasons.is_empty() && form.text.ends_with("する") {
+			let noun = &form.text[..form.text.len() - "する".len()];
+			if !noun.is_empty() {
+				indexes = self.filter_rules(form, noun)?;
+			}
+		}
+		Ok(indexes)
+	}
+
+	/// Returns the entries for `text` that match the rules of `form`.
+	fn filter_rules(&self, form: &Deinflection, text: &str) -> Result<Vec<usize>> {
+		let mut output = Vec::new();
+		for index in self.search_indexes(SearchMode::Exact, text)? {
+			if output.len() >= MAX_TOKEN_ENTRIES {
+				break;
+			}
+			let is_match = match self.load_entry(index, false)? {
+				Some(entry) => entry.definitions.iter().any(|x| form.matches(&x.rules)),
+				None => false,
+			};
+			if is_match {
+				output.push(index);
+			}
+		}
+		Ok(output)
+	}
+}
+
+fn is_sentence_end(chr: char) -> bool {
+	matches!(chr, '。' | '．' | '！' | '？' | '!' | '?' | '\n')
+}
+
+fn is_word_char(chr: char) -> bool {
+	kana::is_letter(chr) || kana::is_japanese_mark(chr)
+}
+
+fn is_kanji_char(chr: char) -> bool {
+	kana::is_kanji(chr) || chr == '々'
+}
+
+/// Returns the hiragana reading for a token `text` with the dictionary form
+/// `base`, from the reading of the entry.
+fn token_reading(text: &str, base: &str, entry: &Entry) -> String {
+	if !text.chars().any(is_kanji_char) || entry.reading.is_empty() {
+		return kana::to_hiragana(text);
+	}
+
+	// Replace the inflected suffix in the entry reading (e.g. `たべる` to
+	// `たべた` for `食べた`).
+	let stem = text
+		.char_indices()
+		.zip(base.chars())
+		.take_while(|((_, a), b)| a == b)
+		.last()
+		.map(|((index, chr), _)| index + chr.len_utf8())
+		.unwrap_or(0);
+	let mut reading = kana::to_hiragana(&entry.reading);
+	if base.ends_with("する") && !reading.ends_with("する") {
+		reading.push_str("する");
+	}
+	let base_suffix = kana::to_hiragana(&base[stem..]);
+	if reading.ends_with(&base_suffix) {
+		let reading_stem = &reading[..reading.len() - base_suffix.len()];
+		format!("{}{}", reading_stem, kana::to_hiragana(&text[stem..]))
+	} else {
+		reading
+	}
+}
+
+/// Splits `text` into kanji and kana runs, with the `reading` for each kanji
+/// run. If the reading cannot be aligned to the text, returns the whole text
+/// with the reading.
+fn furigana(text: &str, reading: &str) -> Vec<Furigana> {
+	if !text.chars().any(is_kanji_char) {
+		return vec![Furigana {
+			text: text.to_string(),
+			reading: String::new(),
+		}];
+	}
+
+	let mut runs: Vec<(String, bool)> = Vec::new();
+	for chr in text.chars() {
+		let kanji = is_kanji_char(chr);
+		match runs.last_mut() {
+			Some((run, run_kanji)) if *run_kanji == kanji => run.push(chr),
+			_ => runs.push((chr.to_string(), kanji)),
+		}
+	}
+
+	let chars: Vec<char> = reading.chars().collect();
+	align_runs(&runs, &chars).unwrap_or_else(|| {
+		vec![Furigana {
+			text: text.to_string(),
+			reading: reading.to_string(),
+		}]
+	})
+}
+
+/// Aligns the kanji and kana runs to a reading, where kana runs must match
+/// the reading and each kanji run has at least one character.
+fn align_runs(runs: &[(String, bool)], reading: &[char]) -> Option<Vec<Furigana>> {
+	let ((text, kanji), rest) = match runs.split_first() {
+		Some(run) => run,
+		None if reading.is_empty() => return Some(Vec::new()),
+		None => return None,
+	};
+
+	if !kanji {
+		let kana: Vec<char> = kana::to_hiragana(text).chars().collect();
+		if !reading.starts_with(&kana) {
+			return None;
+		}
+		let mut output = align_runs(rest, &reading[kana.len()..])?;
+		output.insert(
+			0,
+			Furigana {
+				text: text.clone(),
+				reading: String::new(),
+			},
+		);
+		return Some(output);
+	}
+
+	for length in 1..=reading.len() {
+		if let Some(mut output) = align_runs(rest, &reading[length..]) {
+			output.insert(
+				0,
+				Furigana {
+					text: text.clone(),
+					reading: reading[..length].iter().collect(),
+				},
+			);
+			return Some(output);
+		}
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn furigana_pairs(text: &str, reading: &str) -> Vec<(String, String)> {
+		furigana(text, reading)
+			.into_iter()
+			.map(|x| (x.text, x.reading))
+			.collect()
+	}
+
+	fn pair(text: &str, reading: &str) -> (String, String) {
+		(text.to_string(), reading.to_string())
+	}
+
+	fn entry(expression: &str, reading: &str) -> Entry {
+		Entry {
+			index: 0,
+			expression: expression.to_string(),
+			reading: reading.to_string(),
+			frequency: 0,
+			definitions: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn test_furigana() {
+		assert_eq!(vec![pair("ねこ", "")], furigana_pairs("ねこ", "ねこ"));
+		assert_eq!(
+			vec![pair("日本語", "にほんご")],
+			furigana_pairs("日本語", "にほんご")
+		);
+		assert_eq!(
+			vec![pair("食", "た"), pair("べた", "")],
+			furigana_pairs("食べた", "たべた")
+		);
+		assert_eq!(
+			vec![pair("お", ""), pair("茶", "ちゃ")],
+			furigana_pairs("お茶", "おちゃ")
+		);
+		assert_eq!(
+			vec![pair("人々", "ひとびと")],
+			furigana_pairs("人々", "ひとびと")
+		);
+
+		// Readings that do not align are kept for the whole text.
+		assert_eq!(
+			vec![pair("食べた", "のんだ")],
+			furigana_pairs("食べた", "のんだ")
+		);
+	}
+
+	#[test]
+	fn test_token_reading() {
+		let taberu = entry("食べる", "たべる");
+		assert_eq!("たべた", token_reading("食べた", "食べる", &taberu));
+		assert_eq!(
+			"たべなかった",
+			token_reading("食べなかった", "食べる", &taberu)
+		);
+		assert_eq!("たべる", token_reading("食べる", "食べる", &taberu));
+
+		// Suru verbs from nouns, and kana tokens.
+		let benkyou = entry("勉強", "べんきょう");
+		assert_eq!(
+			"べんきょうした",
+			token_reading("勉強した", "勉強する", &benkyou)
+		);
+		assert_eq!("たべた", token_reading("タベタ", "タベル", &taberu));
+	}
+
+	#[test]
+	fn test_chars() {
+		assert!(is_sentence_end('。'));
+		assert!(is_sentence_end('\n'));
+		assert!(!is_sentence_end('、'));
+		assert!(is_word_char('ー'));
+		assert!(is_word_char('々'));
+		assert!(is_word_char('猫'));
+		assert!(!is_word_char('a'));
+		assert!(!is_word_char('、'));
+	}
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::dictionary::Dictionary;
+use crate::error::{Error, Result};
+use crate::file_dict::Dict;
+use crate::file_examples::Examples;
+use crate::file_kanji::KanjiDict;
+use crate::file_meta::Meta;
+use crate::file_names::Names;
+use crate::file_strokes::Strokes;
+use crate::file_terms::Terms;
+use crate::files::{self, Container, FileData, DATABASE_FILE};
+use crate::manifest::{Manifest, MANIFEST_FILE};
+
+/// Complete dictionary database, with all the data generated by `dict-build`.
+///
+/// The database is a single file (see `DATABASE_FILE`), which can be embedded
+/// in the library (see `Database::embedded`), or loaded at runtime from a path
+/// or from memory. This allows using the library without embedding the data by
+/// enabling the `no-embed` feature.
+///
+/// The database file is memory mapped and used in place. Loading checks the
+/// section table, the entry counts and the part checksums against the database
+/// manifest, which reads the whole file once. The embedded database is part of
+/// the binary and only checked with `verify`.
+pub struct Database {
+	data: Container,
+	manifest: Manifest,
+	dictionary: Dictionary,
+	kanji: KanjiDict,
+	names: Names,
+	examples: Examples,
+	strokes: Strokes,
+}
+
+impl Database {
+	/// Opens the database file at the given path, or the `DATABASE_FILE` in
+	/// the given directory (e.g. `data/database`). The file is memory mapped
+	/// and checked against the manifest checksums.
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Database> {
+		Database::load(Database::map(path)?, true)
+	}
+
+	/// Opens the database file like `open`, but only checks the part sizes,
+	/// not the checksums. This avoids reading the whole file when loading.
+	pub fn open_unverified<P: AsRef<Path>>(path: P) -> Result<Database> {
+		Database::load(Database::map(path)?, false)
+	}
+
+	/// Loads the database from the contents of the database file, and checks
+	/// it against the manifest checksums.
+	pub fn from_bytes(bytes: Vec<u8>) -> Result<Database> {
+		Database::load(FileData::Bytes(Arc::new(bytes)), true)
+	}
+
+	/// Loads the database embedded in the library, without checking the
+	/// checksums.
+	///
+	/// On debug builds or with the `no-embed` feature, the file is instead
+	/// read from the `data/database` directory in the source tree.
+	pub fn embedded() -> Result<Database> {
+		Database::load(files::database()?, false)
+	}
+
+	fn map<P: AsRef<Path>>(path: P) -> Result<FileData> {
+		let mut path = path.as_ref().to_path_buf();
+		if path.is_dir() {
+			path.push(DATABASE_FILE);
+		}
+		FileData::map(&path).map_err(|err| match err.kind() {
+			ErrorKind::NotFound => Error::MissingFile(path.to_string_lossy().to_string()),
+			_ => Error::Io(err),
+		})
+	}
+
+	fn load(data: FileData, verify: bool) -> Result<Database> {
+		let data = Container::new(data)?;
+		let manifest = Manifest::parse(data.section_bytes(MANIFEST_FILE)?)?;
+		if verify {
+			manifest.verify(&data)?;
+		} else {
+			manifest.check_sizes(&data)?;
+		}
+
+		let meta = Meta::new(&data)?;
+		let dict = Dict::new(&data)?;
+		let terms = Terms::new(&data)?;
+		let database = Database {
+			dictionary: Dictionary::new(dict, terms, meta),
+			kanji: KanjiDict::new(&data)?,
+			names: Names::new(&data)?,
+			examples: Examples::new(&data)?,
+			strokes: Strokes::new(&data)?,
+			manifest,
+			data,
+		};
+
+		let counts = &database.manifest.counts;
+		check_count("dict", counts.entries, database.dictionary.count())?;
+		check_count("kanji", counts.kanji, database.kanji.count())?;
+		check_count("names", counts.names, database.names.count())?;
+		check_count("examples", counts.examples, database.examples.count())?;
+		check_count("strokes", counts.strokes, database.strokes.count())?;
+		Ok(database)
+	}
+
+	/// Checks the whole database file against the checksums in the manifest.
+	///
+	/// This reads the entire file. It is already done when loading, except
+	/// for `open_unverified` and `embedded`.
+	pub fn verify(&self) -> Result<()> {
+		self.manifest.verify(&self.data)
+	}
+}
+
+/// Checks the number of entries loaded from a database part against the
+/// manifest.
+fn check_count(name: &str, expected: usize, found: usize) -> Result<()> {
+	if expected != found {
+		let message = format!("expected {} entries, found {}", expected, found);
+		return Err(Error::invalid_data(name, message));
+	}
+	Ok(())
+}
+
+impl Database {
+	/// Database manifest, with the version, build time and sources of the
+	/// database.
+	pub fn info(&self) -> &Manifest {
+		&self.manifest
+	}
+
+	/// Main dictionary, including any user dictionaries.
+	pub fn dictionary(&self) -> &Dictionary {
+		&self.dictionary
+	}
+
+	/// Mutable access to the dictionary, to add user dictionaries.
+	pub fn dictionary_mut(&mut self) -> &mut Dictionary {
+		&mut self.dictionary
+	}
+
+	pub fn kanji(&self) -> &KanjiDict {
+		&self.kanji
+	}
+
+	pub fn names(&self) -> &Names {
+		&self.names
+	}
+
+	pub fn examples(&self) -> &Examples {
+		&self.examples
+	}
+
+	pub fn strokes(&self) -> &Strokes {
+		&self.strokes
+	}
+
+	/// Returns up to `limit` kanji that look similar to `character`, which are
+	/// the kanji sharing the most components with it (see
+	/// `Strokes::components`), and then the most frequent.
+	pub fn similar_kanji(&self, character: char, limit: usize) -> Result<Vec<char>> {
+		let mut shared: HashMap<char, usize> = HashMap::new();
+		for component in self.strokes.components(character)? {
+			for &other in self.strokes.with_component(component)?.iter() {
+				if other != character {
+					*shared.entry(other).or_default() += 1;
+				}
+			}
+		}
+
+		let mut similar: Vec<(char, usize)> = shared.into_iter().collect();
+		similar.sort_by_key(|&(other, count)| {
+			let rank = self.kanji.position(other).unwrap_or(usize::MAX);
+			(std::cmp::Reverse(count), rank, other)
+		});
+		Ok(similar.into_iter().take(limit).map(|x| x.0).collect())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use sha2::{Digest, Sha256};
+
+	use super::*;
+	use crate::files::tests::container_bytes;
+	use crate::manifest::{ManifestCounts, ManifestPart};
+
+	/// Contents of the `dict` part in the test database.
+	const PART: &[u8] = b"dictionary entries";
+
+	/// Returns a database file with a `dict` part, which is incomplete but has
+	/// a valid manifest.
+	fn database_bytes() -> Vec<u8> {
+		let manifest = Manifest {
+			version: files::DATABASE_VERSION,
+			created: 0,
+			parts: vec![ManifestPart {
+				name: "dict".to_string(),
+				size: PART.len() as u64,
+				sha256: format!("{:x}", Sha256::digest(PART)),
+			}],
+			counts: ManifestCounts::default(),
+			sources: Vec::new(),
+		};
+		let manifest = serde_json::to_vec(&manifest).unwrap();
+		container_bytes(&[("dict/0000", PART), (MANIFEST_FILE, &manifest)])
+	}
+
+	fn load(bytes: Vec<u8>, verify: bool) -> Result<()> {
+		Database::load(FileData::Bytes(Arc::new(bytes)), verify).map(|_| ())
+	}
+
+	#[test]
+	fn test_checksum() {
+		// The valid database passes the checks, then fails on the missing
+		// sections.
+		let bytes = database_bytes();
+		match load(bytes.clone(), true) {
+			Err(Error::MissingFile(_)) => {}
+			Err(err) => panic!("unexpected error: {}", err),
+			Ok(_) => panic!("expected an error"),
+		}
+
+		let position = bytes.windows(PART.len()).position(|x| x == PART);
+		let mut corrupted = bytes;
+		corrupted[position.unwrap()] = b'D';
+		match load(corrupted.clone(), true) {
+			Err(Error::ChecksumMismatch(part)) => assert_eq!("dict", part),
+			Err(err) => panic!("unexpected error: {}", err),
+			Ok(_) => panic!("expected a checksum mismatch"),
+		}
+		match Database::from_bytes(corrupted.clone()) {
+			Err(Error::ChecksumMismatch(part)) => assert_eq!("dict", part),
+			Err(err) => panic!("unexpected error: {}", err),
+			Ok(_) => panic!("expected a checksum mismatch"),
+		}
+
+		// Without verification, only the size is checked.
+		match load(corrupted, false) {
+			Err(Error::MissingFile(_)) => {}
+			Err(err) => panic!("unexpected error: {}", err),
+			Ok(_) => panic!("expected an error"),
+		}
+	}
+}
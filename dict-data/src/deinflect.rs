@@ -0,0 +1,374 @@
+//! Rule based deinflection of Japanese verbs and adjectives.
+//!
+//! Each rule replaces an inflected suffix with the suffix of a less inflected
+//! form (e.g. `かった` to `い`). Rules are applied repeatedly, so that chained
+//! inflections (e.g. `食べられなかった`) can be reduced to the dictionary form.
+//!
+//! Each form has a word class, which restricts the rules that can be applied
+//! to it and the dictionary entries it can match (see `Deinflection::matches`).
+
+/// Ichidan verb (`v1`).
+const V1: u32 = 1 << 0;
+
+/// Godan verb (`v5*`).
+const V5: u32 = 1 << 1;
+
+/// Suru verb (`vs*`).
+const VS: u32 = 1 << 2;
+
+/// Kuru verb (`vk`).
+const VK: u32 = 1 << 3;
+
+/// I-adjective (`adj-i`), also used for the `ない` and `たい` forms.
+const ADJ_I: u32 = 1 << 4;
+
+/// Polite `ます` form.
+const MASU: u32 = 1 << 5;
+
+/// `て` form, for the `ている` progressive.
+const TE: u32 = 1 << 6;
+
+/// Forms that are not inflected further (e.g. the past `た` form).
+const FINAL: u32 = 1 << 7;
+
+/// Class of the input text, which can be any form.
+const ANY: u32 = u32::MAX;
+
+/// Maximum number of rules applied to an input.
+const MAX_DEPTH: usize = 6;
+
+/// Possible dictionary form for an inflected text, see `deinflect`.
+#[derive(Clone, Debug)]
+pub struct Deinflection {
+	/// Dictionary form.
+	pub text: String,
+
+	/// Names of the inflections, from the dictionary form to the input (e.g.
+	/// `negative` and `past` for `食べなかった`).
+	pub reasons: Vec<&'static str>,
+
+	class: u32,
+}
+
+impl Deinflection {
+	/// Returns true if an entry with the deinflection rules `rules` (e.g. `v1`,
+	/// `v5k` or `adj-i`) can be the dictionary form. Any entry can match the
+	/// input itself.
+	pub fn matches<S: AsRef<str>>(&self, rules: &[S]) -> bool {
+		if self.reasons.is_empty() {
+			return true;
+		}
+		rules.iter().any(|rule| {
+			let class = match rule.as_ref() {
+				"v1" => V1,
+				"vk" => VK,
+				"adj-i" => ADJ_I,
+				rule if rule.starts_with("v5") => V5,
+				rule if rule.starts_with("vs") => VS,
+				_ => 0,
+			};
+			self.class & class != 0
+		})
+	}
+}
+
+struct Rule {
+	/// Inflected suffix.
+	from: String,
+
+	/// Suffix of the less inflected form.
+	to: String,
+
+	/// Classes of the inflected form for which the rule applies.
+	form: u32,
+
+	/// Class of the less inflected form.
+	class: u32,
+
+	reason: &'static str,
+}
+
+lazy_static! {
+	static ref RULES: Vec<Rule> = build_rules();
+}
+
+/// Returns the possible dictionary forms for `text`, starting with `text`
+/// itself. The forms must be checked against the dictionary, as most will not
+/// be actual words.
+pub fn deinflect(text: &str) -> Vec<Deinflection> {
+	let mut output = vec![Deinflection {
+		text: text.to_string(),
+		reasons: Vec::new(),
+		class: ANY,
+	}];
+	let mut next = 0;
+	while next < output.len() {
+		let current = output[next].clone();
+		next += 1;
+		if current.reasons.len() >= MAX_DEPTH {
+			continue;
+		}
+		for rule in RULES.iter() {
+			if current.class & rule.form == 0 || !current.text.ends_with(&rule.from) {
+				continue;
+			}
+			let stem = &current.text[..current.text.len() - rule.from.len()];
+			let mut reasons = current.reasons.clone();
+			reasons.insert(0, rule.reason);
+			output.push(Deinflection {
+				text: format!("{}{}", stem, rule.to),
+				reasons,
+				class: rule.class,
+			});
+		}
+	}
+	output
+}
+
+// spell-checker: disable
+
+/// Godan verb endings, with the dictionary `u` ending, the `i`, `a`, `e` and
+/// `o` stems, and the `て` and `た` forms.
+const GODAN: &[[&str; 7]] = &[
+	["う", "い", "わ", "え", "お", "って", "った"],
+	["く", "き", "か", "け", "こ", "いて", "いた"],
+	["ぐ", "ぎ", "が", "げ", "ご", "いで", "いだ"],
+	["す", "し", "さ", "せ", "そ", "して", "した"],
+	["つ", "ち", "た", "て", "と", "って", "った"],
+	["ぬ", "に", "な", "ね", "の", "んで", "んだ"],
+	["ぶ", "び", "ば", "べ", "ぼ", "んで", "んだ"],
+	["む", "み", "ま", "め", "も", "んで", "んだ"],
+	["る", "り", "ら", "れ", "ろ", "って", "った"],
+];
+
+/// Verb forms, with the inflected suffix for ichidan, suru and kuru verbs,
+/// the godan stem and suffix, the class of the inflected form and the reason.
+///
+/// The godan stem is the index in `GODAN` (1 to 4 for the `i`, `a`, `e` and
+/// `o` stems, 5 and 6 for the `て` and `た` forms, where the suffix follows
+/// the form).
+const VERB_FORMS: &[(&str, &str, &str, usize, &str, u32, &str)] = &[
+	("ます", "します", "きます", 1, "ます", MASU, "polite"),
+	("たい", "したい", "きたい", 1, "たい", ADJ_I, "want"),
+	("ない", "しない", "こない", 2, "ない", ADJ_I, "negative"),
+	("ず", "せず", "こず", 2, "ず", FINAL, "negative"),
+	("られる", "される", "こられる", 2, "れる", V1, "passive"),
+	("させる", "させる", "こさせる", 2, "せる", V1, "causative"),
+	("られる", "できる", "こられる", 3, "る", V1, "potential"),
+	("れる", "", "これる", 3, "る", V1, "potential"),
+	("れば", "すれば", "くれば", 3, "ば", FINAL, "conditional"),
+	("ろ", "しろ", "こい", 3, "", FINAL, "imperative"),
+	("よう", "しよう", "こよう", 4, "う", FINAL, "volitional"),
+	("て", "して", "きて", 5, "", TE | FINAL, "te"),
+	("た", "した", "きた", 6, "", FINAL, "past"),
+	("たら", "したら", "きたら", 6, "ら", FINAL, "conditional"),
+];
+
+fn build_rules() -> Vec<Rule> {
+	let mut rules = Vec::new();
+	let mut add = |from: String, to: &str, form: u32, class: u32, reason: &'static str| {
+		if !from.is_empty() {
+			rules.push(Rule {
+				from,
+				to: to.to_string(),
+				form,
+				class,
+				reason,
+			});
+		}
+	};
+
+	for &(v1, vs, vk, stem, suffix, form, reason) in VERB_FORMS.iter() {
+		add(v1.to_string(), "る", form, V1, reason);
+		add(vs.to_string(), "する", form, VS, reason);
+		add(vk.to_string(), "くる", form, VK, reason);
+		if !vk.is_empty() {
+			let kanji = vk.replacen(['き', 'こ', 'く'], "来", 1);
+			add(kanji, "来る", form, VK, reason);
+		}
+		for endings in GODAN.iter() {
+			add(
+				format!("{}{}", endings[stem], suffix),
+				endings[0],
+				form,
+				V5,
+				reason,
+			);
+		}
+	}
+
+	// Irregular godan forms.
+	add("行って".to_string(), "行く", TE | FINAL, V5, "te");
+	add("行った".to_string(), "行く", FINAL, V5, "past");
+	add("いって".to_string(), "いく", TE | FINAL, V5, "te");
+	add("いった".to_string(), "いく", FINAL, V5, "past");
+
+	// Polite forms.
+	add("ました".to_string(), "ます", FINAL, MASU, "past");
+	add("ません".to_string(), "ます", FINAL, MASU, "negative");
+	add(
+		"ませんでした".to_string(),
+		"ます",
+		FINAL,
+		MASU,
+		"negative past",
+	);
+	add("ましょう".to_string(), "ます", FINAL, MASU, "volitional");
+
+	// Progressive, which is an ichidan verb after the `て` form.
+	add("ている".to_string(), "て", V1, TE, "progressive");
+	add("でいる".to_string(), "で", V1, TE, "progressive");
+	add("てる".to_string(), "て", V1, TE, "progressive");
+	add("でる".to_string(), "で", V1, TE, "progressive");
+
+	// I-adjectives, including the `ない` and `たい` forms.
+	add("かった".to_string(), "い", FINAL, ADJ_I, "past");
+	add("くない".to_string(), "い", ADJ_I, ADJ_I, "negative");
+	add("くて".to_string(), "い", FINAL, ADJ_I, "te");
+	add("ければ".to_string(), "い", FINAL, ADJ_I, "conditional");
+	add("かったら".to_string(), "い", FINAL, ADJ_I, "conditional");
+	add("く".to_string(), "い", FINAL, ADJ_I, "adverb");
+	add("さ".to_string(), "い", FINAL, ADJ_I, "noun");
+
+	rules
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Checks that `text` deinflects to `base` with the `reasons`, for an
+	/// entry with the `rule`.
+	fn check(text: &str, base: &str, reasons: &[&str], rule: &str) {
+		let forms = deinflect(text);
+		let found = forms
+			.iter()
+			.any(|x| x.text == base && x.reasons == reasons && x.matches(&[rule]));
+		assert!(
+			found,
+			"`{}` to `{}` {:?} ({}) not found in {:?}",
+			text,
+			base,
+			reasons,
+			rule,
+			forms
+				.iter()
+				.map(|x| format!("{} {:?}", x.text, x.reasons))
+				.collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn test_input() {
+		let forms = deinflect("食べる");
+		assert_eq!("食べる", forms[0].text);
+		assert!(forms[0].reasons.is_empty());
+		assert!(forms[0].matches(&["n"]));
+		assert!(forms[0].matches::<&str>(&[]));
+	}
+
+	#[test]
+	fn test_ichidan() {
+		check("食べた", "食べる", &["past"], "v1");
+		check("食べて", "食べる", &["te"], "v1");
+		check("食べない", "食べる", &["negative"], "v1");
+		check("食べなかった", "食べる", &["negative", "past"], "v1");
+		check("食べます", "食べる", &["polite"], "v1");
+		check("食べられる", "食べる", &["passive"], "v1");
+		check("食べられる", "食べる", &["potential"], "v1");
+		check("食べさせる", "食べる", &["causative"], "v1");
+		check("食べれば", "食べる", &["conditional"], "v1");
+		check("食べろ", "食べる", &["imperative"], "v1");
+		check("食べよう", "食べる", &["volitional"], "v1");
+		check("食べたい", "食べる", &["want"], "v1");
+		check("見たら", "見る", &["conditional"], "v1");
+	}
+
+	#[test]
+	fn test_godan() {
+		check("書いた", "書く", &["past"], "v5k");
+		check("泳いで", "泳ぐ", &["te"], "v5g");
+		check("話した", "話す", &["past"], "v5s");
+		check("待って", "待つ", &["te"], "v5t");
+		check("死んだ", "死ぬ", &["past"], "v5n");
+		check("遊んで", "遊ぶ", &["te"], "v5b");
+		check("飲まない", "飲む", &["negative"], "v5m");
+		check("帰ります", "帰る", &["polite"], "v5r");
+		check("買わなかった", "買う", &["negative", "past"], "v5u");
+		check("読める", "読む", &["potential"], "v5m");
+		check("読まれる", "読む", &["passive"], "v5m");
+		check("読もう", "読む", &["volitional"], "v5m");
+		check("読めば", "読む", &["conditional"], "v5m");
+		check("読まず", "読む", &["negative"], "v5m");
+	}
+
+	#[test]
+	fn test_irregular() {
+		check("行って", "行く", &["te"], "v5k-s");
+		check("行った", "行く", &["past"], "v5k-s");
+		check("いって", "いく", &["te"], "v5k-s");
+		check("勉強した", "勉強する", &["past"], "vs");
+		check("勉強しない", "勉強する", &["negative"], "vs");
+		check("勉強できる", "勉強する", &["potential"], "vs");
+		check("勉強させる", "勉強する", &["causative"], "vs");
+		check("来た", "来る", &["past"], "vk");
+		check("来なかった", "来る", &["negative", "past"], "vk");
+		check("こない", "くる", &["negative"], "vk");
+		check("きます", "くる", &["polite"], "vk");
+	}
+
+	#[test]
+	fn test_polite() {
+		check("食べました", "食べる", &["polite", "past"], "v1");
+		check("食べません", "食べる", &["polite", "negative"], "v1");
+		check(
+			"食べませんでした",
+			"食べる",
+			&["polite", "negative past"],
+			"v1",
+		);
+		check("飲みましょう", "飲む", &["polite", "volitional"], "v5m");
+	}
+
+	#[test]
+	fn test_progressive() {
+		check("食べている", "食べる", &["te", "progressive"], "v1");
+		check("読んでいる", "読む", &["te", "progressive"], "v5m");
+		check("食べてる", "食べる", &["te", "progressive"], "v1");
+		check("食べていた", "食べる", &["te", "progressive", "past"], "v1");
+		check(
+			"食べていません",
+			"食べる",
+			&["te", "progressive", "polite", "negative"],
+			"v1",
+		);
+	}
+
+	#[test]
+	fn test_adjective() {
+		check("高かった", "高い", &["past"], "adj-i");
+		check("高くない", "高い", &["negative"], "adj-i");
+		check("高くなかった", "高い", &["negative", "past"], "adj-i");
+		check("高くて", "高い", &["te"], "adj-i");
+		check("高ければ", "高い", &["conditional"], "adj-i");
+		check("高く", "高い", &["adverb"], "adj-i");
+		check("高さ", "高い", &["noun"], "adj-i");
+		check("食べたかった", "食べる", &["want", "past"], "v1");
+	}
+
+	#[test]
+	fn test_classes() {
+		// Forms only match the word class of the rule.
+		let forms = deinflect("食べた");
+		let form = forms.iter().find(|x| x.text == "食べる").unwrap();
+		assert!(form.matches(&["v1"]));
+		assert!(!form.matches(&["v5r", "adj-i"]));
+		assert!(!form.matches::<&str>(&[]));
+
+		// Final forms are not inflected further.
+		assert!(!deinflect("食べたかった")
+			.iter()
+			.any(|x| x.reasons == ["past", "past"]));
+		assert!(deinflect("食べたた").iter().all(|x| x.text != "食べる"));
+	}
+}
@@ -0,0 +1,661 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::file_dict::Dict;
+use crate::file_meta::Meta;
+use crate::file_terms::Terms;
+use crate::page_cache::CacheStats;
+use crate::pattern::{has_wildcards, Pattern};
+use crate::query::{self, Query, Search};
+use crate::term_index;
+use crate::user_dict::{english_words, UserDict};
+
+/// Searchable dictionary, combining the main dictionary (the `dict`, `text`
+/// and `meta` database parts) with any number of user dictionaries.
+///
+/// Entries are identified by an index, where the main dictionary entries come
+/// first (sorted by frequency), followed by the entries of each user
+/// dictionary in the order they were added.
+///
+/// Search results list the user dictionary entries before the main entries,
+/// so that custom terms take precedence over the main dictionary.
+pub struct Dictionary {
+	dict: Dict,
+	terms: Terms,
+	meta: Meta,
+	user: Vec<UserDict>,
+}
+
+impl Dictionary {
+	pub fn new(dict: Dict, terms: Terms, meta: Meta) -> Dictionary {
+		Dictionary {
+			dict,
+			terms,
+			meta,
+			user: Vec::new(),
+		}
+	}
+
+	/// Adds a user dictionary on top of the existing entries.
+	pub fn add_user_dict(&mut self, dict: UserDict) {
+		self.user.push(dict);
+	}
+
+	/// List of the loaded user dictionaries.
+	pub fn user_dicts(&self) -> &[UserDict] {
+		&self.user
+	}
+
+	/// Changes the maximum size in bytes of the cache for the main dictionary
+	/// pages. A zero size disables caching.
+	pub fn set_cache_size(&mut self, size: usize) {
+		self.dict.set_cache_size(size);
+	}
+
+	/// Returns the statistics for the main dictionary page cache.
+	pub fn cache_stats(&self) -> CacheStats {
+		self.dict.cache_stats()
+	}
+}
+
+impl Dictionary {
+	/// Total number of entries, including user dictionaries.
+	pub fn count(&self) -> usize {
+		self.dict.count() + self.user.iter().map(|x| x.count()).sum::<usize>()
+	}
+
+	/// Returns an entry by its index, or `None` if there is no such entry.
+	pub fn get_entry(&self, index: usize) -> Result<Option<Entry>> {
+		self.load_entry(index, true)
+	}
+
+	/// Like `get_entry`, but leaves the main dictionary glossaries empty unless
+	/// `glossary` is true (e.g. to check the entry rules before loading it).
+	pub(crate) fn load_entry(&self, index: usize, glossary: bool) -> Result<Option<Entry>> {
+		if index < self.dict.count() {
+			return Ok(Some(self.load_main_entry(index, glossary)?));
+		}
+
+		let mut offset = self.dict.count();
+		for dict in self.user.iter() {
+			if index < offset + dict.count() {
+				return Ok(Some(get_user_entry(dict, offset, index - offset)));
+			}
+			offset += dict.count();
+		}
+		Ok(None)
+	}
+
+	/// Searches entries with an expression or reading equal to `text`.
+	pub fn search_exact(&self, text: &str) -> Result<Vec<Entry>> {
+		self.search(
+			|terms| terms.search_exact(text),
+			|dict| dict.search_exact(text),
+			usize::MAX,
+		)
+	}
+
+	/// Searches entries with an expression or reading starting with `prefix`.
+	pub fn search_prefix(&self, prefix: &str, limit: usize) -> Result<Vec<Entry>> {
+		self.search(
+			|terms| terms.search_prefix(prefix),
+			|dict| dict.search_prefix(prefix),
+			limit,
+		)
+	}
+
+	/// Searches entries by their romaji reading, starting with `input`. The
+	/// input can be in romaji or kana.
+	pub fn search_romaji(&self, input: &str, limit: usize) -> Result<Vec<Entry>> {
+		let key = kana::to_search_key(input);
+		self.search(
+			|terms| terms.search_romaji(&key),
+			|dict| dict.search_romaji(&key),
+			limit,
+		)
+	}
+
+	/// Searches entries with definitions containing all the English words in
+	/// `text`.
+	pub fn search_english(&self, text: &str, limit: usize) -> Result<Vec<Entry>> {
+		let words = english_words(text);
+		if words.is_empty() {
+			return Ok(Vec::new());
+		}
+		self.search(
+			|terms| terms.search_english(&words),
+			|dict| dict.search_english(&words),
+			limit,
+		)
+	}
+
+	/// Searches entries with an expression or reading containing all the
+	/// Japanese characters in `text`, in any order.
+	pub fn search_chars(&self, text: &str, limit: usize) -> Result<Vec<Entry>> {
+		if text.trim().is_empty() {
+			return Ok(Vec::new());
+		}
+		self.search(
+			|terms| terms.search_chars(text),
+			|dict| dict.search_chars(text),
+			limit,
+		)
+	}
+
+	/// Searches entries with an expression or reading within `distance` edits
+	/// (inserted, deleted or replaced characters) of `text`. The maximum
+	/// distance is `MAX_FUZZY_DISTANCE`.
+	pub fn search_fuzzy(&self, text: &str, distance: u32, limit: usize) -> Result<Vec<Entry>> {
+		let automaton = term_index::fuzzy(text, distance)?;
+		self.search(
+			|terms| terms.search_terms_matching(&automaton),
+			|dict| dict.search_terms_matching(&automaton),
+			limit,
+		)
+	}
+
+	/// Searches entries with an expression or reading fully matching the
+	/// regular expression `pattern` (e.g. `東.*`).
+	pub fn search_regex(&self, pattern: &str, limit: usize) -> Result<Vec<Entry>> {
+		let automaton = term_index::regex(pattern)?;
+		self.search(
+			|terms| terms.search_terms_matching(&automaton),
+			|dict| dict.search_terms_matching(&automaton),
+			limit,
+		)
+	}
+
+	/// Searches entries with an expression or reading matching the wildcard
+	/// `pattern` (e.g. `東*`, `?きょう` or `[漢字]語`). Patterns with only
+	/// ASCII characters match the romaji search keys instead (e.g. `ta*ru`).
+	/// Patterns without any literal character are invalid.
+	///
+	/// See `Pattern` for the syntax.
+	pub fn search_pattern(&self, pattern: &str, limit: usize) -> Result<Vec<Entry>> {
+		let pattern = Pattern::parse(pattern)?;
+		self.search(
+			|terms| terms.search_pattern(&self.dict, &pattern),
+			|dict| dict.search_pattern(&pattern),
+			limit,
+		)
+	}
+
+	/// Searches entries matching a structured `query` (see `Query` for the
+	/// syntax), with user dictionary entries first.
+	///
+	/// The tags and sources in the query must exist in the dictionary, or this
+	/// returns an `Error::QuerySyntax` with their position. Queries with only
+	/// filters or negated clauses (e.g. `tag:v5 -食`) return an
+	/// `Error::InvalidQuery`.
+	pub fn search_query(&self, query: &Query, limit: usize) -> Result<Vec<Entry>> {
+		self.find_query(query, limit)?
+			.into_iter()
+			.filter_map(|x| self.get_entry(x).transpose())
+			.collect()
+	}
+
+	/// Returns the indexes of the entries for `search_query`. The entries from
+	/// the main dictionary are only loaded if a clause cannot be checked with
+	/// the index searches and frequencies, and the glossaries only if a clause
+	/// needs them.
+	fn find_query(&self, query: &Query, limit: usize) -> Result<Vec<usize>> {
+		query.check_tags(|name| {
+			self.meta.has_tag(name) || self.user.iter().any(|x| x.has_tag(name))
+		})?;
+		query.check_sources(|name| {
+			let sources = self.meta.sources().iter().map(|x| x.as_str());
+			let mut sources = sources.chain(self.user.iter().map(|x| x.source()));
+			sources.any(|x| query::is_source(x, name))
+		})?;
+
+		// Entries are searched using the index for each text clause, and then
+		// only those are checked for the filters and negated clauses. Queries
+		// without a text clause would need to check every entry.
+		let searches = query.searches();
+		if searches.is_empty() {
+			let message = "no words to search besides filters and negated clauses";
+			return Err(Error::InvalidQuery(message.to_string()));
+		}
+		let mut output = Vec::new();
+
+		let mut offset = self.dict.count();
+		for dict in self.user.iter() {
+			let lists = searches.iter().map(|x| search_user(dict, x)).collect();
+			for index in intersect_lists(lists) {
+				if output.len() >= limit {
+					return Ok(output);
+				}
+				let entry = get_user_entry(dict, offset, index);
+				if query.matches_frequency(entry.frequency) && query.matches(&entry) {
+					output.push(entry.index);
+				}
+			}
+			offset += dict.count();
+		}
+
+		let mut lists = Vec::with_capacity(searches.len());
+		for search in searches.iter() {
+			lists.push(self.search_main(search)?);
+		}
+		let candidates = intersect_lists(lists);
+		let load = query.needs_entry();
+		let glossary = query.needs_glossary();
+		for index in candidates {
+			if output.len() >= limit {
+				break;
+			}
+			if !query.matches_frequency(self.dict.frequency(index)?) {
+				continue;
+			}
+			if !load || query.matches(&self.load_main_entry(index, glossary)?) {
+				output.push(index);
+			}
+		}
+		Ok(output)
+	}
+
+	fn search_main(&self, search: &Search) -> Result<Vec<usize>> {
+		match *search {
+			Search::Prefix(text) => self.terms.search_prefix(text),
+			Search::Exact(text) => self.terms.search_exact(text),
+			Search::Pattern(pattern) => self.terms.search_pattern(&self.dict, pattern),
+			Search::English(words) => self.terms.search_english(words),
+			Search::Romaji(key) => self.terms.search_romaji(key),
+		}
+	}
+
+	/// Returns the indexes of all the entries found by a search, in the same
+	/// order as the respective `search_*` method. Use `get_entry` to load the
+	/// entries (e.g. to paginate the results).
+	///
+	/// Like `search_query`, this returns an `Error::InvalidQuery` for queries
+	/// with only filters or negated clauses, which would have to check every
+	/// entry.
+	pub fn search_indexes(&self, mode: SearchMode, text: &str) -> Result<Vec<usize>> {
+		match mode {
+			SearchMode::Auto => {
+				let modes = if has_wildcards(text) {
+					vec![SearchMode::Pattern]
+				} else if query::has_japanese(text) {
+					vec![SearchMode::Exact, SearchMode::Prefix]
+				} else {
+					vec![SearchMode::Romaji, SearchMode::English]
+				};
+				let mut output = Vec::new();
+				let mut found = HashSet::new();
+				for mode in modes {
+					let indexes = self.search_indexes(mode, text)?;
+					output.extend(indexes.into_iter().filter(|&x| found.insert(x)));
+				}
+				Ok(output)
+			}
+			SearchMode::Exact => self.find(
+				|terms| terms.search_exact(text),
+				|dict| dict.search_exact(text),
+			),
+			SearchMode::Prefix => self.find(
+				|terms| terms.search_prefix(text),
+				|dict| dict.search_prefix(text),
+			),
+			SearchMode::Romaji => {
+				let key = kana::to_search_key(text);
+				self.find(
+					|terms| terms.search_romaji(&key),
+					|dict| dict.search_romaji(&key),
+				)
+			}
+			SearchMode::English => {
+				let words = english_words(text);
+				if words.is_empty() {
+					return Ok(Vec::new());
+				}
+				self.find(
+					|terms| terms.search_english(&words),
+					|dict| dict.search_english(&words),
+				)
+			}
+			SearchMode::Chars => {
+				if text.trim().is_empty() {
+					return Ok(Vec::new());
+				}
+				self.find(
+					|terms| terms.search_chars(text),
+					|dict| dict.search_chars(text),
+				)
+			}
+			SearchMode::Pattern => {
+				let pattern = Pattern::parse(text)?;
+				self.find(
+					|terms| terms.search_pattern(&self.dict, &pattern),
+					|dict| dict.search_pattern(&pattern),
+				)
+			}
+			SearchMode::Query => {
+				// Queries are searched by their text clauses, so the result
+				// is bounded by those searches even without a limit.
+				let query = Query::parse(text)?;
+				self.find_query(&query, usize::MAX)
+			}
+		}
+	}
+
+	/// Searches the romaji search keys similar to `input`, for typo tolerant
+	/// searches (e.g. "did you mean" suggestions). The input can be in romaji
+	/// or kana.
+	///
+	/// Returns up to `limit` keys ranked by their `kana::romaji_distance` from
+	/// the input, then by the frequency of their entries. The entries are not
+	/// loaded, see `FuzzyMatch::indexes`.
+	pub fn search_romaji_fuzzy(&self, input: &str, limit: usize) -> Result<Vec<FuzzyMatch>> {
+		// Converting to kana first normalizes the romanization (e.g. `si` and
+		// `tu` to `shi` and `tsu`).
+		let key = kana::to_search_key(kana::to_hiragana(input));
+		if key.is_empty() || limit == 0 {
+			return Ok(Vec::new());
+		}
+
+		// The candidates are the keys within a number of edits of the input,
+		// which are then filtered and ranked by the weighted distance.
+		let edits = if key.len() <= SHORT_KEY_LENGTH {
+			1
+		} else {
+			term_index::MAX_FUZZY_DISTANCE
+		};
+		let automaton = term_index::fuzzy(&key, edits)?;
+
+		let mut candidates: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+		let mut offset = self.dict.count();
+		for dict in self.user.iter() {
+			for (key, list) in dict.keys_matching(&automaton) {
+				let list = list.into_iter().map(|x| offset + x);
+				candidates.entry(key).or_default().extend(list);
+			}
+			offset += dict.count();
+		}
+		for (key, list) in self.terms.keys_matching(&automaton)? {
+			candidates.entry(key).or_default().extend(list);
+		}
+
+		let max_distance = max_fuzzy_distance(&key);
+		let mut candidates: Vec<_> = candidates
+			.into_iter()
+			.map(|(candidate, list)| (kana::romaji_distance(&key, &candidate), candidate, list))
+			.filter(|x| x.0 <= max_distance)
+			.collect();
+		candidates.sort_by(|(distance_a, _, list_a), (distance_b, _, list_b)| {
+			let by_frequency = list_a.iter().min().cmp(&list_b.iter().min());
+			distance_a
+				.partial_cmp(distance_b)
+				.unwrap()
+				.then(by_frequency)
+		});
+
+		let output = candidates
+			.into_iter()
+			.take(limit)
+			.map(|(distance, key, list)| FuzzyMatch {
+				key,
+				distance,
+				indexes: list,
+			})
+			.collect();
+		Ok(output)
+	}
+
+	fn search<F, U>(&self, search_main: F, search_user: U, limit: usize) -> Result<Vec<Entry>>
+	where
+		F: Fn(&Terms) -> Result<Vec<usize>>,
+		U: Fn(&UserDict) -> Vec<usize>,
+	{
+		self.find(search_main, search_user)?
+			.into_iter()
+			.take(limit)
+			.filter_map(|x| self.get_entry(x).transpose())
+			.collect()
+	}
+
+	/// Returns the indexes for a search in the main and user dictionaries,
+	/// with the user dictionary entries first.
+	fn find<F, U>(&self, search_main: F, search_user: U) -> Result<Vec<usize>>
+	where
+		F: Fn(&Terms) -> Result<Vec<usize>>,
+		U: Fn(&UserDict) -> Vec<usize>,
+	{
+		let mut output = Vec::new();
+		let mut offset = self.dict.count();
+		for dict in self.user.iter() {
+			output.extend(search_user(dict).into_iter().map(|x| offset + x));
+			offset += dict.count();
+		}
+		output.extend(search_main(&self.terms)?);
+		Ok(output)
+	}
+
+	/// Loads an entry from the main dictionary, leaving the glossaries empty
+	/// unless `glossary` is true since those are the most expensive to load.
+	fn load_main_entry(&self, index: usize, glossary: bool) -> Result<Entry> {
+		let entry = self.dict.get_entry(index)?;
+		let tag_names = |tags: &Vec<usize>| -> Vec<String> {
+			tags.iter()
+				.filter_map(|&x| self.meta.tag(x))
+				.map(|x| x.name.clone())
+				.collect()
+		};
+
+		let mut definitions = Vec::with_capacity(entry.definitions.len());
+		for def in entry.definitions.iter() {
+			definitions.push(Definition {
+				source: self.meta.source(def.source).to_string(),
+				glossary: if glossary {
+					def.text
+						.iter()
+						.map(|&x| self.terms.glossary(x))
+						.collect::<Result<_>>()?
+				} else {
+					Vec::new()
+				},
+				tags: tag_names(&def.tags_for_term)
+					.into_iter()
+					.chain(tag_names(&def.tags_for_text))
+					.collect(),
+				rules: tag_names(&def.rules),
+			});
+		}
+
+		Ok(Entry {
+			index,
+			expression: self.terms.term(entry.expression)?,
+			reading: self.terms.term(entry.reading)?,
+			frequency: entry.frequency,
+			definitions,
+		})
+	}
+}
+
+/// Maximum length of a search key considered short for fuzzy searches, which
+/// are limited to a single edit.
+const SHORT_KEY_LENGTH: usize = 3;
+
+/// Maximum romaji distance for fuzzy search candidates, allowing more edits
+/// for longer keys.
+fn max_fuzzy_distance(key: &str) -> f32 {
+	match key.len() {
+		0..=SHORT_KEY_LENGTH => 0.5,
+		4..=6 => 1.0,
+		_ => 2.0,
+	}
+}
+
+fn search_user(dict: &UserDict, search: &Search) -> Vec<usize> {
+	match *search {
+		Search::Prefix(text) => dict.search_prefix(text),
+		Search::Exact(text) => dict.search_exact(text),
+		Search::Pattern(pattern) => dict.search_pattern(pattern),
+		Search::English(words) => dict.search_english(words),
+		Search::Romaji(key) => dict.search_romaji(key),
+	}
+}
+
+/// Returns the entries in all the `lists`, sorted.
+///
+/// The lists are sorted and then merged, starting from the shortest list to
+/// keep the intermediate result small.
+fn intersect_lists(lists: Vec<Vec<usize>>) -> Vec<usize> {
+	let mut lists: Vec<Vec<usize>> = lists
+		.into_iter()
+		.map(|mut list| {
+			list.sort();
+			list.dedup();
+			list
+		})
+		.collect();
+	lists.sort_by_key(|x| x.len());
+
+	let mut lists = lists.into_iter();
+	let mut output = lists.next().unwrap_or_default();
+	for list in lists {
+		let mut other = list.into_iter().peekable();
+		output.retain(|&x| {
+			while other.peek().is_some_and(|&y| y < x) {
+				other.next();
+			}
+			other.peek() == Some(&x)
+		});
+	}
+	output
+}
+
+fn get_user_entry(dict: &UserDict, offset: usize, index: usize) -> Entry {
+	let entry = dict.entry(index);
+	Entry {
+		index: offset + index,
+		expression: entry.expression.clone(),
+		reading: entry.reading.clone(),
+		frequency: 0,
+		definitions: vec![Definition {
+			source: dict.source().to_string(),
+			glossary: entry.glosses.clone(),
+			tags: entry.tags.clone(),
+			rules: Vec::new(),
+		}],
+	}
+}
+
+/// Kind of search for `Dictionary::search_indexes`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SearchMode {
+	/// Uses `Pattern` if the text has wildcards. Otherwise, Japanese text uses
+	/// `Exact` and then `Prefix`, and any other text uses `Romaji` and then
+	/// `English`.
+	Auto,
+
+	/// See `Dictionary::search_exact`.
+	Exact,
+
+	/// See `Dictionary::search_prefix`.
+	Prefix,
+
+	/// See `Dictionary::search_romaji`.
+	Romaji,
+
+	/// See `Dictionary::search_english`.
+	English,
+
+	/// See `Dictionary::search_chars`.
+	Chars,
+
+	/// See `Dictionary::search_pattern`.
+	Pattern,
+
+	/// See `Dictionary::search_query`, with the text parsed by `Query::parse`.
+	Query,
+}
+
+/// Dictionary entry, from either the main or a user dictionary.
+#[derive(Serialize)]
+pub struct Entry {
+	/// Index of the entry in the `Dictionary`.
+	pub index: usize,
+
+	pub expression: String,
+
+	/// Kana reading for the entry, empty if not available.
+	pub reading: String,
+
+	/// Frequency of the entry in the reference corpus. This is zero for user
+	/// dictionary entries.
+	pub frequency: u32,
+
+	pub definitions: Vec<Definition>,
+}
+
+impl Entry {
+	/// Returns the romaji for the entry reading, or for the expression if the
+	/// entry has no reading.
+	pub fn romaji(&self) -> String {
+		if !self.reading.is_empty() {
+			kana::to_romaji(&self.reading)
+		} else {
+			kana::to_romaji(&self.expression)
+		}
+	}
+}
+
+/// Single definition for a dictionary entry.
+#[derive(Serialize)]
+pub struct Definition {
+	/// Name of the dictionary source for the definition.
+	pub source: String,
+
+	/// English glossary.
+	pub glossary: Vec<String>,
+
+	/// Names of the term and definition tags.
+	pub tags: Vec<String>,
+
+	/// Names of the deinflection rules for the term (e.g. `v5`).
+	pub rules: Vec<String>,
+}
+
+/// Search key found by `Dictionary::search_romaji_fuzzy`.
+pub struct FuzzyMatch {
+	/// Romaji search key.
+	pub key: String,
+
+	/// Distance from the input, as returned by `kana::romaji_distance`.
+	pub distance: f32,
+
+	/// Indexes of the entries with the search key, with user dictionary
+	/// entries first. Short keys can have thousands of entries, so those are
+	/// left for the caller to load with `get_entry`.
+	pub indexes: Vec<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_intersect_lists() {
+		assert_eq!(Vec::<usize>::new(), intersect_lists(vec![]));
+		assert_eq!(vec![1, 2, 3], intersect_lists(vec![vec![3, 1, 2, 1]]));
+		assert_eq!(
+			vec![2, 5],
+			intersect_lists(vec![
+				vec![9, 5, 2, 1],
+				vec![2, 3, 5, 8, 9],
+				vec![5, 2, 2, 7]
+			])
+		);
+		assert_eq!(
+			Vec::<usize>::new(),
+			intersect_lists(vec![vec![1, 2, 3], vec![4, 5], vec![1]])
+		);
+		assert_eq!(
+			Vec::<usize>::new(),
+			intersect_lists(vec![vec![1, 2, 3], vec![]])
+		);
+	}
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+/// Errors from loading and reading the database files, and from searching.
+#[derive(Debug)]
+pub enum Error {
+	/// The database file, or a section of the database file, was not found.
+	MissingFile(String),
+
+	/// A file or section (e.g. a dictionary page) is shorter than required by
+	/// its header or format.
+	TruncatedPage(String),
+
+	/// An index or offset is out of the bounds of its section.
+	BadIndex { file: String, index: usize },
+
+	/// The database format version is not supported by this library.
+	VersionMismatch { expected: u32, found: u32 },
+
+	/// A database part does not match its size and checksum in the manifest.
+	ChecksumMismatch(String),
+
+	/// A file or section has invalid contents (e.g. invalid JSON data).
+	InvalidData { file: String, message: String },
+
+	/// A search query is invalid (e.g. an invalid regular expression).
+	InvalidQuery(String),
+
+	/// A structured search query (see `Query`) has a syntax error, or refers to
+	/// an unknown tag or source. The position is the character offset of the
+	/// error in the query.
+	QuerySyntax { position: usize, message: String },
+
+	/// Error reading the database files.
+	Io(std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+	pub(crate) fn bad_index<S: Into<String>>(file: S, index: usize) -> Error {
+		Error::BadIndex {
+			file: file.into(),
+			index,
+		}
+	}
+
+	pub(crate) fn invalid_data<S: Into<String>, M: ToString>(file: S, message: M) -> Error {
+		Error::InvalidData {
+			file: file.into(),
+			message: message.to_string(),
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::MissingFile(file) => write!(f, "missing database file {}", file),
+			Error::TruncatedPage(file) => write!(f, "database file {} is truncated", file),
+			Error::BadIndex { file, index } => {
+				write!(f, "index {} is out of bounds in {}", index, file)
+			}
+			Error::VersionMismatch { expected, found } => write!(
+				f,
+				"database version is {} (expected version {})",
+				found, expected
+			),
+			Error::ChecksumMismatch(file) => {
+				write!(f, "database part {} does not match the manifest", file)
+			}
+			Error::InvalidData { file, message } => write!(f, "invalid data in {}: {}", file, message),
+			Error::InvalidQuery(message) => write!(f, "invalid query: {}", message),
+			Error::QuerySyntax { position, message } => {
+				write!(f, "invalid query at position {}: {}", position, message)
+			}
+			Error::Io(err) => write!(f, "IO error: {}", err),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Io(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl From<std::io::Error> for Error {
+	fn from(err: std::io::Error) -> Error {
+		Error::Io(err)
+	}
+}
@@ -1,41 +1,51 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-
-use crate::files::Zip;
+use crate::error::{Error, Result};
+use crate::files::{Container, Rows};
+use crate::page_cache::{CacheStats, PageCache, DEFAULT_CACHE_SIZE};
 use crate::raw::RawUint32;
 
-/// Implement loading for the `dict.zip` file which contains the main dictionary
-/// entries.
+/// Implement loading for the `dict` part of the database which contains the
+/// main dictionary entries.
 ///
-/// The structure of this file consists of an `index` file and several numeric
-/// files (`0000`, `0001`, `0002`...).
+/// The part consists of an `index` section and several numeric sections
+/// (`0000`, `0001`, `0002`...).
 ///
-/// The `index` file contains a list of `EntryHeader` for each term in the
+/// The `index` section contains a list of `EntryHeader` for each term in the
 /// dictionary.
 ///
 /// The contents for a given `EntryHeader` term can be retrieved from the numeric
-/// file given by the row position in groups of 1000 (i.e. `index / 1000`).
+/// section given by the row position in groups of 1000 (i.e. `index / 1000`).
+///
+/// Pages are validated and decoded when first used, then kept in a bounded
+/// cache, with `DEFAULT_CACHE_SIZE` bytes of decoded entries unless changed
+/// with `set_cache_size`.
 pub struct Dict {
-	rows: Vec<EntryHeader>,
-	pages: Arc<Mutex<DictPages>>,
-}
+	rows: Rows<EntryHeader>,
 
-struct DictPages {
-	source: Zip,
-	cached: HashMap<usize, EntriesPage>,
+	/// Source for the pages.
+	source: Container,
+	cache: PageCache<EntriesPage>,
 }
 
 impl Dict {
-	pub fn new(mut data: Zip) -> std::io::Result<Dict> {
-		let index: Vec<EntryHeader> = data.read_vec("index")?;
+	pub fn new(data: &Container) -> Result<Dict> {
+		let index: Rows<EntryHeader> = data.rows("dict/index")?;
 		Ok(Dict {
 			rows: index,
-			pages: Arc::new(Mutex::new(DictPages {
-				source: data,
-				cached: Default::default(),
-			})),
+			source: data.clone(),
+			cache: PageCache::new(DEFAULT_CACHE_SIZE),
 		})
 	}
+
+	/// Changes the maximum size in bytes of the page cache. A zero size
+	/// disables caching.
+	pub fn set_cache_size(&mut self, size: usize) {
+		self.cache.set_capacity(size);
+	}
+
+	/// Returns the page cache statistics.
+	pub fn cache_stats(&self) -> CacheStats {
+		self.cache.stats()
+	}
 }
 
 impl Dict {
@@ -43,43 +53,70 @@ impl Dict {
 		self.rows.len()
 	}
 
-	pub fn get_entry(&self, index: usize) -> DictEntry {
-		let page_number = index / 1000;
-		let page_offset = index % 1000;
+	/// Returns the one-based indexes of the expression and reading for an
+	/// entry, without loading its page.
+	pub fn terms(&self, index: usize) -> Result<(usize, usize)> {
+		match self.rows.get(index) {
+			Some(head) => Ok((head.expression.into(), head.reading.into())),
+			None => Err(Error::bad_index("dict/index", index)),
+		}
+	}
 
-		let pages = Arc::clone(&self.pages);
-		let mut pages = pages.lock().unwrap();
-		if !pages.cached.contains_key(&page_number) {
-			let page_file = format!("{:04}", page_number);
-			let page_data = pages.source.read_vec(&page_file).unwrap();
-			pages
-				.cached
-				.insert(page_number, EntriesPage { data: page_data });
+	/// Returns the frequency of an entry, without loading its page.
+	pub fn frequency(&self, index: usize) -> Result<u32> {
+		match self.rows.get(index) {
+			Some(head) => Ok(head.frequency.into()),
+			None => Err(Error::bad_index("dict/index", index)),
 		}
-		let page = &pages.cached[&page_number];
-		let data = page.get_entry(page_offset);
+	}
 
-		let head = &self.rows[index];
+	/// Returns an entry by its zero-based index, loading its page from the
+	/// section if necessary.
+	pub fn get_entry(&self, index: usize) -> Result<DictEntry> {
+		let head = match self.rows.get(index) {
+			Some(head) => head,
+			None => return Err(Error::bad_index("dict/index", index)),
+		};
 
-		DictEntry {
+		let page_number = index / 1000;
+		let page_offset = index % 1000;
+
+		let page = self.cache.get_or_load(page_number, || {
+			let page_name = format!("dict/{:04}", page_number);
+			let page = EntriesPage::load(&self.source, &page_name)?;
+			let size = page.size();
+			Ok((page, size))
+		})?;
+		let data = page.get_entry(page_offset)?;
+
+		Ok(DictEntry {
 			expression: head.expression.into(),
 			reading: head.reading.into(),
 			lookup: head.lookup.into(),
 			frequency: head.frequency.into(),
-			data: data,
-		}
+			definitions: data.definitions.clone(),
+		})
 	}
 }
 
+/// Raw dictionary entry, with the indexes for its strings.
 pub struct DictEntry {
+	/// One-based index of the expression in `terms_text`.
 	pub expression: usize,
+
+	/// One-based index of the reading in `terms_text`.
 	pub reading: usize,
+
+	/// One-based index of the search key in `search_text`.
 	pub lookup: usize,
+
 	pub frequency: u32,
-	data: EntryData,
+
+	/// Definitions for the entry, sorted by score.
+	pub definitions: Vec<EntryDefinition>,
 }
 
-/// A single entry from the `index` file.
+/// A single entry from the `index` section.
 #[repr(C, align(4))]
 struct EntryHeader {
 	/// Index of the main expression text for the entry. This is the main
@@ -102,94 +139,131 @@ struct EntryHeader {
 	frequency: RawUint32,
 }
 
-/// EntriesPage represents the contents of a single numeric file from `Dict`.
+/// EntriesPage represents the contents of a single numeric section from `Dict`.
 ///
-/// A numeric file is composed entirely of 32-bit unsigned integers and its
+/// A numeric section is composed entirely of 32-bit unsigned integers and its
 /// purpose is to store the variable-length data for a term.
 ///
-/// The format of a numeric file is:
+/// The format of a numeric section is:
 ///
 /// ```
-/// 	EntriesPage {
-/// 		IndexLength: u32_le,
-/// 		DataLength:  u32_le,
-/// 		Index:       [u32_le; IndexLength],
-/// 		Data:        [u32_le; DataLength],
-/// 	}
+///     EntriesPage {
+///         IndexLength: u32_le,
+///         DataLength:  u32_le,
+///         Index:       [(Offset: u32_le, Length: u32_le); IndexLength / 2],
+///         Data:        [u32_le; DataLength],
+///     }
 /// ```
 ///
-/// Each entry in `Index` corresponds to a term in the main `index` file, and
-/// gives the offset for that entry's content in the `Data` array.
+/// Each entry in `Index` corresponds to a term in the main `index` section, and
+/// gives the offset and length for that entry's content in the `Data` array.
 ///
 /// ```
-/// 	EntryData {
-/// 		DefinitionCount: u32_le,
-/// 		Definitions:     [EntryDefinition; DefinitionCount],
-/// 	}
+///     EntryData {
+///         DefinitionCount: u32_le,
+///         Definitions:     [EntryDefinition; DefinitionCount],
+///     }
 ///
-/// 	EntryDefinition {
-/// 		SourceIndex: u32_le,
-/// 		Text:        EntryDefinitionList,
-/// 		Rules:       EntryDefinitionList,
-/// 		TagsForTerm: EntryDefinitionList,
-/// 		TagsForText: EntryDefinitionList,
-/// 	}
+///     EntryDefinition {
+///         SourceIndex: u32_le,
+///         Text:        EntryDefinitionList,
+///         Rules:       EntryDefinitionList,
+///         TagsForTerm: EntryDefinitionList,
+///         TagsForText: EntryDefinitionList,
+///     }
 ///
-/// 	EntryDefinitionList {
-/// 		Count: u32_le,
-/// 		Items: [u32_le; Count],
-/// 	}
+///     EntryDefinitionList {
+///         Count: u32_le,
+///         Items: [u32_le; Count],
+///     }
 /// ```
+///
+/// The whole page is validated and decoded on load, so that the page cache
+/// holds the decoded entries rather than a view of the database file.
 struct EntriesPage {
-	// Entire data for the field.
-	data: Vec<RawUint32>,
+	/// Name of the page section, for errors.
+	name: String,
+
+	entries: Vec<EntryData>,
 }
 
 impl EntriesPage {
-	/// Number of entries in this file.
-	pub fn count(&self) -> usize {
-		self.data[0].into()
-	}
+	fn load(source: &Container, name: &str) -> Result<EntriesPage> {
+		let data: Rows<RawUint32> = source.rows(name)?;
 
-	/// Returns the data for an entry from the file.
-	pub fn get_entry(&self, index: usize) -> EntryData {
-		let count = self.count();
-		let data_index = &self.data[2..2 + count];
-		let data_block = &self.data[2 + count..];
-		let data_offset: usize = data_index[index].into();
+		// Check that the data matches the header lengths.
+		if data.len() < 2 {
+			return Err(Error::TruncatedPage(name.to_string()));
+		}
+		let index_length: usize = data[0].into();
+		let data_length: usize = data[1].into();
+		if !index_length.is_multiple_of(2) || data.len() != 2 + index_length + data_length {
+			return Err(Error::TruncatedPage(name.to_string()));
+		}
 
-		let mut data = &data_block[data_offset..];
+		let count = index_length / 2;
+		let data_block = &data[2 + index_length..];
+		let mut entries = Vec::with_capacity(count);
+		for index in 0..count {
+			let offset: usize = data[2 + index * 2].into();
+			let length: usize = data[2 + index * 2 + 1].into();
+			if offset.saturating_add(length) > data_length {
+				return Err(Error::bad_index(name, index));
+			}
+			let entry = EntriesPage::decode(&data_block[offset..offset + length])
+				.ok_or_else(|| Error::bad_index(name, index))?;
+			entries.push(entry);
+		}
 
-		let definition_count: usize = data[0].into();
-		data = &data[1..];
+		Ok(EntriesPage {
+			name: name.to_string(),
+			entries,
+		})
+	}
 
-		let mut entry = EntryData {
-			definitions: Vec::with_capacity(definition_count),
-		};
+	/// Approximate size in bytes of the decoded page in memory.
+	fn size(&self) -> usize {
+		let mut size = std::mem::size_of::<EntriesPage>() + self.name.capacity();
+		size += self.entries.capacity() * std::mem::size_of::<EntryData>();
+		for entry in self.entries.iter() {
+			size += entry.definitions.capacity() * std::mem::size_of::<EntryDefinition>();
+			for it in entry.definitions.iter() {
+				let items = it.text.capacity()
+					+ it.rules.capacity()
+					+ it.tags_for_term.capacity()
+					+ it.tags_for_text.capacity();
+				size += items * std::mem::size_of::<usize>();
+			}
+		}
+		size
+	}
 
-		for _ in 0..definition_count {
-			let source = data[0];
-			data = &data[1..];
+	/// Returns the data for an entry from the page.
+	fn get_entry(&self, index: usize) -> Result<&EntryData> {
+		match self.entries.get(index) {
+			Some(entry) => Ok(entry),
+			None => Err(Error::bad_index(self.name.as_str(), index)),
+		}
+	}
 
-			let text_length: usize = data[0].into();
-			data = &data[1..];
-			let text = &data[..text_length];
-			data = &data[text_length..];
+	/// Decodes the `EntryData` for a single entry, returning `None` if the
+	/// data is invalid.
+	fn decode(data: &[RawUint32]) -> Option<EntryData> {
+		let length = data.len();
+		let mut data = EntryReader { data };
 
-			let rules_length: usize = data[0].into();
-			data = &data[1..];
-			let rules = &data[..rules_length];
-			data = &data[rules_length..];
+		let definition_count: usize = data.next()?.into();
 
-			let tags_for_term_length: usize = data[0].into();
-			data = &data[1..];
-			let tags_for_term = &data[..tags_for_term_length];
-			data = &data[tags_for_term_length..];
+		let mut entry = EntryData {
+			definitions: Vec::with_capacity(definition_count.min(length)),
+		};
 
-			let tags_for_text_length: usize = data[0].into();
-			data = &data[1..];
-			let tags_for_text = &data[..tags_for_text_length];
-			data = &data[tags_for_text_length..];
+		for _ in 0..definition_count {
+			let source = data.next()?;
+			let text = data.list()?;
+			let rules = data.list()?;
+			let tags_for_term = data.list()?;
+			let tags_for_text = data.list()?;
 
 			entry.definitions.push(EntryDefinition {
 				source: source.into(),
@@ -200,7 +274,31 @@ impl EntriesPage {
 			});
 		}
 
-		entry
+		Some(entry)
+	}
+}
+
+/// Bounds checked reader for the data of a single entry.
+struct EntryReader<'a> {
+	data: &'a [RawUint32],
+}
+
+impl<'a> EntryReader<'a> {
+	fn next(&mut self) -> Option<RawUint32> {
+		let (&value, data) = self.data.split_first()?;
+		self.data = data;
+		Some(value)
+	}
+
+	/// Reads an `EntryDefinitionList`.
+	fn list(&mut self) -> Option<&'a [RawUint32]> {
+		let count: usize = self.next()?.into();
+		if count > self.data.len() {
+			return None;
+		}
+		let (list, data) = self.data.split_at(count);
+		self.data = data;
+		Some(list)
 	}
 }
 
@@ -208,12 +306,23 @@ struct EntryData {
 	definitions: Vec<EntryDefinition>,
 }
 
-struct EntryDefinition {
-	source: usize,
-	text: Vec<usize>,
-	rules: Vec<usize>,
-	tags_for_term: Vec<usize>,
-	tags_for_text: Vec<usize>,
+/// Raw definition for a dictionary entry.
+#[derive(Clone)]
+pub struct EntryDefinition {
+	/// One-based index of the source name in `sources.txt`.
+	pub source: usize,
+
+	/// One-based indexes of the glossary text in `glossary`.
+	pub text: Vec<usize>,
+
+	/// Indexes of the rule tags in `tags.json`.
+	pub rules: Vec<usize>,
+
+	/// Indexes of the term tags in `tags.json`.
+	pub tags_for_term: Vec<usize>,
+
+	/// Indexes of the definition tags in `tags.json`.
+	pub tags_for_text: Vec<usize>,
 }
 
 fn _assert_send_sync()
@@ -0,0 +1,63 @@
+use crate::error::Result;
+use crate::file_index::Index;
+use crate::file_text::Text;
+use crate::files::Container;
+
+/// Implement loading for the `examples` part of the database, which contains
+/// the example sentences and their index by dictionary entry.
+///
+/// The part consists of the sections:
+///
+/// - `sentences` text file with a tab separated `japanese` and `english` pair
+///   for each sentence.
+/// - `index` index file with the sentences for each dictionary entry, sorted
+///   by preference (shorter sentences with more frequent vocabulary first).
+pub struct Examples {
+	sentences: Text,
+	index: Index,
+}
+
+impl Examples {
+	pub fn new(data: &Container) -> Result<Examples> {
+		let sentences = Text::load_text(data, "examples/sentences")?;
+		let index = Index::load_index(data, "examples/index")?;
+		index.check_values(sentences.count())?;
+		Ok(Examples { sentences, index })
+	}
+}
+
+impl Examples {
+	/// Total number of example sentences.
+	pub fn count(&self) -> usize {
+		self.sentences.count()
+	}
+
+	pub fn get_example(&self, index: usize) -> Result<Example> {
+		let text = self.sentences.entry(index)?;
+		let mut fields = text.splitn(2, '\t');
+		Ok(Example {
+			japanese: fields.next().unwrap_or_default().to_string(),
+			english: fields.next().unwrap_or_default().to_string(),
+		})
+	}
+
+	/// Returns up to `limit` example sentences for the given dictionary entry
+	/// index, with the preferred sentences first.
+	pub fn examples_for(&self, entry_id: usize, limit: usize) -> Result<Vec<Example>> {
+		if entry_id >= self.index.count() {
+			return Ok(Vec::new());
+		}
+		self.index
+			.entry(entry_id)?
+			.into_iter()
+			.take(limit)
+			.map(|index| self.get_example(index - 1))
+			.collect()
+	}
+}
+
+/// Example sentence with its translation.
+pub struct Example {
+	pub japanese: String,
+	pub english: String,
+}
@@ -0,0 +1,81 @@
+use crate::error::{Error, Result};
+use crate::files::{Container, Rows};
+use crate::raw::RawUint32;
+
+/// Wraps an index file, which maps each entry of a `Text` file to a list of
+/// indexes (e.g. the dictionary entries for a term).
+///
+/// The format of an index file is:
+///
+/// ```
+///     Index {
+///         Count: u32_le,
+///         Index: [(Offset: u32_le, Length: u32_le); Count],
+///         Data:  [u32_le],
+///     }
+/// ```
+///
+/// Values in `Data` are one-based indexes. The offsets and values are
+/// validated on load.
+pub struct Index {
+	name: String,
+	index: Rows<RawUint32>,
+	data: Rows<RawUint32>,
+}
+
+impl Index {
+	pub fn load_index(data: &Container, name: &str) -> Result<Index> {
+		let mut file = data.section(name)?;
+		let count: usize = file.read_uint()?.into();
+		let index = file.read_uint_vec(count.saturating_mul(2))?;
+		let data = file.read_vec::<RawUint32>()?;
+		for (n, entry) in index.chunks(2).enumerate() {
+			let offset: usize = entry[0].into();
+			let length: usize = entry[1].into();
+			if offset.saturating_add(length) > data.len() {
+				return Err(Error::bad_index(file.name(), n));
+			}
+		}
+		for (n, &value) in data.iter().enumerate() {
+			let value: usize = value.into();
+			if value == 0 {
+				return Err(Error::bad_index(file.name(), n));
+			}
+		}
+		Ok(Index {
+			name: file.name().to_string(),
+			index,
+			data,
+		})
+	}
+
+	pub fn count(&self) -> usize {
+		self.index.len() / 2
+	}
+
+	/// Returns the list of one-based indexes for the given entry.
+	pub fn entry(&self, index: usize) -> Result<Vec<usize>> {
+		if index >= self.count() {
+			return Err(Error::bad_index(self.name.as_str(), index));
+		}
+		let pos = index * 2;
+		let offset: usize = self.index[pos].into();
+		let length: usize = self.index[pos + 1].into();
+		Ok(self.data[offset..offset + length]
+			.iter()
+			.map(|&x| x.into())
+			.collect())
+	}
+
+	/// Checks that all values in the index are at most `count` (e.g. the
+	/// number of entries the index refers to).
+	pub fn check_values(&self, count: usize) -> Result<()> {
+		for (n, &value) in self.data.iter().enumerate() {
+			let value: usize = value.into();
+			if value > count {
+				return Err(Error::bad_index(self.name.as_str(), n));
+			}
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::files::Container;
+use crate::user_dict::english_words;
+
+/// Implements loading for the `kanji` part of the database, which contains the
+/// kanji dictionary as a `kanji.json` list sorted by frequency.
+pub struct KanjiDict {
+	list: Vec<Kanji>,
+	map: HashMap<char, usize>,
+}
+
+impl KanjiDict {
+	pub fn new(data: &Container) -> Result<KanjiDict> {
+		let file = data.section("kanji/kanji.json")?;
+		let list: Vec<Kanji> = serde_json::from_slice(file.bytes())
+			.map_err(|err| Error::invalid_data(file.name(), err))?;
+		let map = list
+			.iter()
+			.enumerate()
+			.map(|(index, kanji)| (kanji.character, index))
+			.collect();
+		Ok(KanjiDict { list, map })
+	}
+
+	/// Number of kanji in the dictionary.
+	pub fn count(&self) -> usize {
+		self.list.len()
+	}
+
+	/// Returns a kanji by its position in the frequency sorted list.
+	pub fn entry(&self, index: usize) -> Option<&Kanji> {
+		self.list.get(index)
+	}
+
+	/// Looks up a kanji by its character.
+	pub fn get(&self, character: char) -> Option<&Kanji> {
+		self.map.get(&character).map(|&index| &self.list[index])
+	}
+
+	/// Returns the position of a kanji in the frequency sorted list.
+	pub fn position(&self, character: char) -> Option<usize> {
+		self.map.get(&character).cloned()
+	}
+
+	/// Searches kanji with all the given filters, sorted by frequency:
+	///
+	/// - `reading` matches an onyomi, kunyomi or nanori reading, in either
+	///   romaji or kana. Kunyomi also match without the okurigana (e.g. `た`
+	///   for `た.べる`).
+	/// - `meaning` matches a meaning with all its English words.
+	/// - `strokes` matches the stroke count.
+	pub fn search(
+		&self,
+		reading: Option<&str>,
+		meaning: Option<&str>,
+		strokes: Option<u32>,
+	) -> Vec<&Kanji> {
+		let reading = reading.map(|x| kana::to_hiragana(x.trim()));
+		let meaning = meaning.map(english_words);
+		self.list
+			.iter()
+			.filter(|kanji| match &reading {
+				Some(reading) => kanji.has_reading(reading),
+				None => true,
+			})
+			.filter(|kanji| match &meaning {
+				Some(words) => kanji.has_meaning(words),
+				None => true,
+			})
+			.filter(|kanji| strokes.is_none() || kanji.info.strokes == strokes)
+			.collect()
+	}
+
+	/// Iterates over all kanji, sorted by frequency.
+	pub fn iter(&self) -> std::slice::Iter<'_, Kanji> {
+		self.list.iter()
+	}
+}
+
+/// Dictionary entry for a kanji.
+#[derive(Deserialize)]
+pub struct Kanji {
+	/// Kanji character.
+	pub character: char,
+
+	/// Onyomi (chinese) readings for the Kanji.
+	pub onyomi: Vec<String>,
+
+	/// Kunyomi (japanese) readings for the Kanji.
+	pub kunyomi: Vec<String>,
+
+	/// Tags for the Kanji.
+	pub tags: Vec<String>,
+
+	/// Meanings for the kanji.
+	pub meanings: Vec<String>,
+
+	/// Additional kanji information as raw strings. The keys in `stats` are
+	/// further detailed by the dictionary tags.
+	pub stats: HashMap<String, String>,
+
+	/// Source database name.
+	pub source: String,
+
+	/// Frequency information for this kanji, as the number of occurrences in
+	/// a reference corpus. Zero means no data.
+	pub frequency: u32,
+
+	/// Typed kanji metadata.
+	#[serde(default)]
+	pub info: KanjiInfo,
+}
+
+impl Kanji {
+	/// Returns true if any reading is equal to `reading`, in hiragana.
+	fn has_reading(&self, reading: &str) -> bool {
+		let readings = self.onyomi.iter().chain(self.kunyomi.iter());
+		readings.chain(self.info.nanori.iter()).any(|x| {
+			let x = x.trim_matches('-');
+			let stem = kana::to_hiragana(x.split('.').next().unwrap_or_default());
+			stem == reading || kana::to_hiragana(x.replace('.', "")) == reading
+		})
+	}
+
+	/// Returns true if any meaning has all the English `words`.
+	fn has_meaning(&self, words: &[String]) -> bool {
+		!words.is_empty()
+			&& self.meanings.iter().any(|x| {
+				let meaning = english_words(x);
+				words.iter().all(|word| meaning.contains(word))
+			})
+	}
+}
+
+/// Typed metadata for a kanji, mostly from KANJIDIC2.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct KanjiInfo {
+	/// Stroke count.
+	pub strokes: Option<u32>,
+
+	/// School grade in which the kanji is taught (1-6), or `8` for the
+	/// remaining jouyou kanji, `9` and `10` for jinmeiyou kanji.
+	pub grade: Option<u32>,
+
+	/// JLPT level (pre-2010, from `4` to `1`).
+	pub jlpt: Option<u32>,
+
+	/// Frequency rank among the 2500 most used kanji in newspapers.
+	pub frequency_rank: Option<u32>,
+
+	/// Classical (KangXi Zidian) radical number.
+	pub radical: Option<u32>,
+
+	/// Nelson radical number, when different from the classical radical.
+	pub radical_nelson: Option<u32>,
+
+	/// Variant forms, as `type:code` (e.g. `jis208:52-01`, `ucs:5f8c`).
+	pub variants: Vec<String>,
+
+	/// Readings used only in names.
+	pub nanori: Vec<String>,
+
+	/// Korean readings in hangul.
+	pub korean: Vec<String>,
+
+	/// Korean readings in romanized form.
+	pub korean_romanized: Vec<String>,
+
+	/// Chinese readings in pinyin.
+	pub pinyin: Vec<String>,
+
+	/// Index numbers in reference dictionaries.
+	pub references: KanjiReferences,
+}
+
+/// Kanji index numbers in reference dictionaries.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct KanjiReferences {
+	pub heisig: Option<u32>,
+	pub heisig6: Option<u32>,
+	pub nelson_classic: Option<u32>,
+	pub nelson_new: Option<u32>,
+	pub halpern_njecd: Option<u32>,
+	pub halpern_kkd: Option<u32>,
+	pub halpern_kkld: Option<u32>,
+	pub halpern_kkld_2ed: Option<u32>,
+}
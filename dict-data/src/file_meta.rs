@@ -0,0 +1,72 @@
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::files::Container;
+
+/// Implements loading for the `meta` part of the database, which contains the
+/// dictionary metadata:
+///
+/// - `tags.json` with the list of tags referenced by the dictionary entries.
+/// - `sources.txt` with the name of the dictionary sources, one per line.
+pub struct Meta {
+	tags: Vec<Tag>,
+	sources: Vec<String>,
+}
+
+impl Meta {
+	pub fn new(data: &Container) -> Result<Meta> {
+		let tags = {
+			let file = data.section("meta/tags.json")?;
+			serde_json::from_slice(file.bytes())
+				.map_err(|err| Error::invalid_data(file.name(), err))?
+		};
+		let sources = {
+			let file = data.section("meta/sources.txt")?;
+			String::from_utf8_lossy(file.bytes())
+				.lines()
+				.map(|x| x.to_string())
+				.collect()
+		};
+		Ok(Meta { tags, sources })
+	}
+
+	/// Returns a tag by its index.
+	pub fn tag(&self, index: usize) -> Option<&Tag> {
+		self.tags.get(index)
+	}
+
+	/// Returns true if there is a tag named `name`.
+	pub fn has_tag(&self, name: &str) -> bool {
+		self.tags.iter().any(|x| x.name == name)
+	}
+
+	/// Returns the name of a source by its one-based index.
+	pub fn source(&self, index: usize) -> &str {
+		if index == 0 || index > self.sources.len() {
+			""
+		} else {
+			&self.sources[index - 1]
+		}
+	}
+
+	/// Returns the names of all the sources.
+	pub fn sources(&self) -> &[String] {
+		&self.sources
+	}
+}
+
+/// Tag for dictionary entries and definitions.
+#[derive(Clone, Deserialize)]
+pub struct Tag {
+	/// Tag name (e.g. `v5`, `n`, `arch`).
+	pub name: String,
+
+	/// Tag category (e.g. `partOfSpeech`), may be empty.
+	pub category: String,
+
+	/// Description for the tag.
+	pub notes: String,
+
+	/// Sorting order for the tag.
+	pub order: i32,
+}
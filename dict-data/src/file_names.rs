@@ -0,0 +1,192 @@
+use crate::error::{Error, Result};
+use crate::file_text::Text;
+use crate::files::{Container, Rows};
+use crate::postings::PostingIndex;
+use crate::raw::RawUint32;
+
+/// Implement loading for the `names` part of the database, which contains the
+/// proper names dictionary (JMnedict) separate from the main dictionary.
+///
+/// The part consists of the sections:
+///
+/// - `index` with a list of `NameHeader` for each name.
+/// - `glossary` text file with the tab separated translations for each name.
+/// - `names_text` sorted text file with the name expressions and readings.
+/// - `names_index` posting index mapping `names_text` to the names.
+/// - `names_reverse` reverse index for `names_text`.
+pub struct Names {
+	rows: Rows<NameHeader>,
+	glossary: Text,
+	text: Text,
+	index: PostingIndex,
+}
+
+impl Names {
+	pub fn new(data: &Container) -> Result<Names> {
+		let rows: Rows<NameHeader> = data.rows("names/index")?;
+		let glossary = Text::load_text(data, "names/glossary")?;
+		let text = Text::load_text(data, "names/names_text")?;
+		let index = PostingIndex::load(data, "names/names_index")?;
+		if index.count() != text.count() {
+			let message = "index count does not match the text";
+			return Err(Error::invalid_data(index.name(), message));
+		}
+		Ok(Names {
+			rows,
+			glossary,
+			text,
+			index,
+		})
+	}
+}
+
+impl Names {
+	pub fn count(&self) -> usize {
+		self.rows.len()
+	}
+
+	pub fn get_entry(&self, index: usize) -> Result<NameEntry> {
+		let head = match self.rows.get(index) {
+			Some(head) => head,
+			None => return Err(Error::bad_index("names/index", index)),
+		};
+		let expression: usize = head.expression.into();
+		let reading: usize = head.reading.into();
+		let types: u32 = head.types.into();
+		Ok(NameEntry {
+			expression: self.get_text(expression)?,
+			reading: self.get_text(reading)?,
+			types: NameType::from_mask(types),
+			translations: self
+				.glossary
+				.entry(index)?
+				.split('\t')
+				.filter(|x| !x.is_empty())
+				.map(|x| x.to_string())
+				.collect(),
+		})
+	}
+
+	/// Searches names by their exact kanji or reading. Returns the sorted
+	/// name indexes.
+	pub fn search(&self, text: &str) -> Result<Vec<usize>> {
+		match self.text.find(text) {
+			Some(index) => Ok(self.index.entry(index)?.map(|x| x as usize - 1).collect()),
+			None => Ok(Vec::new()),
+		}
+	}
+
+	/// Searches names with a kanji or reading starting with `prefix`. Returns
+	/// at most `limit` sorted name indexes.
+	pub fn search_prefix(&self, prefix: &str, limit: usize) -> Result<Vec<usize>> {
+		let range = self.text.prefix_range(prefix);
+		Ok(self
+			.index
+			.union(range)?
+			.take(limit)
+			.map(|x| x as usize - 1)
+			.collect())
+	}
+
+	fn get_text(&self, index: usize) -> Result<String> {
+		if index == 0 {
+			Ok(String::new())
+		} else {
+			self.text.entry(index - 1)
+		}
+	}
+}
+
+/// A single entry from the names dictionary.
+pub struct NameEntry {
+	/// Name expression. Same as the reading for kana only names.
+	pub expression: String,
+
+	/// Kana reading for the name.
+	pub reading: String,
+
+	/// Name types.
+	pub types: Vec<NameType>,
+
+	/// Translations for the name, usually the romanized name.
+	pub translations: Vec<String>,
+}
+
+/// Type of a name entry. A name can have multiple types.
+///
+/// The order of the variants is the order of the type bits in `NameHeader`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NameType {
+	Surname,
+	Given,
+	Place,
+	Company,
+	Person,
+	Male,
+	Female,
+	Organization,
+	Product,
+	Work,
+	Station,
+	Unclassified,
+	Other,
+}
+
+const NAME_TYPES: [NameType; 13] = [
+	NameType::Surname,
+	NameType::Given,
+	NameType::Place,
+	NameType::Company,
+	NameType::Person,
+	NameType::Male,
+	NameType::Female,
+	NameType::Organization,
+	NameType::Product,
+	NameType::Work,
+	NameType::Station,
+	NameType::Unclassified,
+	NameType::Other,
+];
+
+impl NameType {
+	/// JMnedict tag for this name type.
+	pub fn tag(self) -> &'static str {
+		match self {
+			NameType::Surname => "surname",
+			NameType::Given => "given",
+			NameType::Place => "place",
+			NameType::Company => "company",
+			NameType::Person => "person",
+			NameType::Male => "masc",
+			NameType::Female => "fem",
+			NameType::Organization => "organization",
+			NameType::Product => "product",
+			NameType::Work => "work",
+			NameType::Station => "station",
+			NameType::Unclassified => "unclass",
+			NameType::Other => "other",
+		}
+	}
+
+	fn from_mask(mask: u32) -> Vec<NameType> {
+		NAME_TYPES
+			.iter()
+			.enumerate()
+			.filter(|(bit, _)| mask & (1 << bit) != 0)
+			.map(|(_, &name_type)| name_type)
+			.collect()
+	}
+}
+
+/// A single entry from the names `index` file.
+#[repr(C, align(4))]
+struct NameHeader {
+	/// One-based index of the name expression in `names_text`.
+	expression: RawUint32,
+
+	/// One-based index of the name reading in `names_text`.
+	reading: RawUint32,
+
+	/// Bit mask of `NameType` for the name.
+	types: RawUint32,
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::error::Result;
+use crate::file_text::Text;
+use crate::files::{Container, Rows};
+use crate::raw::RawUint32;
+
+/// Implement loading for the `strokes` part of the database, which contains the
+/// stroke order data for kanji (from KanjiVG).
+///
+/// The part consists of the sections:
+///
+/// - `index` with a list of `StrokesHeader` sorted by character.
+/// - `strokes` text file with the tab separated `kind` and `path` for each
+///   stroke.
+/// - `groups` text file with the tab separated `level`, `first`, `count`,
+///   `element`, `radical`, `position` and `part` for each stroke group.
+pub struct Strokes {
+	rows: Rows<StrokesHeader>,
+	strokes: Text,
+	groups: Text,
+
+	/// Kanji for each component, see `with_component`.
+	by_component: OnceLock<HashMap<char, Vec<char>>>,
+}
+
+impl Strokes {
+	pub fn new(data: &Container) -> Result<Strokes> {
+		let rows: Rows<StrokesHeader> = data.rows("strokes/index")?;
+		let strokes = Text::load_text(data, "strokes/strokes")?;
+		let groups = Text::load_text(data, "strokes/groups")?;
+		Ok(Strokes {
+			rows,
+			strokes,
+			groups,
+			by_component: OnceLock::new(),
+		})
+	}
+}
+
+impl Strokes {
+	/// Number of kanji with stroke data.
+	pub fn count(&self) -> usize {
+		self.rows.len()
+	}
+
+	/// Returns the stroke data for a kanji, if available.
+	pub fn get(&self, character: char) -> Result<Option<KanjiStrokes>> {
+		let head = match self.find(character) {
+			Some(head) => head,
+			None => return Ok(None),
+		};
+		let first: usize = head.strokes_first.into();
+		let count: usize = head.strokes_count.into();
+		let strokes = (first..first.saturating_add(count))
+			.map(|index| {
+				let text = self.strokes.entry(index)?;
+				let mut fields = text.splitn(2, '\t');
+				Ok(Stroke {
+					kind: fields.next().unwrap_or_default().to_string(),
+					path: fields.next().unwrap_or_default().to_string(),
+				})
+			})
+			.collect::<Result<_>>()?;
+
+		let first: usize = head.groups_first.into();
+		let count: usize = head.groups_count.into();
+		let groups = (first..first.saturating_add(count))
+			.map(|index| {
+				let text = self.groups.entry(index)?;
+				let fields: Vec<&str> = text.split('\t').collect();
+				let field = |n: usize| fields.get(n).cloned().unwrap_or_default();
+				Ok(StrokeGroup {
+					level: field(0).parse().unwrap_or_default(),
+					first: field(1).parse().unwrap_or_default(),
+					count: field(2).parse().unwrap_or_default(),
+					element: field(3).to_string(),
+					radical: field(4).to_string(),
+					position: field(5).to_string(),
+					part: field(6).to_string(),
+				})
+			})
+			.collect::<Result<_>>()?;
+
+		Ok(Some(KanjiStrokes {
+			character,
+			strokes,
+			groups,
+		}))
+	}
+
+	/// Returns the SVG paths for the kanji strokes in stroke order. Returns an
+	/// empty list if there is no stroke data for the kanji.
+	pub fn paths(&self, character: char) -> Result<Vec<String>> {
+		match self.get(character)? {
+			Some(data) => Ok(data.strokes.into_iter().map(|x| x.path).collect()),
+			None => Ok(Vec::new()),
+		}
+	}
+
+	/// Returns the components of a kanji, see `KanjiStrokes::components`.
+	/// Returns an empty list if there is no stroke data for the kanji.
+	pub fn components(&self, character: char) -> Result<Vec<char>> {
+		match self.get(character)? {
+			Some(data) => Ok(data.components()),
+			None => Ok(Vec::new()),
+		}
+	}
+
+	/// Returns the kanji with `component` in their components, sorted by
+	/// character.
+	///
+	/// The first call reads the components for all kanji to build the index.
+	pub fn with_component(&self, component: char) -> Result<&[char]> {
+		let index = match self.by_component.get() {
+			Some(index) => index,
+			None => {
+				let mut index: HashMap<char, Vec<char>> = HashMap::new();
+				for head in self.rows.iter() {
+					let character = std::char::from_u32(head.character.into());
+					let character = character.unwrap_or_default();
+					for component in self.components(character)? {
+						index.entry(component).or_default().push(character);
+					}
+				}
+				self.by_component.get_or_init(|| index)
+			}
+		};
+		Ok(index
+			.get(&component)
+			.map(|x| x.as_slice())
+			.unwrap_or_default())
+	}
+
+	fn find(&self, character: char) -> Option<&StrokesHeader> {
+		let character = character as u32;
+		let index = self
+			.rows
+			.binary_search_by_key(&character, |x| x.character.into())
+			.ok()?;
+		Some(&self.rows[index])
+	}
+}
+
+/// Stroke order data for a single kanji.
+pub struct KanjiStrokes {
+	pub character: char,
+
+	/// Kanji strokes in stroke order.
+	pub strokes: Vec<Stroke>,
+
+	/// Stroke groups (components) for the kanji, in document order. The first
+	/// group is the kanji itself.
+	pub groups: Vec<StrokeGroup>,
+}
+
+impl KanjiStrokes {
+	/// Returns the components of the kanji, which are the elements of its
+	/// stroke groups other than the kanji itself, in stroke order and without
+	/// duplicates.
+	pub fn components(&self) -> Vec<char> {
+		let mut output = Vec::new();
+		for group in self.groups.iter() {
+			let mut chars = group.element.chars();
+			if let (Some(chr), None) = (chars.next(), chars.next()) {
+				if chr != self.character && !output.contains(&chr) {
+					output.push(chr);
+				}
+			}
+		}
+		output
+	}
+}
+
+/// A single kanji stroke.
+pub struct Stroke {
+	/// Stroke type (e.g. `㇐`), may be empty.
+	pub kind: String,
+
+	/// SVG path data for the stroke, in a 109x109 coordinate system.
+	pub path: String,
+}
+
+/// A group of consecutive strokes forming a kanji component.
+pub struct StrokeGroup {
+	/// Nesting level of the group, zero for the top level group.
+	pub level: usize,
+
+	/// One-based number of the first stroke in the group.
+	pub first: usize,
+
+	/// Number of strokes in the group.
+	pub count: usize,
+
+	/// Element (component) represented by the group, if any.
+	pub element: String,
+
+	/// Radical annotation (e.g. `general`, `tradit`, `nelson`), if the group
+	/// is the kanji radical.
+	pub radical: String,
+
+	/// Position of the group in the parent (e.g. `left`, `right`, `top`).
+	pub position: String,
+
+	/// Part number for elements split in multiple groups.
+	pub part: String,
+}
+
+/// A single entry from the strokes `index` file.
+#[repr(C, align(4))]
+struct StrokesHeader {
+	/// Unicode code point for the kanji.
+	character: RawUint32,
+
+	/// Index of the first stroke in `strokes`.
+	strokes_first: RawUint32,
+
+	/// Number of strokes.
+	strokes_count: RawUint32,
+
+	/// Index of the first group in `groups`.
+	groups_first: RawUint32,
+
+	/// Number of groups.
+	groups_count: RawUint32,
+}
@@ -0,0 +1,333 @@
+use fst::automaton::Automaton;
+
+use crate::error::{Error, Result};
+use crate::file_dict::Dict;
+use crate::file_text::Text;
+use crate::files::{Container, Rows};
+use crate::pattern::Pattern;
+use crate::postings::{intersect, PostingIndex, Postings};
+use crate::raw::RawUint32;
+use crate::term_index::TermIndex;
+
+/// Implements loading for the `text` part of the database, which contains the
+/// strings and search indexes for the main dictionary.
+///
+/// The part consists of the sections:
+///
+/// - `glossary` text file with the glossary definitions.
+/// - `english_text` and `english_index` with the sorted English words in the
+///   definitions, and the entries for each.
+/// - `terms_text`, `terms_fst`, `terms_index` and `terms_reverse` with the
+///   sorted term expressions and readings (see `TermTable`).
+/// - `search_text`, `search_fst`, `search_index` and `search_reverse` with the
+///   sorted romaji search keys (see `TermTable`).
+///
+/// The `chars` part is also loaded with the terms, with the sorted list of
+/// characters in the terms (`chars/chars`) and the entries for each
+/// (`chars/index`).
+///
+/// All indexes are `PostingIndex` sections. All search methods return sorted
+/// zero-based entry indexes. Since entries are sorted by frequency, this is
+/// also the frequency order.
+pub struct Terms {
+	glossary: Text,
+	english: Text,
+	english_index: PostingIndex,
+	terms: TermTable,
+	search: TermTable,
+	chars: Rows<RawUint32>,
+	chars_index: PostingIndex,
+}
+
+impl Terms {
+	pub fn new(data: &Container) -> Result<Terms> {
+		let glossary = Text::load_text(data, "text/glossary")?;
+		let english = Text::load_text(data, "text/english_text")?;
+		let english_index = PostingIndex::load(data, "text/english_index")?;
+		let terms = TermTable::load(data, "text/terms")?;
+		let search = TermTable::load(data, "text/search")?;
+		let chars: Rows<RawUint32> = data.rows("chars/chars")?;
+		let chars_index = PostingIndex::load(data, "chars/index")?;
+		check_index_count(&english, &english_index)?;
+		if chars.len() != chars_index.count() {
+			let message = "character count does not match the index";
+			return Err(Error::invalid_data("chars/index", message));
+		}
+		Ok(Terms {
+			glossary,
+			english,
+			english_index,
+			terms,
+			search,
+			chars,
+			chars_index,
+		})
+	}
+}
+
+/// Checks that an index has a list for each text entry.
+fn check_index_count(text: &Text, index: &PostingIndex) -> Result<()> {
+	if text.count() != index.count() {
+		let message = "index count does not match the text";
+		return Err(Error::invalid_data(index.name(), message));
+	}
+	Ok(())
+}
+
+/// Sorted list of strings with its indexes, loaded from the sections:
+///
+/// - `{name}_text` with the sorted strings.
+/// - `{name}_fst` mapping each string to its position.
+/// - `{name}_index` with the entries for each string.
+/// - `{name}_reverse` with the one-based string positions sorted by the
+///   reversed string, after a leading zero.
+///
+/// Methods returning positions use the zero-based string position.
+struct TermTable {
+	text: Text,
+	fst: TermIndex,
+	index: PostingIndex,
+	reverse: Rows<RawUint32>,
+}
+
+impl TermTable {
+	fn load(data: &Container, name: &str) -> Result<TermTable> {
+		let text = Text::load_text(data, &format!("{}_text", name))?;
+		let fst = TermIndex::load(data, &format!("{}_fst", name))?;
+		let index = PostingIndex::load(data, &format!("{}_index", name))?;
+		let reverse_name = format!("{}_reverse", name);
+		let reverse: Rows<RawUint32> = data.rows(&reverse_name)?;
+		check_index_count(&text, &index)?;
+		if text.count() != fst.count() {
+			let message = "term count does not match the text";
+			return Err(Error::invalid_data(fst.name(), message));
+		}
+		if reverse.len() != text.count() + 1 {
+			let message = "reverse index count does not match the text";
+			return Err(Error::invalid_data(reverse_name, message));
+		}
+		for (n, &position) in reverse.iter().enumerate().skip(1) {
+			let position: usize = position.into();
+			if position == 0 || position > text.count() {
+				return Err(Error::bad_index(reverse_name, n));
+			}
+		}
+		Ok(TermTable {
+			text,
+			fst,
+			index,
+			reverse,
+		})
+	}
+
+	/// Returns the positions of the strings ending with `suffix`, using the
+	/// reverse index.
+	fn suffix(&self, suffix: &str) -> Result<Vec<usize>> {
+		let suffix: String = suffix.chars().rev().collect();
+		let reversed = |n: usize| -> Result<String> {
+			let position: usize = self.reverse[n + 1].into();
+			let text = self.text.entry(position - 1)?;
+			Ok(text.chars().rev().collect())
+		};
+
+		// Binary search for the first reversed string that is not before
+		// `suffix`, and then for the first one after the strings starting
+		// with it.
+		let lower_bound = |before: &dyn Fn(&str) -> bool| -> Result<usize> {
+			let (mut sta, mut end) = (0, self.text.count());
+			while sta < end {
+				let mid = sta + (end - sta) / 2;
+				if before(&reversed(mid)?) {
+					sta = mid + 1;
+				} else {
+					end = mid;
+				}
+			}
+			Ok(sta)
+		};
+		let sta = lower_bound(&|x| x < suffix.as_str())?;
+		let end = lower_bound(&|x| x < suffix.as_str() || x.starts_with(suffix.as_str()))?;
+
+		let mut positions: Vec<usize> = (sta..end)
+			.map(|n| -> usize { self.reverse[n + 1].into() })
+			.map(|x| x - 1)
+			.collect();
+		positions.sort();
+		Ok(positions)
+	}
+
+	/// Returns the entries for the strings at `positions`.
+	fn entries<I: IntoIterator<Item = usize>>(&self, positions: I) -> Result<Vec<usize>> {
+		Ok(to_entries(self.index.union(positions)?))
+	}
+}
+
+impl Terms {
+	/// Returns a term (expression or reading) by its one-based index.
+	pub fn term(&self, index: usize) -> Result<String> {
+		if index == 0 {
+			Ok(String::new())
+		} else {
+			self.terms.text.entry(index - 1)
+		}
+	}
+
+	/// Returns a glossary definition by its one-based index.
+	pub fn glossary(&self, index: usize) -> Result<String> {
+		if index == 0 {
+			Ok(String::new())
+		} else {
+			self.glossary.entry(index - 1)
+		}
+	}
+
+	/// Searches entries with an expression or reading equal to `text`.
+	pub fn search_exact(&self, text: &str) -> Result<Vec<usize>> {
+		match self.terms.fst.get(text) {
+			Some(index) => Ok(to_entries(self.terms.index.entry(index)?)),
+			None => Ok(Vec::new()),
+		}
+	}
+
+	/// Searches entries with an expression or reading starting with `prefix`.
+	pub fn search_prefix(&self, prefix: &str) -> Result<Vec<usize>> {
+		self.terms.entries(self.terms.fst.prefix(prefix))
+	}
+
+	/// Searches entries with a romaji search key starting with `key`.
+	///
+	/// The `key` must be a search key, as returned by `kana::to_search_key`.
+	pub fn search_romaji(&self, key: &str) -> Result<Vec<usize>> {
+		if key.is_empty() {
+			return Ok(Vec::new());
+		}
+		self.search.entries(self.search.fst.prefix(key))
+	}
+
+	/// Searches entries with an expression or reading accepted by `automaton`
+	/// (e.g. `term_index::fuzzy` or `term_index::regex`).
+	pub fn search_terms_matching<A: Automaton>(&self, automaton: A) -> Result<Vec<usize>> {
+		let terms = self.terms.fst.search(automaton, usize::MAX);
+		self.terms.entries(terms.into_iter().map(|x| x.1))
+	}
+
+	/// Searches entries with a romaji search key accepted by `automaton`.
+	pub fn search_keys_matching<A: Automaton>(&self, automaton: A) -> Result<Vec<usize>> {
+		let keys = self.search.fst.search(automaton, usize::MAX);
+		self.search.entries(keys.into_iter().map(|x| x.1))
+	}
+
+	/// Returns the romaji search keys accepted by `automaton`, with the entries
+	/// for each key.
+	pub fn keys_matching<A: Automaton>(&self, automaton: A) -> Result<Vec<(String, Vec<usize>)>> {
+		let mut output = Vec::new();
+		for (key, index) in self.search.fst.search(automaton, usize::MAX) {
+			let entries = to_entries(self.search.index.entry(index)?);
+			output.push((key, entries));
+		}
+		Ok(output)
+	}
+
+	/// Returns the term index for the expressions and readings.
+	pub fn terms_fst(&self) -> &TermIndex {
+		&self.terms.fst
+	}
+
+	/// Returns the term index for the romaji search keys.
+	pub fn search_fst(&self) -> &TermIndex {
+		&self.search.fst
+	}
+
+	/// Searches entries with all the English `words` in their definitions.
+	/// The words must be lowercase, as returned by `english_words`.
+	pub fn search_english(&self, words: &[String]) -> Result<Vec<usize>> {
+		let mut lists = Vec::with_capacity(words.len());
+		for word in words.iter() {
+			match self.english.find(word) {
+				Some(index) => lists.push(self.english_index.entry(index)?),
+				None => return Ok(Vec::new()),
+			}
+		}
+		Ok(to_entries(intersect(lists)))
+	}
+
+	/// Searches entries with an expression or reading containing all the
+	/// characters in `text`. Only Japanese characters are indexed, so the
+	/// result is empty if `text` has any other character.
+	pub fn search_chars(&self, text: &str) -> Result<Vec<usize>> {
+		let mut lists: Vec<Postings> = Vec::new();
+		for chr in text.chars().filter(|x| !x.is_whitespace()) {
+			let index = self
+				.chars
+				.binary_search_by_key(&(chr as u32), |&x| x.into());
+			match index {
+				Ok(index) => lists.push(self.chars_index.entry(index)?),
+				Err(_) => return Ok(Vec::new()),
+			}
+		}
+		Ok(to_entries(intersect(lists)))
+	}
+
+	/// Searches entries with an expression or reading (or a romaji search key,
+	/// for romaji patterns) matching `pattern`.
+	///
+	/// The candidate strings are the smallest of the ranges with the literal
+	/// prefix of the pattern (using the FST) and with its literal suffix (using
+	/// the reverse index). Without those, the candidates are the terms for the
+	/// entries with all the literal characters in the pattern (using the
+	/// `chars` index). Patterns without literals are rejected when parsed, so
+	/// this never checks every string.
+	pub fn search_pattern(&self, dict: &Dict, pattern: &Pattern) -> Result<Vec<usize>> {
+		let table = if pattern.is_romaji() {
+			&self.search
+		} else {
+			&self.terms
+		};
+
+		let (prefix, suffix) = (pattern.prefix(), pattern.suffix());
+		let mut candidates: Option<Vec<usize>> = None;
+		if !prefix.is_empty() {
+			candidates = Some(table.fst.prefix(&prefix));
+		}
+		if !suffix.is_empty() {
+			let by_suffix = table.suffix(&suffix)?;
+			if candidates
+				.as_ref()
+				.is_none_or(|x| by_suffix.len() < x.len())
+			{
+				candidates = Some(by_suffix);
+			}
+		}
+
+		let candidates = match candidates {
+			Some(candidates) => candidates,
+			None => {
+				let mut candidates = Vec::new();
+				for entry in self.search_chars(&pattern.literals())? {
+					let (expression, reading) = dict.terms(entry)?;
+					for &term in [expression, reading].iter() {
+						if term > 0 {
+							candidates.push(term - 1);
+						}
+					}
+				}
+				candidates.sort();
+				candidates.dedup();
+				candidates
+			}
+		};
+
+		let mut positions = Vec::new();
+		for position in candidates {
+			if pattern.is_match(&table.text.entry(position)?) {
+				positions.push(position);
+			}
+		}
+		table.entries(positions)
+	}
+}
+
+/// Converts the one-based entries from an index to zero-based.
+fn to_entries<I: Iterator<Item = u32>>(entries: I) -> Vec<usize> {
+	entries.map(|x| x as usize - 1).collect()
+}
@@ -1,21 +1,34 @@
-use crate::files::Zip;
+use crate::error::{Error, Result};
+use crate::files::{Container, Rows};
 use crate::raw::RawUint32;
 
 /// Wraps a text file.
+///
+/// The entry offsets are validated on load, so an entry can only fail to load
+/// if its index is out of bounds.
 pub struct Text {
-	index: Vec<RawUint32>,
-	bytes: Vec<u8>,
+	name: String,
+	index: Rows<RawUint32>,
+	bytes: Rows<u8>,
 }
 
 impl Text {
-	pub fn load_text(zip: &mut Zip, name: &str) -> std::io::Result<Text> {
-		let mut file = zip.open(name)?;
+	pub fn load_text(data: &Container, name: &str) -> Result<Text> {
+		let mut file = data.section(name)?;
 		let count: usize = file.read_uint()?.into();
-		let index = file.read_uint_vec(count * 2)?;
+		let index = file.read_uint_vec(count.saturating_mul(2))?;
 		let bytes = file.read_all()?;
+		for (n, entry) in index.chunks(2).enumerate() {
+			let offset: usize = entry[0].into();
+			let length: usize = entry[1].into();
+			if offset.saturating_add(length) > bytes.len() {
+				return Err(Error::bad_index(file.name(), n));
+			}
+		}
 		Ok(Text {
-			index: index,
-			bytes: bytes,
+			name: file.name().to_string(),
+			index,
+			bytes,
 		})
 	}
 
@@ -23,10 +36,55 @@ impl Text {
 		self.index.len() / 2
 	}
 
-	pub fn entry(&self, index: usize) -> String {
+	pub fn entry(&self, index: usize) -> Result<String> {
+		if index >= self.count() {
+			return Err(Error::bad_index(self.name.as_str(), index));
+		}
+		Ok(String::from_utf8_lossy(self.entry_bytes(index)).to_string())
+	}
+
+	fn entry_bytes(&self, index: usize) -> &[u8] {
 		let pos = index * 2;
 		let offset: usize = self.index[pos + 0].into();
 		let length: usize = self.index[pos + 1].into();
-		String::from_utf8_lossy(&self.bytes[offset..offset + length]).to_string()
+		&self.bytes[offset..offset + length]
+	}
+
+	/// Finds an entry by its exact text.
+	///
+	/// This requires the text file to be sorted, which is the case for all
+	/// the string files except for the glossary.
+	pub fn find(&self, text: &str) -> Option<usize> {
+		let text = text.as_bytes();
+		let index = self.lower_bound(|entry| entry < text);
+		if index < self.count() && self.entry_bytes(index) == text {
+			Some(index)
+		} else {
+			None
+		}
+	}
+
+	/// Returns the range of entries starting with `prefix`.
+	///
+	/// This has the same requirements as `find`.
+	pub fn prefix_range(&self, prefix: &str) -> std::ops::Range<usize> {
+		let prefix = prefix.as_bytes();
+		let sta = self.lower_bound(|entry| entry < prefix);
+		let end = self.lower_bound(|entry| entry < prefix || entry.starts_with(prefix));
+		sta..end
+	}
+
+	/// Returns the first entry index for which `before` is false.
+	fn lower_bound<F: Fn(&[u8]) -> bool>(&self, before: F) -> usize {
+		let (mut sta, mut end) = (0, self.count());
+		while sta < end {
+			let mid = sta + (end - sta) / 2;
+			if before(self.entry_bytes(mid)) {
+				sta = mid + 1;
+			} else {
+				end = mid;
+			}
+		}
+		sta
 	}
 }
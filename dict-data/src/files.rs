@@ -1,10 +1,23 @@
-use std::io::{Error, ErrorKind, Read};
+use std::fs::File;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
 
-use crate::raw::cast_vec;
+use memmap2::Mmap;
+
+use crate::error::{Error, Result};
 use crate::raw::RawUint32;
 
-/// Resolves to a literal string with the path to thetop directory for database
-/// files relative to `Cargo.toml`.
+/// Version of the database format supported by this library. Databases built
+/// with a different version of `dict-build` fail to load.
+pub const DATABASE_VERSION: u32 = 4;
+
+/// Name of the database file generated by `dict-build`.
+pub const DATABASE_FILE: &str = "kotoba.db";
+
+/// Resolves to a literal string with the path to the top directory for
+/// database files relative to `Cargo.toml`.
 macro_rules! data_dir {
 	() => {
 		"../data/database"
@@ -12,31 +25,39 @@ macro_rules! data_dir {
 }
 
 /// Simple `include_bytes!` wrapper to include a database file from its name.
+///
+/// The data is aligned to 8 bytes, so that it can be used without copying.
 #[allow(unused_macros)]
 macro_rules! get_file_data {
-	($name: tt) => {
-		include_bytes!(concat!("../", data_dir!(), "/", $name));
-	};
+	($name: tt) => {{
+		#[repr(C, align(8))]
+		struct Aligned<T: ?Sized>(T);
+		static DATA: &'static Aligned<[u8]> =
+			&Aligned(*include_bytes!(concat!("../", data_dir!(), "/", $name)));
+		Some(&DATA.0)
+	}};
 }
 
-/// Loads a database file during development.
+/// Loads a database file during development. Evaluates to `None` if the file
+/// could not be read.
 #[allow(unused_macros)]
 macro_rules! load_file_data {
 	($name: tt) => {
 			({
 			lazy_static! {
-				static ref DATA: Vec<u8> = {
+				static ref DATA: Option<Vec<u8>> = {
 					let mut file_path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
 					file_path.push(concat!(data_dir!(), "/", $name));
-					std::fs::read(file_path).unwrap()
+					std::fs::read(file_path).ok()
 				};
 				}
-			&DATA
+			DATA.as_ref().map(|x| x.as_slice())
 			})
 	};
 }
 
-/// Evaluates to a database file data as a `&'static [u8]` from the filename.
+/// Evaluates to a database file data as a `Option<&'static [u8]>` from the
+/// filename.
 #[cfg(not(any(debug_assertions, feature = "no-embed")))]
 macro_rules! get_file {
 	($name: tt) => {
@@ -44,7 +65,8 @@ macro_rules! get_file {
 	};
 }
 
-/// Evaluates to a database file data as a `&'static [u8]` from the filename.
+/// Evaluates to a database file data as a `Option<&'static [u8]>` from the
+/// filename.
 #[cfg(any(debug_assertions, feature = "no-embed"))]
 macro_rules! get_file {
 	($name: tt) => {
@@ -52,132 +74,515 @@ macro_rules! get_file {
 	};
 }
 
-/// Returns the contents of `chars.zip`.
-#[inline]
-pub fn chars() -> Zip {
-	zip(get_file!("chars.zip"))
+/// Returns the data for the embedded database file.
+pub fn database() -> Result<FileData> {
+	match get_file!("kotoba.db") {
+		Some(data) => Ok(FileData::Static(data)),
+		None => Err(Error::MissingFile(DATABASE_FILE.to_string())),
+	}
 }
 
-/// Returns the contents of `dict.zip`.
-#[inline]
-pub fn dict() -> Zip {
-	zip(get_file!("dict.zip"))
-}
+/// Contents of a database file.
+#[derive(Clone)]
+pub enum FileData {
+	/// Static data, embedded in the binary.
+	Static(&'static [u8]),
+
+	/// Data loaded in memory.
+	Bytes(Arc<Vec<u8>>),
+
+	/// Data copied to a buffer aligned to 8 bytes, with the length in bytes.
+	Aligned(Arc<Vec<u64>>, usize),
 
-/// Returns the contents of `kanji.zip`.
-#[inline]
-pub fn kanji() -> Zip {
-	zip(get_file!("kanji.zip"))
+	/// Memory mapped file.
+	Mapped(Arc<Mmap>),
 }
 
-/// Returns the contents of `meta.zip`.
-#[inline]
-pub fn meta() -> Zip {
-	zip(get_file!("meta.zip"))
+impl FileData {
+	/// Memory maps the file at the given path.
+	pub fn map<P: AsRef<Path>>(path: P) -> std::io::Result<FileData> {
+		let file = File::open(path)?;
+
+		// The mapping is only valid while the file is not modified, which is
+		// a requirement for database files in use.
+		let map = unsafe { Mmap::map(&file)? };
+		Ok(FileData::Mapped(Arc::new(map)))
+	}
+
+	/// Returns the data aligned to 8 bytes, copying it only if necessary.
+	///
+	/// Memory maps are always page aligned, so this only copies data loaded
+	/// in memory with a smaller alignment.
+	pub fn aligned(self) -> FileData {
+		let bytes = self.as_ref();
+		if (bytes.as_ptr() as usize).is_multiple_of(SECTION_ALIGNMENT) {
+			return self;
+		}
+		let mut buffer = vec![0u64; bytes.len().div_ceil(8)];
+		let length = bytes.len();
+		unsafe {
+			let target = buffer.as_mut_ptr() as *mut u8;
+			std::ptr::copy_nonoverlapping(bytes.as_ptr(), target, length);
+		}
+		FileData::Aligned(Arc::new(buffer), length)
+	}
 }
 
-/// Returns the contents of `text.zip`.
-#[inline]
-pub fn text() -> Zip {
-	zip(get_file!("text.zip"))
+impl AsRef<[u8]> for FileData {
+	fn as_ref(&self) -> &[u8] {
+		match self {
+			FileData::Static(data) => data,
+			FileData::Bytes(data) => data.as_slice(),
+			FileData::Aligned(data, length) => unsafe {
+				std::slice::from_raw_parts(data.as_ptr() as *const u8, *length)
+			},
+			FileData::Mapped(data) => &data[..],
+		}
+	}
 }
 
-type ZipArchive = zip::ZipArchive<std::io::Cursor<&'static [u8]>>;
+/// Magic bytes at the start of the database file.
+const MAGIC: &[u8; 8] = b"KOTOBADB";
+
+/// Size of the database file header before the section table.
+const HEADER_SIZE: usize = 16;
 
-/// Simple alias to a ZipArchive.
-pub struct Zip {
-	file: ZipArchive,
+/// Size of a section name in the section table. Names are zero padded.
+const SECTION_NAME_SIZE: usize = 32;
+
+/// Size of an entry in the section table.
+const SECTION_ENTRY_SIZE: usize = SECTION_NAME_SIZE + 8;
+
+/// Alignment of the section data in the database file.
+const SECTION_ALIGNMENT: usize = 8;
+
+/// The single file database container generated by `dict-build`.
+///
+/// The container is a list of named sections, stored uncompressed so that the
+/// file can be memory mapped and the sections used in place. The format of the
+/// file is:
+///
+/// ```
+///     Database {
+///         Magic:        [u8; 8] = "KOTOBADB",
+///         Version:      u32_le,
+///         SectionCount: u32_le,
+///         Sections:     [Section; SectionCount],
+///         Data:         [u8],
+///     }
+///
+///     Section {
+///         Name:   [u8; 32],
+///         Offset: u32_le,
+///         Length: u32_le,
+///     }
+/// ```
+///
+/// Section names are zero padded (e.g. `dict/index`, `text/glossary`) and the
+/// table is sorted by name. Offsets are from the start of the file and aligned
+/// to 8 bytes. Sections do not overlap each other or the section table.
+///
+/// The header and section table are validated on load.
+#[derive(Clone)]
+pub struct Container {
+	data: FileData,
+	sections: Arc<Vec<SectionEntry>>,
 }
 
-/// Creates a Zip from the data bytes.
-pub fn zip(data: &'static [u8]) -> Zip {
-	let data = std::io::Cursor::new(data);
-	Zip {
-		file: ZipArchive::new(data).unwrap(),
-	}
+struct SectionEntry {
+	name: String,
+	offset: usize,
+	length: usize,
 }
 
-impl Zip {
-	/// Number of files in the container.
+impl Container {
+	/// Opens the container from the data of the database file.
+	pub fn new(data: FileData) -> Result<Container> {
+		let data = data.aligned();
+		let bytes = data.as_ref();
+		let uint = |offset: usize| -> usize {
+			let value = RawUint32::from_bytes([
+				bytes[offset],
+				bytes[offset + 1],
+				bytes[offset + 2],
+				bytes[offset + 3],
+			]);
+			value.into()
+		};
+
+		if bytes.len() < HEADER_SIZE || &bytes[..8] != MAGIC {
+			return Err(Error::invalid_data(DATABASE_FILE, "not a database file"));
+		}
+		let version = uint(8) as u32;
+		if version != DATABASE_VERSION {
+			return Err(Error::VersionMismatch {
+				expected: DATABASE_VERSION,
+				found: version,
+			});
+		}
+
+		let count = uint(12);
+		let table_size = count.saturating_mul(SECTION_ENTRY_SIZE);
+		if HEADER_SIZE.saturating_add(table_size) > bytes.len() {
+			return Err(Error::TruncatedPage(DATABASE_FILE.to_string()));
+		}
+
+		let mut sections: Vec<SectionEntry> = Vec::with_capacity(count);
+		for n in 0..count {
+			let entry = HEADER_SIZE + n * SECTION_ENTRY_SIZE;
+			let name = &bytes[entry..entry + SECTION_NAME_SIZE];
+			let name_length = name.iter().position(|&x| x == 0).unwrap_or(name.len());
+			let name = String::from_utf8_lossy(&name[..name_length]).to_string();
+			let offset = uint(entry + SECTION_NAME_SIZE);
+			let length = uint(entry + SECTION_NAME_SIZE + 4);
+			if offset % SECTION_ALIGNMENT != 0 {
+				return Err(Error::invalid_data(name, "section is not aligned"));
+			}
+			if offset < HEADER_SIZE + table_size {
+				return Err(Error::invalid_data(
+					name,
+					"section overlaps the section table",
+				));
+			}
+			if offset.saturating_add(length) > bytes.len() {
+				return Err(Error::TruncatedPage(name));
+			}
+			if let Some(last) = sections.last() {
+				if last.name >= name {
+					let message = "section table is not sorted";
+					return Err(Error::invalid_data(DATABASE_FILE, message));
+				}
+			}
+			sections.push(SectionEntry {
+				name,
+				offset,
+				length,
+			});
+		}
+
+		let mut ranges: Vec<&SectionEntry> = sections.iter().collect();
+		ranges.sort_by_key(|x| (x.offset, x.length));
+		for pair in ranges.windows(2) {
+			if pair[0].offset + pair[0].length > pair[1].offset {
+				let message = format!("section overlaps `{}`", pair[0].name);
+				return Err(Error::invalid_data(pair[1].name.as_str(), message));
+			}
+		}
+
+		Ok(Container {
+			data,
+			sections: Arc::new(sections),
+		})
+	}
+
+	/// Number of sections in the container.
 	pub fn count(&self) -> usize {
-		self.file.len()
+		self.sections.len()
 	}
 
-	pub fn open<'a>(&'a mut self, name: &str) -> std::io::Result<ZipFile<'a>> {
-		Ok(ZipFile {
-			file: self.file.by_name(name)?,
-		})
+	/// Total size of the database file in bytes.
+	pub fn size(&self) -> usize {
+		self.data.as_ref().len()
 	}
 
-	/// Read a file by name to a typed vector.
-	pub fn read_vec<T: Sized>(&mut self, name: &str) -> std::io::Result<Vec<T>> {
-		let mut file = self.open(name)?;
-		file.read_vec::<T>()
+	/// Names of the sections in the container, in sorted order.
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.sections.iter().map(|x| x.name.as_str())
 	}
-}
 
-pub struct ZipFile<'a> {
-	file: zip::read::ZipFile<'a>,
-}
+	/// Opens a section by its name (e.g. `dict/index`).
+	pub fn section(&self, name: &str) -> Result<Section> {
+		let index = self
+			.sections
+			.binary_search_by(|x| x.name.as_str().cmp(name))
+			.map_err(|_| Error::MissingFile(name.to_string()))?;
+		let entry = &self.sections[index];
+		Ok(Section {
+			name: entry.name.clone(),
+			data: self.data.clone(),
+			start: entry.offset,
+			end: entry.offset + entry.length,
+			position: entry.offset,
+		})
+	}
 
-impl<'a> ZipFile<'a> {
-	/// Read the whole content of the file as a typed vector.
-	pub fn read_vec<T: Sized>(&mut self) -> std::io::Result<Vec<T>> {
-		let row_size: usize = std::mem::size_of::<T>();
+	/// Returns the contents of a section by its name.
+	pub fn section_bytes(&self, name: &str) -> Result<&[u8]> {
+		let section = self.section(name)?;
+		Ok(&self.data.as_ref()[section.start..section.end])
+	}
 
-		// Check that the file size is valid for the given row size.
-		let file_size = self.file.size() as usize;
-		if file_size % row_size != 0 {
-			let err = Error::new(
-				ErrorKind::InvalidData,
-				format!("index file size is invalid ({} bytes)", file_size),
-			);
-			return Err(err);
-		}
+	/// Returns a whole section by name as typed rows.
+	pub fn rows<T: Sized>(&self, name: &str) -> Result<Rows<T>> {
+		let mut section = self.section(name)?;
+		section.read_vec::<T>()
+	}
+}
 
-		// Allocate a vector large enough to hold all the rows. We need to
-		// allocate as T to respect the type alignment.
-		let buffer: Vec<T> = Vec::with_capacity(file_size / row_size);
+/// A section of the database file, read sequentially.
+///
+/// Reading from a section does not copy any data. The returned `Rows` point
+/// directly into the database file.
+pub struct Section {
+	name: String,
+	data: FileData,
+	start: usize,
+	end: usize,
+	position: usize,
+}
 
-		// Cast the buffer to bytes so that it can be used as a reading buffer.
-		let mut buffer: Vec<u8> = unsafe { cast_vec(buffer) };
+impl Section {
+	/// Full name of the section (e.g. `dict/0000`).
+	pub fn name(&self) -> &str {
+		&self.name
+	}
 
-		// Read the index.
-		self.file.read_to_end(&mut buffer)?;
+	/// Size of the section in bytes.
+	pub fn size(&self) -> usize {
+		self.end - self.start
+	}
 
-		// Cast the now-filled buffer back to the target type vector.
-		let buffer: Vec<T> = unsafe { cast_vec(buffer) };
+	/// Contents of the whole section.
+	pub fn bytes(&self) -> &[u8] {
+		&self.data.as_ref()[self.start..self.end]
+	}
 
-		Ok(buffer)
+	/// Reads the remaining content of the section as typed rows.
+	pub fn read_vec<T: Sized>(&mut self) -> Result<Rows<T>> {
+		let row_size = std::mem::size_of::<T>();
+		let remaining = self.end - self.position;
+		if !remaining.is_multiple_of(row_size) {
+			return Err(Error::TruncatedPage(self.name.clone()));
+		}
+		self.read_rows(remaining / row_size)
 	}
 
-	pub fn read_uint(&mut self) -> std::io::Result<RawUint32> {
-		let mut buffer = [0; 4];
-		self.file.read_exact(&mut buffer)?;
-		Ok(RawUint32::from_bytes(&buffer))
+	pub fn read_uint(&mut self) -> Result<RawUint32> {
+		if self.end - self.position < 4 {
+			return Err(Error::TruncatedPage(self.name.clone()));
+		}
+		let bytes = &self.data.as_ref()[self.position..self.position + 4];
+		let value = RawUint32::from_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+		self.position += 4;
+		Ok(value)
 	}
 
-	pub fn read_uint_list(&mut self) -> std::io::Result<Vec<RawUint32>> {
+	pub fn read_uint_list(&mut self) -> Result<Rows<RawUint32>> {
 		let count = self.read_uint()?;
 		let count: usize = count.into();
 		self.read_uint_vec(count)
 	}
 
-	pub fn read_uint_vec(&mut self, count: usize) -> std::io::Result<Vec<RawUint32>> {
-		let mut buffer: Vec<RawUint32> = Vec::with_capacity(count);
-		let mut buffer: Vec<u8> = unsafe {
-			buffer.set_len(count);
-			cast_vec(buffer)
+	pub fn read_uint_vec(&mut self, count: usize) -> Result<Rows<RawUint32>> {
+		self.read_rows(count)
+	}
+
+	/// Reads the remaining content of the section as bytes.
+	pub fn read_all(&mut self) -> Result<Rows<u8>> {
+		self.read_vec()
+	}
+
+	/// Reads `count` rows of `T`, which must be aligned in the file.
+	pub fn read_rows<T: Sized>(&mut self, count: usize) -> Result<Rows<T>> {
+		let size = match count.checked_mul(std::mem::size_of::<T>()) {
+			Some(size) if size <= self.end - self.position => size,
+			_ => return Err(Error::TruncatedPage(self.name.clone())),
 		};
+		let address = self.data.as_ref().as_ptr() as usize + self.position;
+		if !address.is_multiple_of(std::mem::align_of::<T>()) {
+			let message = "data is not aligned";
+			return Err(Error::invalid_data(self.name.as_str(), message));
+		}
+		let rows = Rows {
+			data: self.data.clone(),
+			offset: self.position,
+			len: count,
+			row_type: PhantomData,
+		};
+		self.position += size;
+		Ok(rows)
+	}
+}
+
+/// Typed rows from the database file, used in place of a `Vec<T>` without
+/// copying the data.
+///
+/// The rows keep a reference to the database file data, so they are cheap to
+/// clone and can outlive the `Container`.
+pub struct Rows<T> {
+	data: FileData,
+	offset: usize,
+	len: usize,
+	row_type: PhantomData<T>,
+}
+
+impl<T> Clone for Rows<T> {
+	fn clone(&self) -> Rows<T> {
+		Rows {
+			data: self.data.clone(),
+			offset: self.offset,
+			len: self.len,
+			row_type: PhantomData,
+		}
+	}
+}
+
+impl<T> Deref for Rows<T> {
+	type Target = [T];
+
+	#[inline]
+	fn deref(&self) -> &[T] {
+		// The offset, length and alignment are checked by `Section::read_rows`
+		// and the data is immutable.
+		unsafe {
+			let data = self.data.as_ref().as_ptr().add(self.offset);
+			std::slice::from_raw_parts(data as *const T, self.len)
+		}
+	}
+}
+
+impl AsRef<[u8]> for Rows<u8> {
+	fn as_ref(&self) -> &[u8] {
+		self
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+
+	/// Returns the contents of a database file with the given sections, which
+	/// must be sorted by name.
+	pub fn container_bytes(sections: &[(&str, &[u8])]) -> Vec<u8> {
+		let mut table = Vec::new();
+		let mut offset = HEADER_SIZE + sections.len() * SECTION_ENTRY_SIZE;
+		for (name, data) in sections {
+			offset = offset.next_multiple_of(SECTION_ALIGNMENT);
+			table.push((*name, offset, data.len()));
+			offset += data.len();
+		}
+		let mut bytes = table_bytes(DATABASE_VERSION, &table, offset);
+		for (&(_, offset, length), (_, data)) in table.iter().zip(sections) {
+			bytes[offset..offset + length].copy_from_slice(data);
+		}
+		bytes
+	}
+
+	/// Returns a database file with a section table of names, offsets and
+	/// lengths, padded with zeros to `size` bytes.
+	fn table_bytes(version: u32, sections: &[(&str, usize, usize)], size: usize) -> Vec<u8> {
+		let mut bytes = MAGIC.to_vec();
+		bytes.extend_from_slice(&version.to_le_bytes());
+		bytes.extend_from_slice(&(sections.len() as u32).to_le_bytes());
+		for &(name, offset, length) in sections {
+			let mut entry = name.as_bytes().to_vec();
+			entry.resize(SECTION_NAME_SIZE, 0);
+			bytes.extend_from_slice(&entry);
+			bytes.extend_from_slice(&(offset as u32).to_le_bytes());
+			bytes.extend_from_slice(&(length as u32).to_le_bytes());
+		}
+		bytes.resize(size.max(bytes.len()), 0);
+		bytes
+	}
 
-		self.file.read_exact(&mut buffer)?;
-		let buffer = unsafe { cast_vec(buffer) };
-		Ok(buffer)
+	fn open(bytes: Vec<u8>) -> Result<Container> {
+		Container::new(FileData::Bytes(Arc::new(bytes)))
 	}
 
-	pub fn read_all(&mut self) -> std::io::Result<Vec<u8>> {
-		let mut buffer = Vec::new();
-		self.file.read_to_end(&mut buffer)?;
-		Ok(buffer)
+	fn open_error(bytes: Vec<u8>) -> String {
+		match open(bytes) {
+			Ok(_) => panic!("expected an error"),
+			Err(err) => err.to_string(),
+		}
+	}
+
+	#[test]
+	fn test_container() {
+		let bytes = container_bytes(&[("a", b"abc"), ("b/c", b""), ("b/d", b"0123456789")]);
+		let container = open(bytes).unwrap();
+		assert_eq!(3, container.count());
+		assert_eq!(
+			vec!["a", "b/c", "b/d"],
+			container.names().collect::<Vec<_>>()
+		);
+		assert_eq!(b"abc", container.section_bytes("a").unwrap());
+		assert_eq!(b"", container.section_bytes("b/c").unwrap());
+		assert_eq!(b"0123456789", container.section_bytes("b/d").unwrap());
+		assert!(matches!(container.section("b"), Err(Error::MissingFile(_))));
+
+		let container = open(table_bytes(DATABASE_VERSION, &[], 0)).unwrap();
+		assert_eq!(0, container.count());
+		assert_eq!(HEADER_SIZE, container.size());
+	}
+
+	#[test]
+	fn test_container_errors() {
+		let not_database = "invalid data in kotoba.db: not a database file";
+		assert_eq!(not_database, open_error(Vec::new()));
+		assert_eq!(not_database, open_error(MAGIC.to_vec()));
+		let mut bytes = container_bytes(&[("a", b"abc")]);
+		bytes[0] = b'k';
+		assert_eq!(not_database, open_error(bytes));
+
+		let version = DATABASE_VERSION + 1;
+		assert_eq!(
+			format!(
+				"database version is {} (expected version {})",
+				version, DATABASE_VERSION
+			),
+			open_error(table_bytes(version, &[], 0))
+		);
+
+		// The table is larger than the file.
+		let mut bytes = table_bytes(DATABASE_VERSION, &[("a", 56, 0)], 0);
+		bytes[12] = 2;
+		assert_eq!("database file kotoba.db is truncated", open_error(bytes));
+
+		// Sections must be in the file, after the table and aligned.
+		let table = HEADER_SIZE + 2 * SECTION_ENTRY_SIZE;
+		let check = |sections: &[(&str, usize, usize)], expected: &str| {
+			let bytes = table_bytes(DATABASE_VERSION, sections, table + 16);
+			assert_eq!(expected, open_error(bytes), "sections {:?}", sections);
+		};
+		check(
+			&[("a", table, 8), ("b", table + 8, 9)],
+			"database file b is truncated",
+		);
+		check(
+			&[("a", table, 8), ("b", table + 24, 0)],
+			"database file b is truncated",
+		);
+		check(
+			&[("a", table, 8), ("b", table + 4, 4)],
+			"invalid data in b: section is not aligned",
+		);
+		check(
+			&[("a", table - 8, 8), ("b", table, 8)],
+			"invalid data in a: section overlaps the section table",
+		);
+		check(
+			&[("a", table, 16), ("b", table + 8, 8)],
+			"invalid data in b: section overlaps `a`",
+		);
+		check(
+			&[("a", table + 8, 8), ("b", table, 16)],
+			"invalid data in a: section overlaps `b`",
+		);
+		check(
+			&[("b", table, 8), ("a", table + 8, 8)],
+			"invalid data in kotoba.db: section table is not sorted",
+		);
+		check(
+			&[("a", table, 8), ("a", table + 8, 8)],
+			"invalid data in kotoba.db: section table is not sorted",
+		);
+
+		// Empty sections don't overlap.
+		let bytes = table_bytes(
+			DATABASE_VERSION,
+			&[("a", table, 8), ("b", table + 8, 0)],
+			table + 8,
+		);
+		assert_eq!(2, open(bytes).unwrap().count());
 	}
 }
@@ -1,54 +1,106 @@
-#![feature(vec_into_raw_parts)]
-
 #[macro_use]
 extern crate lazy_static;
 
-extern crate zip;
+extern crate fst;
+extern crate kana;
+extern crate levenshtein_automata;
+extern crate memmap2;
+extern crate regex_automata;
+extern crate serde;
+extern crate serde_json;
+extern crate sha2;
 
+mod analyze;
+mod database;
+mod deinflect;
+mod dictionary;
+mod error;
 mod file_dict;
+mod file_examples;
+mod file_index;
+mod file_kanji;
+mod file_meta;
+mod file_names;
+mod file_strokes;
+mod file_terms;
 pub mod file_text;
 mod files;
+mod manifest;
+mod page_cache;
+mod pattern;
+mod postings;
+mod query;
 mod raw;
+mod term_index;
+mod user_dict;
 
-pub use file_dict::Dict;
+pub use analyze::{Analysis, Furigana, Sentence, Span, Token, MAX_TOKEN_ENTRIES, MAX_TOKEN_LENGTH};
+pub use database::Database;
+pub use deinflect::{deinflect, Deinflection};
+pub use dictionary::{Definition, Dictionary, Entry, FuzzyMatch, SearchMode};
+pub use error::{Error, Result};
+pub use file_dict::{Dict, DictEntry, EntryDefinition};
+pub use file_examples::{Example, Examples};
+pub use file_kanji::{Kanji, KanjiDict, KanjiInfo, KanjiReferences};
+pub use file_meta::{Meta, Tag};
+pub use files::{DATABASE_FILE, DATABASE_VERSION};
+pub use file_names::{NameEntry, NameType, Names};
+pub use file_strokes::{KanjiStrokes, Stroke, StrokeGroup, Strokes};
+pub use file_terms::Terms;
+pub use manifest::{Manifest, ManifestCounts, ManifestPart, ManifestSource, MANIFEST_FILE};
+pub use page_cache::{CacheStats, DEFAULT_CACHE_SIZE};
+pub use pattern::Pattern;
+pub use postings::{intersect, union, Intersection, PostingIndex, Postings, Union};
+pub use query::Query;
+pub use term_index::MAX_FUZZY_DISTANCE;
+pub use user_dict::{UserDict, UserEntry};
 
 pub fn version() -> &'static str {
 	env!("CARGO_PKG_VERSION")
 }
 
-pub fn load() {
-	use files::Zip;
-
-	let chars: Zip = files::chars();
-	let dict: Zip = files::dict();
-	let kanji: Zip = files::kanji();
-	let meta: Zip = files::meta();
-	let text: Zip = files::text();
-	let total_files: usize =
-		chars.count() + dict.count() + kanji.count() + meta.count() + text.count();
-	println!("Loaded {} files", total_files);
+pub fn load() -> Result<()> {
+	let data = files::Container::new(files::database()?)?;
+	println!(
+		"Loaded {} sections ({})",
+		data.count(),
+		bytes(data.size())
+	);
 
-	let dict: Dict = Dict::new(dict).unwrap();
+	let dict: Dict = Dict::new(&data)?;
 	println!("Loaded {} dictionary entries", dict.count());
 
-	let mut text = text;
-	let terms_text = file_text::Text::load_text(&mut text, "terms_text").unwrap();
+	let kanji: KanjiDict = KanjiDict::new(&data)?;
+	println!("Loaded {} kanji", kanji.count());
+
+	let names: Names = Names::new(&data)?;
+	println!("Loaded {} names", names.count());
+
+	let examples: Examples = Examples::new(&data)?;
+	println!("Loaded {} example sentences", examples.count());
+
+	let strokes: Strokes = Strokes::new(&data)?;
+	println!("Loaded {} kanji strokes", strokes.count());
+
+	let terms_text = file_text::Text::load_text(&data, "text/terms_text")?;
 	println!("Loaded {} terms text entries", terms_text.count());
 
 	for i in 0..10 {
 		let base = i * 100;
-		println!("Entry {} - {}", base + 0, terms_text.entry(base + 0));
-		println!("Entry {} - {}", base + 1, terms_text.entry(base + 1));
-		println!("Entry {} - {}", base + 2, terms_text.entry(base + 2));
-		println!("Entry {} - {}", base + 3, terms_text.entry(base + 3));
-		println!("Entry {} - {}", base + 4, terms_text.entry(base + 4));
-		println!("Entry {} - {}", base + 5, terms_text.entry(base + 5));
+		println!("Entry {} - {}", base, terms_text.entry(base)?);
+		println!("Entry {} - {}", base + 1, terms_text.entry(base + 1)?);
+		println!("Entry {} - {}", base + 2, terms_text.entry(base + 2)?);
+		println!("Entry {} - {}", base + 3, terms_text.entry(base + 3)?);
+		println!("Entry {} - {}", base + 4, terms_text.entry(base + 4)?);
+		println!("Entry {} - {}", base + 5, terms_text.entry(base + 5)?);
 	}
 
 	let count = terms_text.count();
 	for i in (1..=20).rev() {
-		println!("Entry {} - {}", count - i, terms_text.entry(count - i));
+		println!("Entry {} - {}", count - i, terms_text.entry(count - i)?);
 	}
+
+	Ok(())
 }
 
 fn bytes(value: usize) -> String {
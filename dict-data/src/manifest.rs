@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use crate::files::{Container, DATABASE_VERSION};
+
+/// Name of the manifest section in the database file.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Database manifest, generated by `dict-build` as the `manifest.json` section
+/// of the database file.
+///
+/// The manifest identifies the database and is used to check the database
+/// sections when loading.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Manifest {
+	/// Database format version, see `DATABASE_VERSION`.
+	pub version: u32,
+
+	/// Build time of the database, in seconds since the Unix epoch.
+	pub created: u64,
+
+	/// Database parts with their size and checksum.
+	pub parts: Vec<ManifestPart>,
+
+	/// Number of entries in the database.
+	pub counts: ManifestCounts,
+
+	/// Source dictionaries imported in the database.
+	pub sources: Vec<ManifestSource>,
+}
+
+/// A database part listed in the manifest. A part is the group of sections
+/// with the same name prefix (e.g. `dict/index` and `dict/0000` for `dict`).
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ManifestPart {
+	/// Part name (e.g. `dict`).
+	pub name: String,
+
+	/// Total size of the part sections in bytes.
+	pub size: u64,
+
+	/// SHA-256 of the part sections as lowercase hex. The sections are hashed
+	/// in the order of their names.
+	pub sha256: String,
+}
+
+/// Number of entries in each part of the database.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct ManifestCounts {
+	/// Main dictionary entries.
+	pub entries: usize,
+	pub kanji: usize,
+	pub names: usize,
+	pub examples: usize,
+
+	/// Kanji with stroke order data.
+	pub strokes: usize,
+}
+
+/// A source dictionary imported in the database.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ManifestSource {
+	/// Dictionary title (e.g. `JMdict (English)`).
+	pub title: String,
+
+	/// Dictionary revision, may be empty.
+	pub revision: String,
+}
+
+impl Manifest {
+	/// Parses the manifest section and checks the format version.
+	pub fn parse(data: &[u8]) -> Result<Manifest> {
+		let manifest: Manifest =
+			serde_json::from_slice(data).map_err(|err| Error::invalid_data(MANIFEST_FILE, err))?;
+		if manifest.version != DATABASE_VERSION {
+			return Err(Error::VersionMismatch {
+				expected: DATABASE_VERSION,
+				found: manifest.version,
+			});
+		}
+		Ok(manifest)
+	}
+
+	/// Checks the size of each database part. This is cheap, as it only uses
+	/// the section table.
+	pub fn check_sizes(&self, data: &Container) -> Result<()> {
+		for part in self.parts.iter() {
+			let size: usize = part_sections(data, &part.name)?
+				.iter()
+				.map(|x| x.len())
+				.sum();
+			if size as u64 != part.size {
+				return Err(Error::ChecksumMismatch(part.name.clone()));
+			}
+		}
+		Ok(())
+	}
+
+	/// Checks each database part against its size and checksum. This reads
+	/// the whole database file.
+	pub fn verify(&self, data: &Container) -> Result<()> {
+		self.check_sizes(data)?;
+		for part in self.parts.iter() {
+			let mut hasher = Sha256::new();
+			for section in part_sections(data, &part.name)? {
+				hasher.update(section);
+			}
+			let sha256 = format!("{:x}", hasher.finalize());
+			if sha256 != part.sha256.to_lowercase() {
+				return Err(Error::ChecksumMismatch(part.name.clone()));
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Returns the contents of the sections of a database part, in name order.
+fn part_sections<'a>(data: &'a Container, part: &str) -> Result<Vec<&'a [u8]>> {
+	let prefix = format!("{}/", part);
+	data.names()
+		.filter(|x| x.starts_with(&prefix))
+		.map(|x| data.section_bytes(x))
+		.collect()
+}
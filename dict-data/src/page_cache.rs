@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::Serialize;
+
+use crate::error::Result;
+
+/// Number of independently locked shards in a `PageCache`.
+const SHARDS: usize = 16;
+
+/// Default size of the dictionary page cache, in bytes.
+pub const DEFAULT_CACHE_SIZE: usize = 32 * 1024 * 1024;
+
+/// Bounded cache of decoded pages, which evicts the least recently used pages
+/// when the total size of the pages exceeds the capacity.
+///
+/// Pages are immutable and shared as `Arc`, so a lock is only held to look up
+/// or insert a page, never while a page is loaded or used. The cache is split
+/// by page number in shards, each with its own lock and an equal part of the
+/// capacity, so that concurrent readers don't contend on a single lock.
+pub struct PageCache<T> {
+	shards: Vec<Mutex<Shard<T>>>,
+	capacity: usize,
+	clock: AtomicU64,
+	hits: AtomicU64,
+	misses: AtomicU64,
+}
+
+struct Shard<T> {
+	pages: HashMap<usize, CachedPage<T>>,
+	size: usize,
+}
+
+struct CachedPage<T> {
+	page: Arc<T>,
+	size: usize,
+	last_used: u64,
+}
+
+/// Statistics for a page cache.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct CacheStats {
+	/// Number of lookups served from the cache.
+	pub hits: u64,
+
+	/// Number of lookups that had to load the page.
+	pub misses: u64,
+
+	/// Number of pages in the cache.
+	pub pages: usize,
+
+	/// Total size of the cached pages, in bytes, as estimated when loaded.
+	pub size: usize,
+
+	/// Maximum size of the cached pages, in bytes.
+	pub capacity: usize,
+}
+
+impl<T> PageCache<T> {
+	/// Creates an empty cache with a capacity in bytes. A zero capacity
+	/// disables the cache.
+	pub fn new(capacity: usize) -> PageCache<T> {
+		PageCache {
+			shards: (0..SHARDS)
+				.map(|_| {
+					Mutex::new(Shard {
+						pages: HashMap::new(),
+						size: 0,
+					})
+				})
+				.collect(),
+			capacity,
+			clock: AtomicU64::new(0),
+			hits: AtomicU64::new(0),
+			misses: AtomicU64::new(0),
+		}
+	}
+
+	/// Changes the cache capacity in bytes, evicting pages as necessary.
+	pub fn set_capacity(&mut self, capacity: usize) {
+		self.capacity = capacity;
+		for shard in self.shards.iter_mut() {
+			let shard = match shard.get_mut() {
+				Ok(shard) => shard,
+				Err(poisoned) => poisoned.into_inner(),
+			};
+			shard.evict(capacity / SHARDS);
+		}
+	}
+
+	/// Returns a page from the cache, or loads it with `load` if the page is
+	/// not cached. The `load` function returns the page and its size in bytes.
+	///
+	/// Pages larger than a cache shard are returned without being cached.
+	pub fn get_or_load<F>(&self, number: usize, load: F) -> Result<Arc<T>>
+	where
+		F: FnOnce() -> Result<(T, usize)>,
+	{
+		let tick = self.clock.fetch_add(1, Ordering::Relaxed);
+		if let Some(page) = self.shard(number).get(number, tick) {
+			self.hits.fetch_add(1, Ordering::Relaxed);
+			return Ok(page);
+		}
+		self.misses.fetch_add(1, Ordering::Relaxed);
+
+		// Concurrent misses for the same page may load it more than once, but
+		// only one copy is kept in the cache.
+		let (page, size) = load()?;
+		let page = Arc::new(page);
+		let limit = self.capacity / SHARDS;
+		if size <= limit {
+			let mut shard = self.shard(number);
+			shard.insert(
+				number,
+				CachedPage {
+					page: Arc::clone(&page),
+					size,
+					last_used: tick,
+				},
+			);
+			shard.evict(limit);
+		}
+		Ok(page)
+	}
+
+	/// Returns the current cache statistics.
+	pub fn stats(&self) -> CacheStats {
+		let mut stats = CacheStats {
+			hits: self.hits.load(Ordering::Relaxed),
+			misses: self.misses.load(Ordering::Relaxed),
+			pages: 0,
+			size: 0,
+			capacity: self.capacity,
+		};
+		for number in 0..SHARDS {
+			let shard = self.shard(number);
+			stats.pages += shard.pages.len();
+			stats.size += shard.size;
+		}
+		stats
+	}
+
+	fn shard(&self, number: usize) -> MutexGuard<'_, Shard<T>> {
+		// Shards are never left in an inconsistent state, so a poisoned lock
+		// is still safe to use.
+		match self.shards[number % SHARDS].lock() {
+			Ok(shard) => shard,
+			Err(poisoned) => poisoned.into_inner(),
+		}
+	}
+}
+
+impl<T> Shard<T> {
+	fn get(&mut self, number: usize, tick: u64) -> Option<Arc<T>> {
+		let cached = self.pages.get_mut(&number)?;
+		cached.last_used = tick;
+		Some(Arc::clone(&cached.page))
+	}
+
+	fn insert(&mut self, number: usize, page: CachedPage<T>) {
+		self.size += page.size;
+		if let Some(previous) = self.pages.insert(number, page) {
+			self.size -= previous.size;
+		}
+	}
+
+	/// Evicts the least recently used pages until the shard size is at most
+	/// `limit` bytes.
+	fn evict(&mut self, limit: usize) {
+		while self.size > limit {
+			let number = match self.pages.iter().min_by_key(|(_, x)| x.last_used) {
+				Some((&number, _)) => number,
+				None => break,
+			};
+			if let Some(page) = self.pages.remove(&number) {
+				self.size -= page.size;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::error::Error;
+
+	/// Page size used in tests, with a shard capacity of three pages.
+	const PAGE_SIZE: usize = 10;
+	const CAPACITY: usize = 3 * PAGE_SIZE * SHARDS;
+
+	/// Returns whether the page was loaded, rather than found in the cache.
+	fn get(cache: &PageCache<usize>, number: usize, size: usize) -> bool {
+		let mut loaded = false;
+		let page = cache
+			.get_or_load(number, || {
+				loaded = true;
+				Ok((number, size))
+			})
+			.unwrap();
+		assert_eq!(number, *page);
+		loaded
+	}
+
+	#[test]
+	fn test_eviction_order() {
+		// Pages 0, 16, 32 and 48 are in the same shard.
+		let cache = PageCache::new(CAPACITY);
+		assert!(get(&cache, 0, PAGE_SIZE));
+		assert!(get(&cache, 16, PAGE_SIZE));
+		assert!(get(&cache, 32, PAGE_SIZE));
+		assert!(!get(&cache, 0, PAGE_SIZE));
+
+		// Page 16 is now the least recently used.
+		assert!(get(&cache, 48, PAGE_SIZE));
+		assert!(!get(&cache, 0, PAGE_SIZE));
+		assert!(!get(&cache, 32, PAGE_SIZE));
+		assert!(!get(&cache, 48, PAGE_SIZE));
+
+		// Loading page 16 again evicts page 0.
+		assert!(get(&cache, 16, PAGE_SIZE));
+		assert!(!get(&cache, 48, PAGE_SIZE));
+
+		// Other shards are not affected.
+		assert!(get(&cache, 1, PAGE_SIZE));
+		assert!(!get(&cache, 16, PAGE_SIZE));
+		assert!(!get(&cache, 32, PAGE_SIZE));
+		assert!(get(&cache, 0, PAGE_SIZE));
+	}
+
+	#[test]
+	fn test_capacity() {
+		let cache = PageCache::new(CAPACITY);
+		for number in 0..1000 {
+			get(&cache, number, PAGE_SIZE);
+		}
+		let stats = cache.stats();
+		assert_eq!(CAPACITY, stats.capacity);
+		assert_eq!(CAPACITY, stats.size);
+		assert_eq!(3 * SHARDS, stats.pages);
+
+		// Pages larger than a shard are not cached.
+		let mut cache = PageCache::new(CAPACITY);
+		assert!(get(&cache, 0, 4 * PAGE_SIZE));
+		assert!(get(&cache, 0, 4 * PAGE_SIZE));
+		assert_eq!(0, cache.stats().pages);
+
+		// Reducing the capacity evicts pages.
+		for number in 0..3 * SHARDS {
+			get(&cache, number, PAGE_SIZE);
+		}
+		cache.set_capacity(CAPACITY / 3);
+		let stats = cache.stats();
+		assert_eq!(SHARDS, stats.pages);
+		assert_eq!(CAPACITY / 3, stats.size);
+
+		// A zero capacity disables the cache.
+		let cache = PageCache::new(0);
+		assert!(get(&cache, 0, 1));
+		assert!(get(&cache, 0, 1));
+		assert_eq!(0, cache.stats().pages);
+	}
+
+	#[test]
+	fn test_stats() {
+		let cache = PageCache::new(CAPACITY);
+		get(&cache, 0, PAGE_SIZE);
+		get(&cache, 1, PAGE_SIZE);
+		get(&cache, 0, PAGE_SIZE);
+		get(&cache, 0, PAGE_SIZE);
+		let stats = cache.stats();
+		assert_eq!(2, stats.hits);
+		assert_eq!(2, stats.misses);
+		assert_eq!(2, stats.pages);
+		assert_eq!(2 * PAGE_SIZE, stats.size);
+
+		// Failed loads count as misses and are not cached.
+		let result = cache.get_or_load(2, || Err(Error::InvalidQuery("test".to_string())));
+		assert!(result.is_err());
+		let stats = cache.stats();
+		assert_eq!(3, stats.misses);
+		assert_eq!(2, stats.pages);
+	}
+}
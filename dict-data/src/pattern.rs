@@ -0,0 +1,371 @@
+use crate::error::{Error, Result};
+
+/// Wildcard pattern for `Dictionary::search_pattern`.
+///
+/// The pattern syntax is:
+///
+/// - `?` (or `？`) matches any single character.
+/// - `*` (or `＊`) matches any run of characters, including none.
+/// - `[漢字]`, `[かな]`, `[ひらがな]` and `[カタカナ]` match any single kanji,
+///   kana, hiragana or katakana character, respectively.
+/// - `[...]` with any other characters matches a single one of them (e.g.
+///   `[はば]`). Full-width brackets (`［］`) can also be used.
+/// - `\` matches the next character itself (e.g. `\?` or `\\`).
+/// - Any other character matches itself.
+///
+/// Patterns are matched against the whole term. A pattern with only ASCII
+/// literal characters is a romaji pattern, matched against the romaji search
+/// keys (e.g. `ta*ru`).
+///
+/// Patterns are searched by their literal characters, so a pattern without
+/// any (e.g. `??` or `[漢字][漢字]`) is invalid. Romaji patterns are searched by
+/// their literal prefix or suffix, so they must start or end with a literal
+/// (e.g. `ta*` but not `*ta*`).
+#[derive(Clone)]
+pub struct Pattern {
+	items: Vec<Item>,
+	romaji: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Item {
+	Char(char),
+	Any,
+	Run,
+	Class(CharClass),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum CharClass {
+	Kanji,
+	Kana,
+	Hiragana,
+	Katakana,
+	Set(Vec<char>),
+}
+
+impl Pattern {
+	pub fn parse(pattern: &str) -> Result<Pattern> {
+		let pattern = pattern.trim();
+		let romaji = pattern.is_ascii() && pattern.chars().any(|x| x.is_ascii_alphanumeric());
+		let mut items = Vec::new();
+		let mut chars = pattern.chars();
+		while let Some(chr) = chars.next() {
+			let item = match chr {
+				'?' | '？' => Item::Any,
+				'*' | '＊' => {
+					// Consecutive runs are the same as a single one.
+					if items.last() == Some(&Item::Run) {
+						continue;
+					}
+					Item::Run
+				}
+				'[' | '［' => {
+					let mut class = String::new();
+					loop {
+						match chars.next() {
+							Some(']' | '］') => break,
+							Some('\\') => class.push(escaped(pattern, chars.next())?),
+							Some(chr) => class.push(chr),
+							None => return Err(invalid(pattern, "missing `]`")),
+						}
+					}
+					Item::Class(
+						CharClass::parse(&class).ok_or_else(|| invalid(pattern, "empty `[]`"))?,
+					)
+				}
+				'\\' => Item::Char(escaped(pattern, chars.next())?),
+				chr => Item::Char(chr),
+			};
+			let item = match item {
+				Item::Char(chr) if romaji => Item::Char(chr.to_ascii_lowercase()),
+				item => item,
+			};
+			items.push(item);
+		}
+		if items.is_empty() {
+			return Err(Error::InvalidQuery(String::from("empty pattern")));
+		}
+
+		let result = Pattern { items, romaji };
+		if result.literals().is_empty() {
+			return Err(invalid(pattern, "no characters to search"));
+		}
+		if romaji && result.prefix().is_empty() && result.suffix().is_empty() {
+			return Err(invalid(pattern, "no romaji to search at the start or end"));
+		}
+		Ok(result)
+	}
+
+	/// True if this is a romaji pattern, matching romaji search keys.
+	pub fn is_romaji(&self) -> bool {
+		self.romaji
+	}
+
+	/// Returns true if `text` fully matches the pattern.
+	pub fn is_match(&self, text: &str) -> bool {
+		let text: Vec<char> = text.chars().collect();
+		match_items(&self.items, &text)
+	}
+
+	/// Returns the literal characters at the start of the pattern.
+	pub fn prefix(&self) -> String {
+		self.items
+			.iter()
+			.map_while(|x| match x {
+				Item::Char(chr) => Some(*chr),
+				_ => None,
+			})
+			.collect()
+	}
+
+	/// Returns the literal characters at the end of the pattern.
+	pub fn suffix(&self) -> String {
+		let suffix: Vec<char> = self
+			.items
+			.iter()
+			.rev()
+			.map_while(|x| match x {
+				Item::Char(chr) => Some(*chr),
+				_ => None,
+			})
+			.collect();
+		suffix.into_iter().rev().collect()
+	}
+
+	/// Returns all the literal characters in the pattern.
+	pub fn literals(&self) -> String {
+		self.items
+			.iter()
+			.filter_map(|x| match x {
+				Item::Char(chr) => Some(*chr),
+				_ => None,
+			})
+			.collect()
+	}
+}
+
+impl CharClass {
+	fn parse(class: &str) -> Option<CharClass> {
+		let class = match class {
+			"漢字" => CharClass::Kanji,
+			"かな" | "カナ" => CharClass::Kana,
+			"ひらがな" => CharClass::Hiragana,
+			"カタカナ" => CharClass::Katakana,
+			"" => return None,
+			set => CharClass::Set(set.chars().collect()),
+		};
+		Some(class)
+	}
+
+	fn is_match(&self, chr: char) -> bool {
+		match self {
+			CharClass::Kanji => kana::is_kanji(chr),
+			CharClass::Kana => kana::is_kana(chr),
+			CharClass::Hiragana => kana::is_hiragana(chr),
+			CharClass::Katakana => kana::is_katakana(chr),
+			CharClass::Set(set) => set.contains(&chr),
+		}
+	}
+}
+
+/// Returns true if `text` has any wildcard, and should be parsed as a pattern.
+pub(crate) fn has_wildcards(text: &str) -> bool {
+	text.chars()
+		.any(|x| x == '?' || x == '*' || x == '？' || x == '＊' || x == '[' || x == '［')
+}
+
+/// Returns the character after a `\\` escape.
+fn escaped(pattern: &str, chr: Option<char>) -> Result<char> {
+	chr.ok_or_else(|| invalid(pattern, "missing character after `\\`"))
+}
+
+fn invalid(pattern: &str, message: &str) -> Error {
+	Error::InvalidQuery(format!("{} in pattern `{}`", message, pattern))
+}
+
+/// Matches `text` against `items`, backtracking only to the last run.
+fn match_items(items: &[Item], text: &[char]) -> bool {
+	let (mut item, mut pos) = (0, 0);
+	let mut last_run: Option<(usize, usize)> = None;
+	while pos < text.len() {
+		let matched = match items.get(item) {
+			Some(Item::Char(chr)) => *chr == text[pos],
+			Some(Item::Any) => true,
+			Some(Item::Class(class)) => class.is_match(text[pos]),
+			Some(Item::Run) => {
+				last_run = Some((item, pos));
+				item += 1;
+				continue;
+			}
+			None => false,
+		};
+		if matched {
+			item += 1;
+			pos += 1;
+		} else if let Some((run_item, run_pos)) = last_run {
+			// Extend the last run by one character and retry.
+			item = run_item + 1;
+			pos = run_pos + 1;
+			last_run = Some((run_item, pos));
+		} else {
+			return false;
+		}
+	}
+	items[item..].iter().all(|x| *x == Item::Run)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn items(pattern: &str) -> Vec<Item> {
+		match Pattern::parse(pattern) {
+			Ok(pattern) => pattern.items,
+			Err(err) => panic!("pattern `{}`: {}", pattern, err),
+		}
+	}
+
+	fn chars(text: &str) -> Vec<char> {
+		text.chars().collect()
+	}
+
+	#[test]
+	fn test_parse() {
+		use self::CharClass::*;
+		use self::Item::*;
+
+		assert_eq!(vec![Char('東'), Run], items("東*"));
+		assert_eq!(vec![Char('東'), Run], items(" 東＊＊ "));
+		assert_eq!(vec![Any, Char('き'), Any], items("?き？"));
+		assert_eq!(vec![Run, Char('a'), Run, Char('b')], items("*a**B"));
+
+		assert_eq!(vec![Class(Kanji), Char('き')], items("[漢字]き"));
+		assert_eq!(vec![Class(Kana), Char('字')], items("［かな］字"));
+		assert_eq!(vec![Class(Kana), Char('字')], items("[カナ]字"));
+		assert_eq!(vec![Class(Hiragana), Char('字')], items("[ひらがな]字"));
+		assert_eq!(vec![Class(Katakana), Char('字')], items("[カタカナ]字"));
+		assert_eq!(
+			vec![Class(Set(vec!['は', 'ば'])), Char('し')],
+			items("[はば]し")
+		);
+		assert_eq!(
+			vec![Class(Set(vec!['漢', ']'])), Char('字')],
+			items("[漢\\]]字")
+		);
+
+		assert_eq!(vec![Char('?'), Run], items("\\?*"));
+		assert_eq!(vec![Char('*'), Char('\\')], items("\\*\\\\"));
+		assert_eq!(vec![Char('['), Char('字'), Char(']')], items("\\[字]"));
+		assert_eq!(vec![Char('a'), Any], items("\\A?"));
+	}
+
+	#[test]
+	fn test_romaji() {
+		assert!(Pattern::parse("ta*ru").unwrap().is_romaji());
+		assert!(Pattern::parse("TA?").unwrap().is_romaji());
+		assert!(!Pattern::parse("た*る").unwrap().is_romaji());
+		assert!(!Pattern::parse("東?").unwrap().is_romaji());
+		assert!(!Pattern::parse("*-*").unwrap().is_romaji());
+	}
+
+	#[test]
+	fn test_parse_errors() {
+		fn check(pattern: &str, expected: &str) {
+			match Pattern::parse(pattern) {
+				Err(Error::InvalidQuery(message)) => {
+					assert_eq!(expected, message, "pattern `{}`", pattern)
+				}
+				Err(err) => panic!("pattern `{}`: unexpected error: {}", pattern, err),
+				Ok(_) => panic!("pattern `{}`: expected an error", pattern),
+			}
+		}
+
+		check("", "empty pattern");
+		check("  ", "empty pattern");
+		check("[漢字", "missing `]` in pattern `[漢字`");
+		check("東[]", "empty `[]` in pattern `東[]`");
+		check("東\\", "missing character after `\\` in pattern `東\\`");
+		check("[東\\", "missing character after `\\` in pattern `[東\\`");
+
+		// Patterns must have a literal to search.
+		check("*", "no characters to search in pattern `*`");
+		check("??", "no characters to search in pattern `??`");
+		check(
+			"[漢字][漢字]",
+			"no characters to search in pattern `[漢字][漢字]`",
+		);
+		check("*[ab]*", "no characters to search in pattern `*[ab]*`");
+		check(
+			"*ta*",
+			"no romaji to search at the start or end in pattern `*ta*`",
+		);
+		check(
+			"?a?",
+			"no romaji to search at the start or end in pattern `?a?`",
+		);
+	}
+
+	#[test]
+	fn test_literals() {
+		fn check(pattern: &str, prefix: &str, suffix: &str, literals: &str) {
+			let pattern = Pattern::parse(pattern).unwrap();
+			assert_eq!(prefix, pattern.prefix());
+			assert_eq!(suffix, pattern.suffix());
+			assert_eq!(literals, pattern.literals());
+		}
+
+		check("東京*", "東京", "", "東京");
+		check("*きょう", "", "きょう", "きょう");
+		check("東?京", "東", "京", "東京");
+		check("?東*京?", "", "", "東京");
+		check("[漢字]中[漢字]", "", "", "中");
+		check("東京", "東京", "東京", "東京");
+		check("Ta*rU", "ta", "ru", "taru");
+		check("\\**\\?", "*", "?", "*?");
+	}
+
+	#[test]
+	fn test_match_items() {
+		fn check(pattern: &str, text: &str, expected: bool) {
+			let result = match_items(&items(pattern), &chars(text));
+			assert_eq!(expected, result, "`{}` with `{}`", pattern, text);
+		}
+
+		check("東京", "東京", true);
+		check("東京", "東京都", false);
+		check("東京", "東", false);
+		check("東?", "東京", true);
+		check("東?", "東", false);
+		check("東*", "東", true);
+		check("東*", "東京都", true);
+		check("*都", "東京都", true);
+		check("*京*", "東京都", true);
+		check("*京*", "京", true);
+		check("*京*", "東都", false);
+
+		// Runs backtrack to match later occurrences.
+		check("*ab", "aab", true);
+		check("*ab", "abab", true);
+		check("a*b*c", "abbbc", true);
+		check("a*b*c", "acbc", true);
+		check("a*b*c", "abcb", false);
+		check("*a?c", "abcabc", true);
+
+		check("[漢字]き", "好き", true);
+		check("[漢字]き", "すき", false);
+		check("[かな]き", "すき", true);
+		check("[かな]き", "スき", true);
+		check("[ひらがな]き", "スき", false);
+		check("[カタカナ]き", "スき", true);
+		check("[はば]し", "ばし", true);
+		check("[はば]し", "ぱし", false);
+
+		// Patterns without literals are only rejected when parsed.
+		assert!(match_items(&[Item::Run], &[]));
+		assert!(match_items(&[Item::Run, Item::Run], &chars("東京")));
+		assert!(!match_items(&[Item::Any], &[]));
+		assert!(!match_items(&[], &chars("東")));
+		assert!(match_items(&[], &[]));
+	}
+}
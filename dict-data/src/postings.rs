@@ -0,0 +1,416 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::error::{Error, Result};
+use crate::files::{Container, Rows};
+use crate::raw::RawUint32;
+
+/// Compressed posting list, a sorted list of unique `u32` values (e.g. the
+/// one-based entry indexes for a term).
+///
+/// Each value is stored as the difference from the previous value (from zero
+/// for the first value), encoded as a LEB128 varint. Since values are strictly
+/// increasing every difference is at least one, so values are never zero.
+///
+/// Lists are decoded lazily. Invalid data (a zero difference, a truncated or
+/// overflowing varint) ends the list early, corruption is detected by the
+/// database checksums.
+#[derive(Clone)]
+pub struct Postings<'a> {
+	data: &'a [u8],
+	remaining: usize,
+	last: u32,
+
+	/// Value found by `seek` and not yet returned by `next`.
+	head: Option<u32>,
+}
+
+impl<'a> Postings<'a> {
+	/// Reads `count` values from the encoded list `data`.
+	pub fn new(data: &'a [u8], count: usize) -> Postings<'a> {
+		Postings {
+			data,
+			remaining: count,
+			last: 0,
+			head: None,
+		}
+	}
+
+	/// Returns an empty list.
+	pub fn empty() -> Postings<'static> {
+		Postings::new(&[], 0)
+	}
+
+	/// Number of values left in the list.
+	pub fn len(&self) -> usize {
+		self.remaining + if self.head.is_some() { 1 } else { 0 }
+	}
+
+	/// True if there are no values left in the list.
+	pub fn is_empty(&self) -> bool {
+		self.remaining == 0 && self.head.is_none()
+	}
+
+	/// Advances the list to the first value greater or equal to `target` and
+	/// returns it. The value is not consumed, so it is also returned by the
+	/// next call to `next`.
+	pub fn seek(&mut self, target: u32) -> Option<u32> {
+		loop {
+			let value = match self.head {
+				Some(value) => value,
+				None => self.decode()?,
+			};
+			if value >= target {
+				self.head = Some(value);
+				return Some(value);
+			}
+			self.head = None;
+		}
+	}
+
+	fn decode(&mut self) -> Option<u32> {
+		if self.remaining == 0 {
+			return None;
+		}
+		let mut delta: u64 = 0;
+		let mut shift = 0;
+		loop {
+			let (&byte, data) = match self.data.split_first() {
+				Some(next) if shift < 35 => next,
+				_ => return self.invalid(),
+			};
+			self.data = data;
+			delta |= ((byte & 0x7F) as u64) << shift;
+			shift += 7;
+			if byte & 0x80 == 0 {
+				break;
+			}
+		}
+		if delta == 0 || self.last as u64 + delta > u32::MAX as u64 {
+			return self.invalid();
+		}
+		self.last += delta as u32;
+		self.remaining -= 1;
+		Some(self.last)
+	}
+
+	fn invalid(&mut self) -> Option<u32> {
+		self.remaining = 0;
+		None
+	}
+}
+
+impl<'a> Iterator for Postings<'a> {
+	type Item = u32;
+
+	fn next(&mut self) -> Option<u32> {
+		match self.head.take() {
+			Some(value) => Some(value),
+			None => self.decode(),
+		}
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(0, Some(self.len()))
+	}
+}
+
+/// Returns the values present in all the given lists, in order. The result
+/// is empty if there are no lists.
+pub fn intersect<'a>(mut lists: Vec<Postings<'a>>) -> Intersection<'a> {
+	// Leading with the shortest list minimizes the number of seeks.
+	lists.sort_by_key(|x| x.len());
+	Intersection { lists }
+}
+
+/// Returns the values present in any of the given lists, in order.
+pub fn union<'a>(lists: Vec<Postings<'a>>) -> Union<'a> {
+	let mut union = Union {
+		heap: BinaryHeap::with_capacity(lists.len()),
+		lists,
+		last: None,
+	};
+	for (n, list) in union.lists.iter_mut().enumerate() {
+		if let Some(value) = list.next() {
+			union.heap.push(Reverse((value, n)));
+		}
+	}
+	union
+}
+
+/// Iterator for `intersect`.
+pub struct Intersection<'a> {
+	lists: Vec<Postings<'a>>,
+}
+
+impl<'a> Iterator for Intersection<'a> {
+	type Item = u32;
+
+	fn next(&mut self) -> Option<u32> {
+		let (first, others) = self.lists.split_first_mut()?;
+		let mut candidate = first.next()?;
+		'search: loop {
+			for list in others.iter_mut() {
+				let value = list.seek(candidate)?;
+				if value > candidate {
+					candidate = first.seek(value)?;
+					first.next();
+					continue 'search;
+				}
+			}
+			return Some(candidate);
+		}
+	}
+}
+
+/// Iterator for `union`.
+pub struct Union<'a> {
+	heap: BinaryHeap<Reverse<(u32, usize)>>,
+	lists: Vec<Postings<'a>>,
+	last: Option<u32>,
+}
+
+impl<'a> Iterator for Union<'a> {
+	type Item = u32;
+
+	fn next(&mut self) -> Option<u32> {
+		loop {
+			let Reverse((value, n)) = self.heap.pop()?;
+			if let Some(next) = self.lists[n].next() {
+				self.heap.push(Reverse((next, n)));
+			}
+			if self.last != Some(value) {
+				self.last = Some(value);
+				return Some(value);
+			}
+		}
+	}
+}
+
+/// Wraps a posting index section, which maps each entry of a `Text` section
+/// (or other sorted key list) to a compressed posting list.
+///
+/// The format of a posting index section is:
+///
+/// ```
+///     PostingIndex {
+///         Count: u32_le,
+///         Index: [(Offset: u32_le, Length: u32_le); Count],
+///         Data:  [u8],
+///     }
+/// ```
+///
+/// `Offset` is the position of the list in `Data` in bytes and `Length` is the
+/// number of values in the list. Lists are stored in index order, so each list
+/// ends at the offset of the next. The offsets are validated on load.
+pub struct PostingIndex {
+	name: String,
+	index: Rows<RawUint32>,
+	data: Rows<u8>,
+}
+
+impl PostingIndex {
+	pub fn load(data: &Container, name: &str) -> Result<PostingIndex> {
+		let mut file = data.section(name)?;
+		let count: usize = file.read_uint()?.into();
+		let index = file.read_uint_vec(count.saturating_mul(2))?;
+		let data = file.read_all()?;
+		let mut last = 0;
+		for (n, entry) in index.chunks(2).enumerate() {
+			let offset: usize = entry[0].into();
+			if offset < last || offset > data.len() {
+				return Err(Error::bad_index(file.name(), n));
+			}
+			last = offset;
+		}
+		Ok(PostingIndex {
+			name: file.name().to_string(),
+			index,
+			data,
+		})
+	}
+
+	/// Full name of the section (e.g. `text/terms_index`).
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn count(&self) -> usize {
+		self.index.len() / 2
+	}
+
+	/// Returns the posting list for the given entry.
+	pub fn entry(&self, index: usize) -> Result<Postings<'_>> {
+		if index >= self.count() {
+			return Err(Error::bad_index(self.name.as_str(), index));
+		}
+		let pos = index * 2;
+		let offset: usize = self.index[pos].into();
+		let length: usize = self.index[pos + 1].into();
+		let end: usize = match self.index.get(pos + 2) {
+			Some(&next) => next.into(),
+			None => self.data.len(),
+		};
+		Ok(Postings::new(&self.data[offset..end], length))
+	}
+
+	/// Returns the union of the posting lists for the given entries (e.g. a
+	/// range of entries).
+	pub fn union<I: IntoIterator<Item = usize>>(&self, entries: I) -> Result<Union<'_>> {
+		let lists = entries
+			.into_iter()
+			.map(|x| self.entry(x))
+			.collect::<Result<Vec<_>>>()?;
+		Ok(union(lists))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::files::tests::container_bytes;
+	use crate::files::FileData;
+
+	/// Encodes a list like `dict-build`, see `dict-build/src/postings.rs` for
+	/// the test that both agree.
+	fn encode(list: &[u32], output: &mut Vec<u8>) {
+		let mut last = 0;
+		for &value in list.iter() {
+			let mut delta = value - last;
+			while delta >= 0x80 {
+				output.push((delta & 0x7F) as u8 | 0x80);
+				delta >>= 7;
+			}
+			output.push(delta as u8);
+			last = value;
+		}
+	}
+
+	fn encoded(lists: &[&[u32]]) -> Vec<(Vec<u8>, usize)> {
+		lists
+			.iter()
+			.map(|list| {
+				let mut data = Vec::new();
+				encode(list, &mut data);
+				(data, list.len())
+			})
+			.collect()
+	}
+
+	fn postings(lists: &[(Vec<u8>, usize)]) -> Vec<Postings<'_>> {
+		lists
+			.iter()
+			.map(|(data, count)| Postings::new(data, *count))
+			.collect()
+	}
+
+	#[test]
+	fn test_postings() {
+		let list = [1, 2, 127, 128, 300, 16_384, 1 << 21, 1 << 28, u32::MAX];
+		let mut data = Vec::new();
+		encode(&list, &mut data);
+		assert_eq!(
+			list.to_vec(),
+			Postings::new(&data, list.len()).collect::<Vec<_>>()
+		);
+		assert_eq!(0, Postings::empty().count());
+
+		// The count limits the values read.
+		assert_eq!(vec![1, 2], Postings::new(&data, 2).collect::<Vec<_>>());
+
+		let mut postings = Postings::new(&data, list.len());
+		assert_eq!(list.len(), postings.len());
+		assert_eq!(Some(300), postings.seek(200));
+		assert_eq!(Some(300), postings.seek(300));
+		assert_eq!(list.len() - 4, postings.len());
+		assert_eq!(Some(300), postings.next());
+		assert_eq!(Some(u32::MAX), postings.seek(1 << 29));
+		assert_eq!(Some(u32::MAX), postings.next());
+		assert_eq!(None, postings.seek(0));
+
+		// Invalid data ends the list: a zero delta, a truncated varint and
+		// an overflow.
+		assert_eq!(vec![1], Postings::new(&[1, 0, 1], 3).collect::<Vec<_>>());
+		assert_eq!(vec![1], Postings::new(&[1, 0x80], 2).collect::<Vec<_>>());
+		let overflow = [0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 1];
+		assert_eq!(
+			vec![u32::MAX],
+			Postings::new(&overflow, 2).collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn test_intersect() {
+		let check = |lists: &[&[u32]], expected: &[u32]| {
+			let data = encoded(lists);
+			let result: Vec<u32> = intersect(postings(&data)).collect();
+			assert_eq!(expected, result.as_slice(), "lists {:?}", lists);
+		};
+		check(&[], &[]);
+		check(&[&[1, 2, 3]], &[1, 2, 3]);
+		check(&[&[1, 3, 5, 7, 9], &[2, 3, 4, 9, 10]], &[3, 9]);
+		check(&[&[1, 3, 5, 7, 9], &[]], &[]);
+		check(&[&[1, 2], &[3, 4]], &[]);
+		check(
+			&[
+				&[1, 5, 9, 200, 1000],
+				&[5, 200, 1000, 5000],
+				&[1, 2, 5, 1000],
+			],
+			&[5, 1000],
+		);
+		check(&[&[u32::MAX], &[1, u32::MAX]], &[u32::MAX]);
+	}
+
+	#[test]
+	fn test_union() {
+		let check = |lists: &[&[u32]], expected: &[u32]| {
+			let data = encoded(lists);
+			let result: Vec<u32> = union(postings(&data)).collect();
+			assert_eq!(expected, result.as_slice(), "lists {:?}", lists);
+		};
+		check(&[], &[]);
+		check(&[&[], &[]], &[]);
+		check(&[&[1, 2, 3]], &[1, 2, 3]);
+		check(&[&[1, 3, 5], &[2, 3, 4], &[]], &[1, 2, 3, 4, 5]);
+		check(&[&[10, 20], &[1, 2], &[2, 10, 30]], &[1, 2, 10, 20, 30]);
+	}
+
+	#[test]
+	fn test_posting_index() {
+		let lists: &[&[u32]] = &[&[1, 5, 9], &[], &[2, 1000, 100_000], &[5]];
+		let mut section = (lists.len() as u32).to_le_bytes().to_vec();
+		let mut data = Vec::new();
+		for list in lists {
+			section.extend_from_slice(&(data.len() as u32).to_le_bytes());
+			section.extend_from_slice(&(list.len() as u32).to_le_bytes());
+			encode(list, &mut data);
+		}
+		section.extend_from_slice(&data);
+		let bytes = container_bytes(&[("text/index", &section)]);
+		let container = Container::new(FileData::Bytes(Arc::new(bytes))).unwrap();
+
+		let index = PostingIndex::load(&container, "text/index").unwrap();
+		assert_eq!("text/index", index.name());
+		assert_eq!(lists.len(), index.count());
+		for (n, list) in lists.iter().enumerate() {
+			assert_eq!(list.to_vec(), index.entry(n).unwrap().collect::<Vec<_>>());
+		}
+		assert!(index.entry(lists.len()).is_err());
+
+		let result: Vec<u32> = index.union(vec![0, 1, 3]).unwrap().collect();
+		assert_eq!(vec![1, 5, 9], result);
+		assert!(index.union(vec![0, 4]).is_err());
+
+		// Offsets must be in order and in the section.
+		for &(position, offset) in [(4, 100u32), (20, 0)].iter() {
+			let mut invalid = section.clone();
+			invalid[position..position + 4].copy_from_slice(&offset.to_le_bytes());
+			let bytes = container_bytes(&[("text/index", &invalid)]);
+			let container = Container::new(FileData::Bytes(Arc::new(bytes))).unwrap();
+			assert!(PostingIndex::load(&container, "text/index").is_err());
+		}
+	}
+}
@@ -0,0 +1,509 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::dictionary::Entry;
+use crate::error::{Error, Result};
+use crate::pattern::{has_wildcards, Pattern};
+use crate::user_dict::english_words;
+
+/// Search query for `Dictionary::search_query`, parsed from the query language.
+///
+/// A query is a list of clauses separated by whitespace, all of which must
+/// match an entry:
+///
+/// - A Japanese word (e.g. `食`) matches entries with an expression or reading
+///   starting with it. A quoted Japanese word (e.g. `"食べる"`) must match the
+///   whole expression or reading.
+/// - A word with wildcards (e.g. `東*` or `ta*ru`) matches as a `Pattern`.
+/// - Any other word (e.g. `eat`) matches entries with the English word in
+///   their definitions. A quoted phrase (e.g. `"to eat"`) must appear in a
+///   single glossary.
+/// - `romaji:KEY` matches entries with a romaji reading starting with `KEY`.
+/// - `tag:NAME` matches entries with the tag in any definition (e.g. `v5`).
+///   Tag names are from the dictionary `tags.json`.
+/// - `source:NAME` matches entries with a definition from the source (e.g.
+///   `JMdict`). Source names are from the dictionary `sources.txt` or the
+///   user dictionaries, see `is_source`.
+/// - `freq>N`, `freq>=N`, `freq<N`, `freq<=N` and `freq=N` (or `freq:N`)
+///   compare the entry frequency.
+///
+/// Any clause can be negated with a leading `-` (e.g. `-tag:arch`).
+///
+/// Entries are found by the Japanese words, patterns, English words and
+/// romaji, then checked for the other clauses. A query needs at least one of
+/// those that is not negated (e.g. `食 tag:v5` but not `tag:v5`).
+///
+/// Queries are displayed in a normalized form, which parses to the same query
+/// (e.g. for saved searches).
+#[derive(Clone)]
+pub struct Query {
+	clauses: Vec<Clause>,
+}
+
+#[derive(Clone)]
+struct Clause {
+	/// Character offset of the clause in the query text, for errors.
+	position: usize,
+	negated: bool,
+	filter: Filter,
+}
+
+#[derive(Clone)]
+enum Filter {
+	Prefix(String),
+	Exact(String),
+	Pattern(String, Pattern),
+	English(String, Vec<String>),
+	Phrase(String, Vec<String>),
+	Romaji(String),
+	Tag(String),
+	Source(String),
+	Frequency(Ordering, bool, u32),
+}
+
+impl Query {
+	/// Parses a query. Errors are returned as `Error::QuerySyntax`, with the
+	/// character offset of the error in `query`.
+	pub fn parse(query: &str) -> Result<Query> {
+		let chars: Vec<char> = query.chars().collect();
+		let mut clauses = Vec::new();
+		let mut pos = 0;
+		while pos < chars.len() {
+			if chars[pos].is_whitespace() {
+				pos += 1;
+				continue;
+			}
+
+			let position = pos;
+			let negated = chars[pos] == '-';
+			if negated {
+				pos += 1;
+			}
+
+			let filter = if chars.get(pos) == Some(&'"') {
+				let end = match chars[pos + 1..].iter().position(|&x| x == '"') {
+					Some(end) => pos + 1 + end,
+					None => return Err(syntax(pos, "missing closing `\"`")),
+				};
+				if end + 1 < chars.len() && !chars[end + 1].is_whitespace() {
+					return Err(syntax(end + 1, "expected a space after the quote"));
+				}
+				let text: String = chars[pos + 1..end].iter().collect();
+				let filter = parse_quoted(pos, text.trim())?;
+				pos = end + 1;
+				filter
+			} else {
+				let end = chars[pos..]
+					.iter()
+					.position(|x| x.is_whitespace())
+					.map_or(chars.len(), |end| pos + end);
+				if end == pos {
+					return Err(syntax(position, "expected a search term after `-`"));
+				}
+				let filter = parse_word(pos, &chars[pos..end])?;
+				pos = end;
+				filter
+			};
+
+			clauses.push(Clause {
+				position,
+				negated,
+				filter,
+			});
+		}
+
+		if clauses.is_empty() {
+			return Err(syntax(0, "empty query"));
+		}
+		Ok(Query { clauses })
+	}
+
+	/// Calls `check` with the position and name of each tag filter, and
+	/// returns the first error.
+	pub(crate) fn check_tags<F: Fn(&str) -> bool>(&self, check: F) -> Result<()> {
+		for clause in self.clauses.iter() {
+			if let Filter::Tag(name) = &clause.filter {
+				if !check(name) {
+					let message = format!("unknown tag `{}`", name);
+					return Err(syntax(clause.position, &message));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Same as `check_tags`, for the source filters.
+	pub(crate) fn check_sources<F: Fn(&str) -> bool>(&self, check: F) -> Result<()> {
+		for clause in self.clauses.iter() {
+			if let Filter::Source(name) = &clause.filter {
+				if !check(name) {
+					let message = format!("unknown source `{}`", name);
+					return Err(syntax(clause.position, &message));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Returns the searches for the clauses that can be answered by an index
+	/// (i.e. the non-negated text clauses). The entries for a query are
+	/// always in the intersection of the searches.
+	pub(crate) fn searches(&self) -> Vec<Search<'_>> {
+		self.clauses
+			.iter()
+			.filter(|x| !x.negated)
+			.filter_map(|x| match &x.filter {
+				Filter::Prefix(text) => Some(Search::Prefix(text)),
+				Filter::Exact(text) => Some(Search::Exact(text)),
+				Filter::Pattern(_, pattern) => Some(Search::Pattern(pattern)),
+				Filter::English(_, words) => Some(Search::English(words)),
+				Filter::Phrase(_, words) => Some(Search::English(words)),
+				Filter::Romaji(key) => Some(Search::Romaji(key)),
+				_ => None,
+			})
+			.collect()
+	}
+
+	/// Returns false if the frequency excludes an entry, which can be checked
+	/// without loading the entry.
+	pub(crate) fn matches_frequency(&self, frequency: u32) -> bool {
+		self.clauses.iter().all(|x| match x.filter {
+			Filter::Frequency(..) => x.filter.matches_frequency(frequency) != x.negated,
+			_ => true,
+		})
+	}
+
+	/// True if `matches` checks any clause besides the index searches and the
+	/// frequency, which need the entry to be loaded.
+	pub(crate) fn needs_entry(&self) -> bool {
+		self.clauses.iter().any(|x| {
+			let indexed = !x.negated && x.filter.is_indexed();
+			match x.filter {
+				Filter::Frequency(..) => false,
+				_ => !indexed,
+			}
+		})
+	}
+
+	/// True if checking the clauses with `matches` needs the glossary of the
+	/// entries, which the index searches do not.
+	pub(crate) fn needs_glossary(&self) -> bool {
+		self.clauses.iter().any(|x| {
+			let indexed = !x.negated && x.filter.is_indexed();
+			match x.filter {
+				Filter::English(..) | Filter::Phrase(..) => !indexed,
+				_ => false,
+			}
+		})
+	}
+
+	/// Returns true if an entry from `searches` matches all the clauses. The
+	/// index searches are assumed to match.
+	pub(crate) fn matches(&self, entry: &Entry) -> bool {
+		self.clauses.iter().all(|x| {
+			let indexed = !x.negated && x.filter.is_indexed();
+			indexed || x.filter.matches(entry) != x.negated
+		})
+	}
+}
+
+/// Index search for a query clause.
+pub(crate) enum Search<'a> {
+	Prefix(&'a str),
+	Exact(&'a str),
+	Pattern(&'a Pattern),
+	English(&'a [String]),
+	Romaji(&'a str),
+}
+
+impl Filter {
+	/// True if the index search for the filter gives only matching entries.
+	/// Phrases are searched by their words, and must be checked.
+	fn is_indexed(&self) -> bool {
+		matches!(
+			self,
+			Filter::Prefix(_)
+				| Filter::Exact(_)
+				| Filter::Pattern(..)
+				| Filter::English(..)
+				| Filter::Romaji(_)
+		)
+	}
+
+	fn matches_frequency(&self, frequency: u32) -> bool {
+		match self {
+			Filter::Frequency(order, or_equal, value) => {
+				let cmp = frequency.cmp(value);
+				cmp == *order || (*or_equal && cmp == Ordering::Equal)
+			}
+			_ => true,
+		}
+	}
+
+	fn matches(&self, entry: &Entry) -> bool {
+		let terms = [&entry.expression, &entry.reading];
+		let glossary = || entry.definitions.iter().flat_map(|x| x.glossary.iter());
+		match self {
+			Filter::Prefix(text) => terms.iter().any(|x| x.starts_with(text.as_str())),
+			Filter::Exact(text) => terms.contains(&text),
+			Filter::Pattern(_, pattern) if pattern.is_romaji() => {
+				pattern.is_match(&search_key(entry))
+			}
+			Filter::Pattern(_, pattern) => terms.iter().any(|x| pattern.is_match(x)),
+			Filter::English(_, words) => {
+				let glossary: Vec<String> = glossary().flat_map(|x| english_words(x)).collect();
+				words.iter().all(|x| glossary.contains(x))
+			}
+			Filter::Phrase(_, words) => glossary().any(|x| {
+				english_words(x)
+					.windows(words.len())
+					.any(|x| x == &words[..])
+			}),
+			Filter::Romaji(key) => search_key(entry).starts_with(key.as_str()),
+			Filter::Tag(name) => entry
+				.definitions
+				.iter()
+				.any(|x| x.tags.contains(name) || x.rules.contains(name)),
+			Filter::Source(name) => entry.definitions.iter().any(|x| is_source(&x.source, name)),
+			Filter::Frequency(..) => self.matches_frequency(entry.frequency),
+		}
+	}
+}
+
+/// Returns true if `name` refers to `source`, ignoring case. The name can be
+/// either the full source name or its first word (e.g. `jmdict` for
+/// `JMdict (English)`).
+pub(crate) fn is_source(source: &str, name: &str) -> bool {
+	let first_word = source.split_whitespace().next().unwrap_or_default();
+	source.eq_ignore_ascii_case(name) || first_word.eq_ignore_ascii_case(name)
+}
+
+/// Romaji search key for an entry, see `kana::to_search_key`.
+fn search_key(entry: &Entry) -> String {
+	if !entry.reading.is_empty() {
+		kana::to_search_key(&entry.reading)
+	} else {
+		kana::to_search_key(&entry.expression)
+	}
+}
+
+fn syntax(position: usize, message: &str) -> Error {
+	Error::QuerySyntax {
+		position,
+		message: message.to_string(),
+	}
+}
+
+pub(crate) fn has_japanese(text: &str) -> bool {
+	text.chars().any(|x| kana::is_japanese(x, false))
+}
+
+/// Parses a quoted text starting at `position`.
+fn parse_quoted(position: usize, text: &str) -> Result<Filter> {
+	if text.is_empty() {
+		return Err(syntax(position, "empty quotes"));
+	}
+	if has_japanese(text) {
+		return Ok(Filter::Exact(text.to_string()));
+	}
+	let words = english_words(text);
+	if words.is_empty() {
+		return Err(syntax(position, "no words to search in quotes"));
+	}
+	Ok(Filter::Phrase(text.to_string(), words))
+}
+
+/// Parses an unquoted word starting at `position`.
+fn parse_word(position: usize, word: &[char]) -> Result<Filter> {
+	let text: String = word.iter().collect();
+
+	let name_length = word.iter().take_while(|x| x.is_ascii_alphabetic()).count();
+	let name = text[..name_length].to_lowercase();
+	let value_position = position + name_length + 1;
+	let value: String = word.iter().skip(name_length + 1).collect();
+	match word.get(name_length) {
+		Some('>' | '<' | '=') if name == "freq" => {
+			return parse_frequency(position + name_length, &word[name_length..]);
+		}
+		Some(':') if name_length > 0 => {
+			if value.is_empty() {
+				return Err(syntax(value_position, "expected a value"));
+			}
+			let filter = match name.as_str() {
+				"tag" => Filter::Tag(value),
+				"source" => Filter::Source(value),
+				"romaji" if has_wildcards(&value) => {
+					Filter::Pattern(value.clone(), parse_pattern(value_position, &value)?)
+				}
+				"romaji" => Filter::Romaji(kana::to_search_key(&value)),
+				"freq" => return parse_frequency(position + name_length, &word[name_length..]),
+				_ => {
+					let message = format!("unknown field `{}`", &text[..name_length]);
+					return Err(syntax(position, &message));
+				}
+			};
+			return Ok(filter);
+		}
+		_ => {}
+	}
+
+	if has_wildcards(&text) {
+		Ok(Filter::Pattern(
+			text.clone(),
+			parse_pattern(position, &text)?,
+		))
+	} else if has_japanese(&text) {
+		Ok(Filter::Prefix(text))
+	} else {
+		let words = english_words(&text);
+		if words.is_empty() {
+			return Err(syntax(position, "no words to search"));
+		}
+		Ok(Filter::English(text, words))
+	}
+}
+
+fn parse_pattern(position: usize, text: &str) -> Result<Pattern> {
+	Pattern::parse(text).map_err(|err| match err {
+		Error::InvalidQuery(message) => syntax(position, &message),
+		err => err,
+	})
+}
+
+/// Parses a frequency comparison (e.g. `>=5000`) starting at `position`.
+fn parse_frequency(position: usize, text: &[char]) -> Result<Filter> {
+	let (order, or_equal, length) = match text {
+		['>', '=', ..] => (Ordering::Greater, true, 2),
+		['<', '=', ..] => (Ordering::Less, true, 2),
+		['>', ..] => (Ordering::Greater, false, 1),
+		['<', ..] => (Ordering::Less, false, 1),
+		_ => (Ordering::Equal, true, 1),
+	};
+	let value: String = text[length..].iter().collect();
+	match value.parse::<u32>() {
+		Ok(value) => Ok(Filter::Frequency(order, or_equal, value)),
+		Err(_) => Err(syntax(position + length, "expected a frequency number")),
+	}
+}
+
+impl fmt::Display for Query {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (n, clause) in self.clauses.iter().enumerate() {
+			if n > 0 {
+				write!(f, " ")?;
+			}
+			if clause.negated {
+				write!(f, "-")?;
+			}
+			match &clause.filter {
+				Filter::Prefix(text) | Filter::English(text, _) => write!(f, "{}", text)?,
+				Filter::Pattern(text, pattern) if pattern.is_romaji() => {
+					write!(f, "romaji:{}", text)?
+				}
+				Filter::Pattern(text, _) => write!(f, "{}", text)?,
+				Filter::Exact(text) | Filter::Phrase(text, _) => write!(f, "\"{}\"", text)?,
+				Filter::Romaji(key) => write!(f, "romaji:{}", key)?,
+				Filter::Tag(name) => write!(f, "tag:{}", name)?,
+				Filter::Source(name) => write!(f, "source:{}", name)?,
+				Filter::Frequency(order, or_equal, value) => {
+					let op = match (order, or_equal) {
+						(Ordering::Greater, false) => ">",
+						(Ordering::Greater, true) => ">=",
+						(Ordering::Less, false) => "<",
+						(Ordering::Less, true) => "<=",
+						(Ordering::Equal, _) => "=",
+					};
+					write!(f, "freq{}{}", op, value)?
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_errors() {
+		fn check(query: &str, expected_position: usize, expected_message: &str) {
+			match Query::parse(query) {
+				Err(Error::QuerySyntax { position, message }) => {
+					assert_eq!(expected_position, position, "query `{}`", query);
+					assert_eq!(expected_message, message, "query `{}`", query);
+				}
+				Err(err) => panic!("query `{}`: unexpected error: {}", query, err),
+				Ok(result) => panic!("query `{}`: expected an error, got `{}`", query, result),
+			}
+		}
+
+		check("", 0, "empty query");
+		check("   ", 0, "empty query");
+		check("食 \"abc", 2, "missing closing `\"`");
+		check("\"abc\"x", 5, "expected a space after the quote");
+		check("\"\"", 0, "empty quotes");
+		check("\"  \"", 0, "empty quotes");
+		check("\"!!\"", 0, "no words to search in quotes");
+		check("!!", 0, "no words to search");
+		check("食 -", 2, "expected a search term after `-`");
+		check("食 - eat", 2, "expected a search term after `-`");
+		check("tag:", 4, "expected a value");
+		check("食 foo:bar", 2, "unknown field `foo`");
+		check("freq>x", 5, "expected a frequency number");
+		check("freq>=", 6, "expected a frequency number");
+		check("freq:", 5, "expected a value");
+
+		// Positions are in characters, not bytes.
+		check("食べる tag:", 8, "expected a value");
+		check("食べる -", 4, "expected a search term after `-`");
+		check("食べる \"たべる", 4, "missing closing `\"`");
+	}
+
+	#[test]
+	fn test_needs_entry() {
+		fn check(query: &str, entry: bool, glossary: bool) {
+			let query = Query::parse(query).unwrap();
+			assert_eq!(entry, query.needs_entry(), "query `{}`", query);
+			assert_eq!(glossary, query.needs_glossary(), "query `{}`", query);
+		}
+
+		check("食 eat romaji:ta ta*ru", false, false);
+		check("食 freq>10 -freq<5", false, false);
+		check("tag:v5", true, false);
+		check("source:JMdict", true, false);
+		check("-食", true, false);
+		check("\"to eat\"", true, true);
+		check("食 -eat", true, true);
+	}
+
+	#[test]
+	fn test_display() {
+		fn check(query: &str, expected: &str) {
+			let parsed = Query::parse(query).unwrap();
+			assert_eq!(expected, parsed.to_string(), "query `{}`", query);
+
+			// The normalized form parses to the same query.
+			let reparsed = Query::parse(expected).unwrap();
+			assert_eq!(expected, reparsed.to_string(), "query `{}`", query);
+		}
+
+		check("食", "食");
+		check("  食   eat  ", "食 eat");
+		check("\"食べる\"", "\"食べる\"");
+		check("\" to eat \"", "\"to eat\"");
+		check("-tag:arch -食", "-tag:arch -食");
+		check("source:JMdict tag:v5", "source:JMdict tag:v5");
+		check("romaji:TABE", "romaji:tabe");
+		check("romaji:ta*ru", "romaji:ta*ru");
+		check("東* ?きょう", "東* ?きょう");
+		check(
+			"freq>1 freq>=2 freq<3 freq<=4 freq=5 freq:6 FREQ>7",
+			"freq>1 freq>=2 freq<3 freq<=4 freq=5 freq=6 freq>7",
+		);
+		check(
+			"食 tag:v5 freq>5000 source:JMdict -tag:arch \"to eat\"",
+			"食 tag:v5 freq>5000 source:JMdict -tag:arch \"to eat\"",
+		);
+	}
+}
@@ -23,12 +23,8 @@ impl RawUint32 {
 		self.0.to_le_bytes()
 	}
 
-	pub fn from_bytes(bytes: &[u8]) -> RawUint32 {
-		let (ptr, len) = (bytes.as_ptr(), bytes.len());
-		if len != 4 {
-			panic!("RawUint32 from_bytes must with invalid size");
-		}
-		unsafe { *(ptr as *const RawUint32) }
+	pub fn from_bytes(bytes: [u8; 4]) -> RawUint32 {
+		Self(u32::from_ne_bytes(bytes))
 	}
 }
 
@@ -60,19 +56,3 @@ impl std::convert::Into<usize> for RawUint32 {
 		index as usize
 	}
 }
-
-#[inline]
-pub unsafe fn cast_vec<S, T: Sized>(data: Vec<S>) -> Vec<T> {
-	let src_size = std::mem::size_of::<S>();
-	let dst_size = std::mem::size_of::<T>();
-	let (ptr, len, cap) = data.into_raw_parts();
-
-	let src_len = len * src_size;
-	let src_cap = cap * src_size;
-	assert!(src_len % dst_size == 0);
-	assert!(src_cap % dst_size == 0);
-
-	let new_len = src_len / dst_size;
-	let new_cap = src_cap / dst_size;
-	Vec::from_raw_parts(ptr as *mut T, new_len, new_cap)
-}
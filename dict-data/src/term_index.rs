@@ -0,0 +1,135 @@
+use fst::automaton::Automaton;
+use fst::{IntoStreamer, Map, Streamer};
+use levenshtein_automata::{LevenshteinAutomatonBuilder, DFA};
+use regex_automata::{dense, DenseDFA};
+
+use crate::error::{Error, Result};
+use crate::files::{Container, Rows};
+
+/// Maximum edit distance for `fuzzy` queries. Higher distances match most of
+/// the vocabulary for short terms and are very expensive to build.
+pub const MAX_FUZZY_DISTANCE: u32 = 2;
+
+lazy_static! {
+	/// Levenshtein automaton builders for each distance, which precompute the
+	/// automaton transitions.
+	static ref FUZZY_BUILDERS: Vec<LevenshteinAutomatonBuilder> = (0..=MAX_FUZZY_DISTANCE)
+		.map(|distance| LevenshteinAutomatonBuilder::new(distance as u8, true))
+		.collect();
+}
+
+/// Regular expression automaton for `TermIndex::search`, see `regex`.
+pub type Regex = DenseDFA<Vec<usize>, usize>;
+
+/// Wraps a term index section, which is an FST (finite state transducer)
+/// mapping each term of a sorted `Text` section to its position, which is also
+/// the position of the term's entry list in the respective `PostingIndex`.
+///
+/// The FST gives exact, prefix and range queries, as well as queries by any
+/// `fst::Automaton` over the whole vocabulary (e.g. `fuzzy` and `regex`).
+pub struct TermIndex {
+	name: String,
+	map: Map<Rows<u8>>,
+}
+
+impl TermIndex {
+	pub fn load(data: &Container, name: &str) -> Result<TermIndex> {
+		let mut file = data.section(name)?;
+		let bytes = file.read_all()?;
+		let map = Map::new(bytes).map_err(|err| Error::invalid_data(file.name(), err))?;
+		Ok(TermIndex {
+			name: file.name().to_string(),
+			map,
+		})
+	}
+
+	/// Full name of the section (e.g. `text/terms_fst`).
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn count(&self) -> usize {
+		self.map.len()
+	}
+
+	/// Returns the position of a term.
+	pub fn get(&self, term: &str) -> Option<usize> {
+		self.map.get(term).map(|x| x as usize)
+	}
+
+	/// Returns the positions of the terms starting with `prefix`.
+	pub fn prefix(&self, prefix: &str) -> Vec<usize> {
+		let mut stream = self.map.range().ge(prefix).into_stream();
+		let mut output = Vec::new();
+		while let Some((key, value)) = stream.next() {
+			if !key.starts_with(prefix.as_bytes()) {
+				break;
+			}
+			output.push(value as usize);
+		}
+		output
+	}
+
+	/// Returns the positions of the terms from `start` (inclusive) to `end`
+	/// (exclusive), in order.
+	pub fn range(&self, start: &str, end: &str) -> Vec<usize> {
+		let stream = self.map.range().ge(start).lt(end).into_stream();
+		stream
+			.into_values()
+			.into_iter()
+			.map(|x| x as usize)
+			.collect()
+	}
+
+	/// Returns the terms accepted by `automaton` and their positions, in term
+	/// order, up to `limit` terms.
+	pub fn search<A: Automaton>(&self, automaton: A, limit: usize) -> Vec<(String, usize)> {
+		let mut stream = self.map.search(automaton).into_stream();
+		let mut output = Vec::new();
+		while let Some((key, value)) = stream.next() {
+			if output.len() >= limit {
+				break;
+			}
+			let key = String::from_utf8_lossy(key).to_string();
+			output.push((key, value as usize));
+		}
+		output
+	}
+}
+
+/// Returns an automaton accepting the terms within `distance` edits (inserted,
+/// deleted, replaced or transposed characters) of `term`.
+///
+/// The automaton can also compute the distance for a term with `DFA::eval`.
+pub fn fuzzy(term: &str, distance: u32) -> Result<DFA> {
+	match FUZZY_BUILDERS.get(distance as usize) {
+		Some(builder) => Ok(builder.build_dfa(term)),
+		None => {
+			let message = format!("maximum edit distance is {}", MAX_FUZZY_DISTANCE);
+			Err(Error::InvalidQuery(message))
+		}
+	}
+}
+
+/// Returns an automaton accepting the terms fully matching the regular
+/// expression `pattern`. Anchors (e.g. `^` and `$`) are not supported, since
+/// the pattern must always match the whole term.
+pub fn regex(pattern: &str) -> Result<Regex> {
+	dense::Builder::new()
+		.anchored(true)
+		.build(pattern)
+		.map_err(|err| Error::InvalidQuery(err.to_string()))
+}
+
+/// Returns true if `automaton` accepts `text`. This is used to match terms
+/// that are not in a `TermIndex` (e.g. from user dictionaries).
+pub fn is_match<A: Automaton>(automaton: &A, text: &str) -> bool {
+	let mut state = automaton.start();
+	for &byte in text.as_bytes() {
+		if !automaton.can_match(&state) {
+			return false;
+		}
+		state = automaton.accept(&state, byte);
+	}
+	automaton.is_match(&state)
+}
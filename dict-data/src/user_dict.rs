@@ -0,0 +1,437 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::ErrorKind;
+use std::path::Path;
+
+use fst::automaton::Automaton;
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::pattern::Pattern;
+use crate::term_index;
+
+/// User dictionary with custom terms, loaded at runtime.
+///
+/// User dictionaries are layered over the main dictionary by `Dictionary`
+/// and participate in all searches. The terms are indexed in memory, using
+/// the same search keys as the main dictionary.
+///
+/// Terms can be loaded from a JSON or CSV file:
+///
+/// - JSON files contain a list of objects with the `expression`, `reading`,
+///   `glosses` and `tags` fields. Only the `expression` is required.
+/// - CSV files contain the `expression,reading,glosses,tags` columns, where
+///   the `glosses` are separated by `;` and the `tags` by spaces. Fields can
+///   be quoted with `"` and an optional header line is ignored.
+pub struct UserDict {
+	source: String,
+	entries: Vec<UserEntry>,
+
+	/// Map of expression and reading to entry indexes.
+	terms: BTreeMap<String, Vec<usize>>,
+
+	/// Map of romaji search key to entry indexes.
+	search: BTreeMap<String, Vec<usize>>,
+
+	/// Map of lowercase English words in the glosses to entry indexes.
+	english: HashMap<String, Vec<usize>>,
+}
+
+/// A single term from a user dictionary.
+#[derive(Clone, Deserialize)]
+pub struct UserEntry {
+	pub expression: String,
+
+	/// Kana reading, may be empty for kana only terms.
+	#[serde(default)]
+	pub reading: String,
+
+	/// English definitions.
+	#[serde(default)]
+	pub glosses: Vec<String>,
+
+	/// Tag names for the term (e.g. `n`, `product`).
+	#[serde(default)]
+	pub tags: Vec<String>,
+}
+
+impl UserDict {
+	/// Creates an empty user dictionary with the given source name.
+	pub fn new(source: &str) -> UserDict {
+		UserDict {
+			source: source.to_string(),
+			entries: Vec::new(),
+			terms: BTreeMap::new(),
+			search: BTreeMap::new(),
+			english: HashMap::new(),
+		}
+	}
+
+	/// Loads a user dictionary from a `.json` or `.csv` file. The source name
+	/// is the file name without extension.
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<UserDict> {
+		let path = path.as_ref();
+		let source = path.file_stem().unwrap_or_default().to_string_lossy();
+		let text = std::fs::read_to_string(path).map_err(|err| match err.kind() {
+			ErrorKind::NotFound => Error::MissingFile(path.to_string_lossy().to_string()),
+			ErrorKind::InvalidData => Error::invalid_data(path.to_string_lossy(), err),
+			_ => Error::Io(err),
+		})?;
+		let is_json = match path.extension() {
+			Some(ext) => ext.to_string_lossy().to_lowercase() == "json",
+			None => false,
+		};
+		if is_json {
+			UserDict::from_json(&source, &text)
+		} else {
+			UserDict::from_csv(&source, &text)
+		}
+	}
+
+	/// Loads a user dictionary from JSON text.
+	pub fn from_json(source: &str, text: &str) -> Result<UserDict> {
+		let entries: Vec<UserEntry> =
+			serde_json::from_str(text).map_err(|err| Error::invalid_data(source, err))?;
+		let mut dict = UserDict::new(source);
+		for it in entries {
+			dict.add(it);
+		}
+		Ok(dict)
+	}
+
+	/// Loads a user dictionary from CSV text.
+	pub fn from_csv(source: &str, text: &str) -> Result<UserDict> {
+		let mut dict = UserDict::new(source);
+		for (line_number, line) in text.lines().enumerate() {
+			if line.trim().is_empty() {
+				continue;
+			}
+
+			let fields = parse_csv_line(line).ok_or_else(|| {
+				let message = format!("invalid CSV at line {}", line_number + 1);
+				Error::invalid_data(source, message)
+			})?;
+			let field = |n: usize| fields.get(n).map(|x| x.trim()).unwrap_or_default();
+			if line_number == 0 && field(0) == "expression" {
+				continue;
+			}
+
+			dict.add(UserEntry {
+				expression: field(0).to_string(),
+				reading: field(1).to_string(),
+				glosses: field(2)
+					.split(';')
+					.map(|x| x.trim().to_string())
+					.filter(|x| !x.is_empty())
+					.collect(),
+				tags: field(3).split_whitespace().map(|x| x.to_string()).collect(),
+			});
+		}
+		Ok(dict)
+	}
+
+	/// Adds a term to the dictionary.
+	///
+	/// Readings are normalized to hiragana. Kana only terms without a reading
+	/// use the expression as reading.
+	pub fn add(&mut self, mut entry: UserEntry) {
+		entry.expression = entry.expression.trim().to_string();
+		entry.reading = entry.reading.trim().to_string();
+		if entry.expression.is_empty() {
+			if entry.reading.is_empty() {
+				return;
+			}
+			entry.expression = entry.reading.clone();
+		}
+
+		entry.reading = if entry.reading.is_empty() {
+			if entry.expression.chars().any(kana::is_kanji) {
+				String::new()
+			} else {
+				kana::to_hiragana(&entry.expression)
+			}
+		} else {
+			kana::to_hiragana(&entry.reading)
+		};
+
+		let index = self.entries.len();
+		let push = |map: &mut BTreeMap<String, Vec<usize>>, key: &str| {
+			let list = map.entry(key.to_string()).or_default();
+			if list.last() != Some(&index) {
+				list.push(index);
+			}
+		};
+
+		push(&mut self.terms, &entry.expression);
+		if !entry.reading.is_empty() {
+			push(&mut self.terms, &entry.reading);
+		}
+
+		let search_key = kana::to_search_key(if !entry.reading.is_empty() {
+			&entry.reading
+		} else {
+			&entry.expression
+		});
+		if !search_key.is_empty() {
+			push(&mut self.search, &search_key);
+		}
+
+		for gloss in entry.glosses.iter() {
+			for word in english_words(gloss) {
+				let list = self.english.entry(word).or_default();
+				if list.last() != Some(&index) {
+					list.push(index);
+				}
+			}
+		}
+
+		self.entries.push(entry);
+	}
+
+	/// Source name for the dictionary terms.
+	pub fn source(&self) -> &str {
+		&self.source
+	}
+
+	/// Number of terms in the dictionary.
+	pub fn count(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn entry(&self, index: usize) -> &UserEntry {
+		&self.entries[index]
+	}
+
+	/// Returns true if any entry has the tag `name`.
+	pub fn has_tag(&self, name: &str) -> bool {
+		self.entries.iter().any(|x| x.tags.iter().any(|x| x == name))
+	}
+
+	/// Searches entries with an expression or reading equal to `text`.
+	pub fn search_exact(&self, text: &str) -> Vec<usize> {
+		self.terms.get(text).cloned().unwrap_or_default()
+	}
+
+	/// Searches entries with an expression or reading starting with `prefix`.
+	pub fn search_prefix(&self, prefix: &str) -> Vec<usize> {
+		collect_prefix(&self.terms, prefix)
+	}
+
+	/// Searches entries with a romaji search key starting with `key`.
+	pub fn search_romaji(&self, key: &str) -> Vec<usize> {
+		if key.is_empty() {
+			return Vec::new();
+		}
+		collect_prefix(&self.search, key)
+	}
+
+	/// Searches entries with an expression or reading accepted by `automaton`.
+	pub(crate) fn search_terms_matching<A: Automaton>(&self, automaton: &A) -> Vec<usize> {
+		let mut output = Vec::new();
+		for (term, list) in self.terms.iter() {
+			if term_index::is_match(automaton, term) {
+				output.extend_from_slice(list);
+			}
+		}
+		output.sort();
+		output.dedup();
+		output
+	}
+
+	/// Searches entries with an expression or reading (or a romaji search key,
+	/// for romaji patterns) matching `pattern`.
+	pub fn search_pattern(&self, pattern: &Pattern) -> Vec<usize> {
+		let map = if pattern.is_romaji() {
+			&self.search
+		} else {
+			&self.terms
+		};
+		let mut output = Vec::new();
+		for (term, list) in map.iter() {
+			if pattern.is_match(term) {
+				output.extend_from_slice(list);
+			}
+		}
+		output.sort();
+		output.dedup();
+		output
+	}
+
+	/// Returns the romaji search keys accepted by `automaton`, with the entries
+	/// for each key.
+	pub(crate) fn keys_matching<A: Automaton>(&self, automaton: &A) -> Vec<(String, Vec<usize>)> {
+		self.search
+			.iter()
+			.filter(|(key, _)| term_index::is_match(automaton, key))
+			.map(|(key, list)| (key.clone(), list.clone()))
+			.collect()
+	}
+
+	/// Searches entries with all the English `words` in their glosses. The
+	/// words must be lowercase.
+	pub fn search_english(&self, words: &[String]) -> Vec<usize> {
+		let mut output: Option<Vec<usize>> = None;
+		for word in words.iter() {
+			let list = self.english.get(word).cloned().unwrap_or_default();
+			output = Some(match output {
+				Some(output) => output
+					.into_iter()
+					.filter(|x| list.binary_search(x).is_ok())
+					.collect(),
+				None => list,
+			});
+		}
+		output.unwrap_or_default()
+	}
+
+	/// Searches entries with an expression or reading containing all the
+	/// characters in `text`.
+	pub fn search_chars(&self, text: &str) -> Vec<usize> {
+		let chars: Vec<char> = text.chars().filter(|x| !x.is_whitespace()).collect();
+		let has_chars = |term: &str| chars.iter().all(|&chr| term.contains(chr));
+		self.entries
+			.iter()
+			.enumerate()
+			.filter(|(_, x)| has_chars(&x.expression) || has_chars(&x.reading))
+			.map(|(index, _)| index)
+			.collect()
+	}
+}
+
+fn collect_prefix(map: &BTreeMap<String, Vec<usize>>, prefix: &str) -> Vec<usize> {
+	let mut output = Vec::new();
+	for (key, list) in map.range(prefix.to_string()..) {
+		if !key.starts_with(prefix) {
+			break;
+		}
+		output.extend_from_slice(list);
+	}
+	output.sort();
+	output.dedup();
+	output
+}
+
+/// Splits a text into lowercase English words, the same way as the English
+/// index for the main dictionary.
+pub(crate) fn english_words(text: &str) -> Vec<String> {
+	text.split(|c: char| !c.is_alphabetic())
+		.filter(|x| !x.is_empty() && !kana::is_japanese(x.chars().next().unwrap(), true))
+		.map(|x| x.to_lowercase())
+		.collect()
+}
+
+/// Parses a CSV line, with support for quoted fields (using `""` to escape a
+/// quote). Returns `None` if a quoted field is not terminated.
+fn parse_csv_line(line: &str) -> Option<Vec<String>> {
+	let mut fields = Vec::new();
+	let mut field = String::new();
+	let mut chars = line.chars().peekable();
+	let mut quoted = false;
+	while let Some(chr) = chars.next() {
+		match chr {
+			'"' if quoted => {
+				if chars.peek() == Some(&'"') {
+					chars.next();
+					field.push('"');
+				} else {
+					quoted = false;
+				}
+			}
+			'"' if field.trim().is_empty() => {
+				field.clear();
+				quoted = true;
+			}
+			',' if !quoted => {
+				fields.push(field);
+				field = String::new();
+			}
+			_ => field.push(chr),
+		}
+	}
+	if quoted {
+		return None;
+	}
+	fields.push(field);
+	Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_csv_line() {
+		fn check(line: &str, expected: &[&str]) {
+			let expected = expected.iter().map(|x| x.to_string()).collect();
+			assert_eq!(Some(expected), parse_csv_line(line), "line `{}`", line);
+		}
+
+		check("a,b,c", &["a", "b", "c"]);
+		check("a,,c,", &["a", "", "c", ""]);
+		check("", &[""]);
+
+		// Quoted fields
+		check(r#""a,b",c"#, &["a,b", "c"]);
+		check(r#"a, "b, c" ,d"#, &["a", "b, c ", "d"]);
+		check(r#""""#, &[""]);
+
+		// Escaped quotes
+		check(r#""say ""hi""",x"#, &[r#"say "hi""#, "x"]);
+		check(r#""""""#, &[r#"""#]);
+
+		// Quotes inside an unquoted field are kept
+		check(r#"a"b,c"#, &[r#"a"b"#, "c"]);
+
+		// Unterminated quotes
+		assert_eq!(None, parse_csv_line(r#""abc"#));
+		assert_eq!(None, parse_csv_line(r#"a,"b,c"#));
+		assert_eq!(None, parse_csv_line(r#""a""b"#));
+	}
+
+	#[test]
+	fn test_from_csv() {
+		let text = "expression,reading,glosses,tags\n\
+		            漢字,かんじ,\"kanji; Chinese characters\",n\n\
+		            \n\
+		            カナ,,katakana;;  ; kana ,n  product\n";
+		let dict = UserDict::from_csv("test", text).unwrap();
+		assert_eq!("test", dict.source());
+		assert_eq!(2, dict.count());
+
+		let entry = dict.entry(0);
+		assert_eq!("漢字", entry.expression);
+		assert_eq!("かんじ", entry.reading);
+		assert_eq!(vec!["kanji", "Chinese characters"], entry.glosses);
+		assert_eq!(vec!["n"], entry.tags);
+
+		let entry = dict.entry(1);
+		assert_eq!("カナ", entry.expression);
+		assert_eq!("かな", entry.reading);
+		assert_eq!(vec!["katakana", "kana"], entry.glosses);
+		assert_eq!(vec!["n", "product"], entry.tags);
+	}
+
+	#[test]
+	fn test_from_csv_without_header() {
+		let dict =
+			UserDict::from_csv("test", "漢字,かんじ\nexpression,えくすぷれっしょん").unwrap();
+		assert_eq!(2, dict.count());
+		assert_eq!("漢字", dict.entry(0).expression);
+		assert_eq!(0, dict.entry(0).glosses.len());
+
+		// The header is only skipped in the first line.
+		assert_eq!("expression", dict.entry(1).expression);
+	}
+
+	#[test]
+	fn test_from_csv_error() {
+		let text = "expression,reading\n漢字,かんじ\n\n\"カナ,かな\n";
+		match UserDict::from_csv("test", text) {
+			Err(Error::InvalidData { file, message }) => {
+				assert_eq!("test", file);
+				assert_eq!("invalid CSV at line 4", message);
+			}
+			Err(err) => panic!("unexpected error: {}", err),
+			Ok(_) => panic!("expected an error"),
+		}
+	}
+}
@@ -37,6 +37,45 @@ pub struct Dict {
 	/// Frequency metadata for kanji.
 	#[serde(skip)]
 	pub meta_kanji: Vec<Meta>,
+
+	/// List of imported proper names (e.g. from JMnedict).
+	#[serde(skip)]
+	pub names: Vec<Name>,
+
+	/// List of imported example sentences.
+	#[serde(skip)]
+	pub examples: Vec<Example>,
+
+	/// Stroke order data for kanji (e.g. from KanjiVG).
+	#[serde(skip)]
+	pub strokes: Vec<KanjiStrokes>,
+}
+
+impl Dict {
+	/// Creates an empty dictionary for sources that are not in the Yomichan
+	/// format (i.e. without an `index.json`).
+	pub fn new(title: &str) -> Dict {
+		Dict {
+			title: title.to_string(),
+			format: 3,
+			revision: String::new(),
+			terms: Vec::new(),
+			kanji: Vec::new(),
+			tags: Vec::new(),
+			meta_terms: Vec::new(),
+			meta_kanji: Vec::new(),
+			names: Vec::new(),
+			examples: Vec::new(),
+			strokes: Vec::new(),
+		}
+	}
+
+	/// Returns true if this is a dictionary of proper names (JMnedict).
+	///
+	/// Terms from a names dictionary are imported as `names` instead.
+	pub fn is_names(&self) -> bool {
+		self.title.starts_with("JMnedict")
+	}
 }
 
 /// Dictionary entry for a term.
@@ -86,11 +125,11 @@ pub struct Term {
 impl fmt::Display for Term {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		write!(f, "-> {}", self.expression)?;
-		if self.reading.len() > 0 && self.reading != self.expression {
+		if !self.reading.is_empty() && self.reading != self.expression {
 			write!(f, " 「{}」", self.reading)?;
 		}
 		write!(f, " -- {}/{}", self.sequence, self.score)?;
-		if self.term_tags.len() > 0 {
+		if !self.term_tags.is_empty() {
 			write!(f, "  {}", self.term_tags.join(", "))?;
 		}
 		writeln!(f)?;
@@ -108,7 +147,7 @@ impl fmt::Display for Term {
 				}
 				write!(f, "rules: {}", self.rules.join(", "))?;
 			}
-			write!(f, "]\n")?;
+			writeln!(f, "]")?;
 		}
 
 		write!(f, "   {}", self.glossary.join("; "))?;
@@ -116,6 +155,112 @@ impl fmt::Display for Term {
 	}
 }
 
+/// Dictionary entry for a proper name (people, places, companies, etc).
+pub struct Name {
+	/// Name expression. This is the same as `reading` for kana only names.
+	pub expression: String,
+
+	/// Kana reading for the name.
+	pub reading: String,
+
+	/// Name types (e.g. `surname`, `given`, `place`, `company`).
+	pub types: Vec<String>,
+
+	/// Translations for the name (usually the romanized name).
+	pub translations: Vec<String>,
+}
+
+impl fmt::Display for Name {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "-> {}", self.expression)?;
+		if !self.reading.is_empty() && self.reading != self.expression {
+			write!(f, " 「{}」", self.reading)?;
+		}
+		if !self.types.is_empty() {
+			write!(f, "  [{}]", self.types.join(", "))?;
+		}
+		write!(f, "\n   {}", self.translations.join("; "))
+	}
+}
+
+/// Example sentence with its translation.
+pub struct Example {
+	/// Japanese sentence.
+	pub japanese: String,
+
+	/// English translation.
+	pub english: String,
+
+	/// Indexed words for the sentence, if available. Those link the sentence
+	/// to the dictionary entries.
+	pub words: Vec<ExampleWord>,
+}
+
+/// Indexed word from an example sentence.
+pub struct ExampleWord {
+	/// Dictionary form of the word.
+	pub expression: String,
+
+	/// Reading for the word, if given.
+	pub reading: String,
+}
+
+impl fmt::Display for ExampleWord {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if !self.reading.is_empty() {
+			write!(f, "{}({})", self.expression, self.reading)
+		} else {
+			write!(f, "{}", self.expression)
+		}
+	}
+}
+
+/// Stroke order data for a single kanji.
+pub struct KanjiStrokes {
+	pub character: char,
+
+	/// Strokes in stroke order.
+	pub strokes: Vec<Stroke>,
+
+	/// Stroke groups (components) for the kanji, in document order. The first
+	/// group is the kanji itself, containing all strokes.
+	pub groups: Vec<StrokeGroup>,
+}
+
+/// A single kanji stroke.
+pub struct Stroke {
+	/// Stroke type (e.g. `㇐`), may be empty.
+	pub kind: String,
+
+	/// SVG path data for the stroke, in a 109x109 coordinate system.
+	pub path: String,
+}
+
+/// A group of consecutive strokes forming a kanji component.
+pub struct StrokeGroup {
+	/// Nesting level of the group, zero for the top level group.
+	pub level: usize,
+
+	/// One-based number of the first stroke in the group.
+	pub first: usize,
+
+	/// Number of strokes in the group.
+	pub count: usize,
+
+	/// Element (component) represented by the group, if any.
+	pub element: String,
+
+	/// Radical annotation (e.g. `general`, `tradit`, `nelson`), if the group
+	/// is the kanji radical.
+	pub radical: String,
+
+	/// Position of the group in the parent (e.g. `left`, `right`, `top`).
+	pub position: String,
+
+	/// Part number for elements split in multiple groups.
+	pub part: String,
+}
+
 /// Dictionary entry for a kanji.
 #[derive(Serialize)]
 pub struct Kanji {
@@ -143,6 +288,175 @@ pub struct Kanji {
 
 	/// Frequency information for this kanji.
 	pub frequency: u32,
+
+	/// Typed kanji metadata.
+	pub info: KanjiInfo,
+}
+
+impl Kanji {
+	/// Merges the data from another entry for the same kanji into this one.
+	///
+	/// Fields already present in this entry take precedence.
+	pub fn merge(&mut self, other: Kanji) {
+		fn extend(target: &mut Vec<String>, source: Vec<String>) {
+			for it in source {
+				if !target.contains(&it) {
+					target.push(it);
+				}
+			}
+		}
+
+		extend(&mut self.onyomi, other.onyomi);
+		extend(&mut self.kunyomi, other.kunyomi);
+		extend(&mut self.tags, other.tags);
+		extend(&mut self.meanings, other.meanings);
+		for (key, value) in other.stats {
+			self.stats.entry(key).or_insert(value);
+		}
+		self.info.merge(other.info);
+	}
+}
+
+/// Typed metadata for a kanji, mostly from KANJIDIC2.
+///
+/// Missing values are `None` (or empty for lists).
+#[derive(Default, Serialize)]
+pub struct KanjiInfo {
+	/// Stroke count.
+	pub strokes: Option<u32>,
+
+	/// School grade in which the kanji is taught (1-6), or `8` for the
+	/// remaining jouyou kanji, `9` and `10` for jinmeiyou kanji.
+	pub grade: Option<u32>,
+
+	/// JLPT level (pre-2010, from `4` to `1`).
+	pub jlpt: Option<u32>,
+
+	/// Frequency rank among the 2500 most used kanji in newspapers.
+	pub frequency_rank: Option<u32>,
+
+	/// Classical (KangXi Zidian) radical number.
+	pub radical: Option<u32>,
+
+	/// Nelson radical number, when different from the classical radical.
+	pub radical_nelson: Option<u32>,
+
+	/// Variant forms, as `type:code` (e.g. `jis208:52-01`, `ucs:5f8c`).
+	pub variants: Vec<String>,
+
+	/// Readings used only in names.
+	pub nanori: Vec<String>,
+
+	/// Korean readings in hangul.
+	pub korean: Vec<String>,
+
+	/// Korean readings in romanized form.
+	pub korean_romanized: Vec<String>,
+
+	/// Chinese readings in pinyin.
+	pub pinyin: Vec<String>,
+
+	/// Index numbers in reference dictionaries.
+	pub references: KanjiReferences,
+}
+
+impl KanjiInfo {
+	/// Fills missing values in this instance from `other`.
+	pub fn merge(&mut self, other: KanjiInfo) {
+		fn merge_list(target: &mut Vec<String>, source: Vec<String>) {
+			if target.is_empty() {
+				*target = source;
+			}
+		}
+
+		self.strokes = self.strokes.or(other.strokes);
+		self.grade = self.grade.or(other.grade);
+		self.jlpt = self.jlpt.or(other.jlpt);
+		self.frequency_rank = self.frequency_rank.or(other.frequency_rank);
+		self.radical = self.radical.or(other.radical);
+		self.radical_nelson = self.radical_nelson.or(other.radical_nelson);
+		merge_list(&mut self.variants, other.variants);
+		merge_list(&mut self.nanori, other.nanori);
+		merge_list(&mut self.korean, other.korean);
+		merge_list(&mut self.korean_romanized, other.korean_romanized);
+		merge_list(&mut self.pinyin, other.pinyin);
+		self.references.merge(other.references);
+	}
+
+	/// Parses the typed values from a Yomichan `stats` map.
+	pub fn from_stats(stats: &HashMap<String, String>) -> KanjiInfo {
+		let mut info = KanjiInfo::default();
+		for (key, value) in stats.iter() {
+			info.set_value(key, value);
+		}
+		info
+	}
+
+	/// Sets a single value given by its KANJIDIC2 name (e.g. `strokes`,
+	/// `grade`, `heisig`, `nelson_c`). Unknown names are ignored.
+	pub fn set_value(&mut self, key: &str, value: &str) {
+		let number = value.trim().parse::<u32>().ok();
+		let target = match key {
+			"strokes" => &mut self.strokes,
+			"grade" => &mut self.grade,
+			"jlpt" => &mut self.jlpt,
+			"freq" => &mut self.frequency_rank,
+			"heisig" => &mut self.references.heisig,
+			"heisig6" => &mut self.references.heisig6,
+			"nelson_c" => &mut self.references.nelson_classic,
+			"nelson_n" => &mut self.references.nelson_new,
+			"halpern_njecd" => &mut self.references.halpern_njecd,
+			"halpern_kkd" => &mut self.references.halpern_kkd,
+			"halpern_kkld" => &mut self.references.halpern_kkld,
+			"halpern_kkld_2ed" => &mut self.references.halpern_kkld_2ed,
+			_ => return,
+		};
+		if target.is_none() {
+			*target = number;
+		}
+	}
+}
+
+/// Kanji index numbers in reference dictionaries.
+#[derive(Default, Serialize)]
+pub struct KanjiReferences {
+	/// "Remembering The Kanji" by James Heisig.
+	pub heisig: Option<u32>,
+
+	/// "Remembering The Kanji", sixth edition.
+	pub heisig6: Option<u32>,
+
+	/// "Modern Reader's Japanese-English Character Dictionary" by Andrew Nelson.
+	pub nelson_classic: Option<u32>,
+
+	/// "The New Nelson Japanese-English Character Dictionary" by John Haig.
+	pub nelson_new: Option<u32>,
+
+	/// "New Japanese-English Character Dictionary" by Jack Halpern.
+	pub halpern_njecd: Option<u32>,
+
+	/// "Kodansha Kanji Dictionary" by Jack Halpern.
+	pub halpern_kkd: Option<u32>,
+
+	/// "Kanji Learners Dictionary" by Jack Halpern.
+	pub halpern_kkld: Option<u32>,
+
+	/// "Kanji Learners Dictionary", second edition.
+	pub halpern_kkld_2ed: Option<u32>,
+}
+
+impl KanjiReferences {
+	/// Fills missing values in this instance from `other`.
+	pub fn merge(&mut self, other: KanjiReferences) {
+		self.heisig = self.heisig.or(other.heisig);
+		self.heisig6 = self.heisig6.or(other.heisig6);
+		self.nelson_classic = self.nelson_classic.or(other.nelson_classic);
+		self.nelson_new = self.nelson_new.or(other.nelson_new);
+		self.halpern_njecd = self.halpern_njecd.or(other.halpern_njecd);
+		self.halpern_kkd = self.halpern_kkd.or(other.halpern_kkd);
+		self.halpern_kkld = self.halpern_kkld.or(other.halpern_kkld);
+		self.halpern_kkld_2ed = self.halpern_kkld_2ed.or(other.halpern_kkld_2ed);
+	}
 }
 
 impl fmt::Display for Kanji {
@@ -164,11 +478,11 @@ impl fmt::Display for Kanji {
 			write!(f, " 」")?;
 		}
 		writeln!(f)?;
-		if self.tags.len() > 0 {
-			write!(f, "   [{}]\n", self.tags.join(", "))?;
+		if !self.tags.is_empty() {
+			writeln!(f, "   [{}]", self.tags.join(", "))?;
 		}
 		write!(f, "   {}", self.meanings.join("; "))?;
-		if self.stats.len() > 0 {
+		if !self.stats.is_empty() {
 			let mut pairs: Vec<_> = self.stats.iter().collect();
 			pairs.sort();
 			let pairs: Vec<_> = pairs
@@ -0,0 +1,85 @@
+//! Import of example sentences from a Tatoeba style TSV file.
+//!
+//! Each line in the file is a sentence pair, in one of the formats:
+//!
+//! - `japanese<TAB>english[<TAB>index]` where `index` is an optional word
+//!   index in the format of the Tanaka Corpus "B lines" (e.g.
+//!   `彼(かれ)[01] は 何時も{いつも} 忙しい`).
+//! - `id<TAB>japanese<TAB>id<TAB>english` as exported by the Tatoeba project
+//!   "sentence pairs" download.
+//!
+//! Empty lines and lines starting with `#` are ignored.
+
+use std::fs;
+use std::io;
+
+use regex::Regex;
+use unicode_normalization::UnicodeNormalization;
+
+use dict::*;
+
+/// Source name for the imported examples.
+const EXAMPLES_TITLE: &str = "Tatoeba";
+
+/// Imports the example sentences from a TSV file.
+pub fn import_examples<P: AsRef<std::path::Path>>(path: P) -> io::Result<Dict> {
+	let start = std::time::Instant::now();
+
+	let path = path.as_ref();
+	println!("\n>>> Importing from {:}", path.to_string_lossy());
+
+	let mut dict = Dict::new(EXAMPLES_TITLE);
+	let text = fs::read_to_string(path)?;
+	for (line_number, line) in text.lines().enumerate() {
+		let line = line.trim_end_matches('\r');
+		if line.trim().is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		let fields: Vec<&str> = line.split('\t').map(|x| x.trim()).collect();
+		let is_numeric = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+		let (japanese, english, index) = if fields.len() == 4 && is_numeric(fields[0]) {
+			(fields[1], fields[3], "")
+		} else if fields.len() >= 2 {
+			(fields[0], fields[1], fields.get(2).cloned().unwrap_or_default())
+		} else {
+			println!("[WARN] invalid example at line {}: {}", line_number + 1, line);
+			continue;
+		};
+
+		dict.examples.push(Example {
+			japanese: japanese.nfc().collect(),
+			english: english.nfc().collect(),
+			words: parse_index(index),
+		});
+	}
+
+	println!("... Elapsed {:?}", start.elapsed());
+	println!("... Loaded {} examples", dict.examples.len());
+
+	Ok(dict)
+}
+
+/// Parses a "B line" word index from the Tanaka Corpus.
+///
+/// Each word in the index is in the format `word(reading)[sense]{form}~`, where
+/// all but `word` are optional. Only the word and reading are used, since
+/// senses are not kept in our dictionary.
+fn parse_index(index: &str) -> Vec<ExampleWord> {
+	lazy_static! {
+		static ref RE_WORD: Regex =
+			Regex::new(r"^([^(\[{~]+)(?:\(([^)]*)\))?(?:\[\d+\])?(?:\{[^}]*\})?~?$").unwrap();
+	}
+
+	let mut words = Vec::new();
+	for it in index.split_whitespace() {
+		if let Some(caps) = RE_WORD.captures(it) {
+			let reading = caps.get(2).map(|x| x.as_str()).unwrap_or_default();
+			words.push(ExampleWord {
+				expression: caps[1].nfc().collect(),
+				reading: reading.nfc().collect(),
+			});
+		}
+	}
+	words
+}
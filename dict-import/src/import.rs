@@ -9,13 +9,19 @@ use serde::Deserialize;
 use serde_json;
 
 use dict::*;
+use jmnedict::import_jmnedict;
+use kanjidic::import_kanjidic;
+use kanjivg::{import_kanjivg, import_kanjivg_archive, is_kanjivg_archive};
+use xml::XmlReader;
 
 use unicode_normalization::UnicodeNormalization;
 
 /// Imports a `.zip` file containing Yomichan compatible dictionary data.
+///
+/// KanjiVG archives with SVG files are also supported.
 pub fn import_file<P: AsRef<std::path::Path>>(path: P) -> Result<Dict, std::io::Error> {
 	/// The index file contains the basic information about the dictionary data.
-	const INDEX_FILE_NAME: &'static str = "index.json";
+	const INDEX_FILE_NAME: &str = "index.json";
 
 	let start = std::time::Instant::now();
 
@@ -26,6 +32,13 @@ pub fn import_file<P: AsRef<std::path::Path>>(path: P) -> Result<Dict, std::io::
 	let file = fs::File::open(path)?;
 	let mut archive = zip::ZipArchive::new(file)?;
 
+	if is_kanjivg_archive(&mut archive) {
+		let dict = import_kanjivg_archive(&mut archive)?;
+		println!("... Elapsed {:?}", start.elapsed());
+		println!("... Loaded {} kanji strokes", dict.strokes.len());
+		return Ok(dict);
+	}
+
 	let index_file = archive.by_name(INDEX_FILE_NAME)?;
 	let mut dict: Dict = serde_json::from_reader(index_file)?;
 
@@ -63,11 +76,54 @@ pub fn import_file<P: AsRef<std::path::Path>>(path: P) -> Result<Dict, std::io::
 	println!("... Elapsed {:?}", start.elapsed());
 
 	use std::cmp::max;
+	if dict.is_names() {
+		println!("... Loaded {} names", dict.names.len());
+	} else {
+		println!(
+			"... Loaded {} terms / {} kanji / {} tags",
+			max(dict.terms.len(), dict.meta_terms.len()),
+			max(dict.kanji.len(), dict.meta_kanji.len()),
+			dict.tags.len()
+		);
+	}
+
+	Ok(dict)
+}
+
+/// Imports a `.xml` file containing dictionary data. The format is detected
+/// from the root element.
+///
+/// Supported formats are:
+///
+/// - KANJIDIC2 (`kanjidic2` root)
+/// - JMnedict (`JMnedict` root)
+/// - KanjiVG (`kanjivg` root)
+pub fn import_xml_file<P: AsRef<std::path::Path>>(path: P) -> Result<Dict, std::io::Error> {
+	let start = std::time::Instant::now();
+
+	let path = path.as_ref();
+	let path_str = path.to_string_lossy();
+	println!("\n>>> Importing from {:}", path_str);
+
+	let mut reader = XmlReader::open(path)?;
+	let root = reader.root()?.unwrap_or_default();
+	let dict = match root.as_str() {
+		"kanjidic2" => import_kanjidic(reader)?,
+		"JMnedict" => import_jmnedict(reader)?,
+		"kanjivg" => import_kanjivg(reader)?,
+		_ => {
+			let err = format!("unsupported XML file `{}` (root is `{}`)", path_str, root);
+			return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+		}
+	};
+
+	println!("... {:} -- {:}", dict.title, dict.revision);
+	println!("... Elapsed {:?}", start.elapsed());
 	println!(
-		"... Loaded {} terms / {} kanji / {} tags",
-		max(dict.terms.len(), dict.meta_terms.len()),
-		max(dict.kanji.len(), dict.meta_kanji.len()),
-		dict.tags.len()
+		"... Loaded {} kanji / {} names / {} kanji strokes",
+		dict.kanji.len(),
+		dict.names.len(),
+		dict.strokes.len()
 	);
 
 	Ok(dict)
@@ -95,31 +151,53 @@ where
 					String,      // term tags (CSV)
 				);
 				let rows: Vec<TermRow> = serde_json::from_reader(entry_file)?;
+				if dict.is_names() {
+					for it in rows {
+						let expression = it.0.trim().nfc().collect::<String>();
+						let reading = it.1.trim().nfc().collect::<String>();
+						dict.names.push(Name {
+							expression: if !expression.is_empty() {
+								expression
+							} else {
+								reading.clone()
+							},
+							reading,
+							types: csv(&it.2),
+							translations: it
+								.5
+								.iter()
+								.map(|x| x.trim().nfc().collect::<String>())
+								.filter(|x| !x.is_empty())
+								.collect(),
+						});
+					}
+					return Ok(());
+				}
 				for it in rows {
 					let expression = it.0.trim().nfc().collect::<String>();
 					let reading = it.1.trim().nfc().collect::<String>();
-					let expression = if expression.len() > 0 {
+					let expression = if !expression.is_empty() {
 						expression
 					} else {
 						reading.clone()
 					};
 					let reading = if reading == "させ方" {
 						"させかた".to_string()
-					} else if reading.len() == 0 && expression.chars().all(|c| !kana::is_kanji(c)) {
+					} else if reading.is_empty() && expression.chars().all(|c| !kana::is_kanji(c)) {
 						kana::to_hiragana(&expression)
 					} else {
 						kana::to_hiragana(reading)
 					};
 
-					let search_key = get_search_key(if reading.len() > 0 {
+					let search_key = get_search_key(if !reading.is_empty() {
 						reading.as_str()
 					} else {
 						expression.as_str()
 					});
 					let term = Term {
-						expression: expression,
-						reading: reading,
-						search_key: search_key,
+						expression,
+						reading,
+						search_key,
 						definition_tags: csv(&it.2),
 						rules: csv(&it.3),
 						score: it.4,
@@ -127,13 +205,13 @@ where
 							.5
 							.iter()
 							.map(|x| x.trim().nfc().collect::<String>())
-							.filter(|x| x.len() > 0)
+							.filter(|x| !x.is_empty())
 							.collect(),
 						sequence: it.6,
 						term_tags: csv(&it.7),
 						source: dict.title.clone(),
 					};
-					if term.expression.len() == 0 {
+					if term.expression.is_empty() {
 						println!("[WARN] empty term - {}", term);
 					}
 					dict.terms.push(term);
@@ -151,6 +229,7 @@ where
 				);
 				let rows: Vec<KanjiRow> = serde_json::from_reader(entry_file)?;
 				for it in rows {
+					let info = KanjiInfo::from_stats(&it.5);
 					dict.kanji.push(Kanji {
 						character: it.0,
 						onyomi: csv(&it.1),
@@ -160,6 +239,7 @@ where
 						stats: it.5,
 						source: dict.title.clone(),
 						frequency: 0,
+						info,
 					});
 				}
 			}
@@ -218,7 +298,7 @@ fn read_meta<R: io::Read>(input: R) -> io::Result<Vec<Meta>> {
 }
 
 fn csv(ls: &str) -> Vec<String> {
-	if ls.len() == 0 {
+	if ls.is_empty() {
 		Vec::new()
 	} else {
 		ls.split(' ').map(|s| s.trim().nfc().collect()).collect()
@@ -249,37 +329,20 @@ fn get_kind(file_name: &str) -> Option<DataKind> {
 /// - The romaji conversion in the kana library handles the most corner cases
 ///   in terms of weird characters (e.g. iteration marks, old characters, etc).
 /// - It allows for incomplete syllables in romaji searches.
+///
+/// The key itself is generated by `kana::to_search_key`, which is also used
+/// by `dict_data` to index user dictionaries at runtime.
 fn get_search_key(term: &str) -> String {
 	lazy_static! {
-		static ref RE_REPLACE: Regex = Regex::new(r"[-,'‘’/~]").unwrap();
 		static ref RE_VALIDATE: Regex = Regex::new(r"^[a-z0-9]+$").unwrap();
 	}
 
-	match term {
-		// spell-checker: disable
-		"ヽ" => "odoriji".to_string(),
-		"ヾ" => "odoriji".to_string(),
-		"ゝ" => "odoriji".to_string(),
-		"ゞ" => "odoriji".to_string(),
-		"ー" => "chooonpu".to_string(),
-		// spell-checker: enable
-		_ => {
-			let key = kana::to_romaji(term).to_lowercase();
-
-			// Eliminate hepburn style conversions derived from `ー`
-			let key = kana::expand_romaji(key);
-
-			// Replace other symbol characters that can be generated form the romanization
-			let key = RE_REPLACE.replace_all(key.as_str(), "").to_string();
-
-			if !RE_VALIDATE.is_match(key.as_str()) {
-				println!(
-					"WARNING: term `{}` generated an invalid search key: `{}`",
-					term, key
-				);
-			}
-
-			key
-		}
+	let key = kana::to_search_key(term);
+	if !RE_VALIDATE.is_match(key.as_str()) {
+		println!(
+			"WARNING: term `{}` generated an invalid search key: `{}`",
+			term, key
+		);
 	}
+	key
 }
@@ -0,0 +1,117 @@
+//! Import of the JMnedict (Japanese proper names) XML file.
+//!
+//! See http://www.edrdg.org/enamdict/enamdict_doc.html for the format.
+
+use std::io;
+
+use unicode_normalization::UnicodeNormalization;
+
+use dict::*;
+use xml::{Node, XmlReader};
+
+/// Source name for the imported names.
+const JMNEDICT_TITLE: &str = "JMnedict";
+
+/// Reading element (`r_ele`) for an entry.
+#[derive(Default)]
+struct Reading {
+	text: String,
+
+	/// Kanji elements this reading is restricted to. Empty if the reading
+	/// applies to all kanji elements.
+	restrict: Vec<String>,
+}
+
+/// Imports the names from a JMnedict file. The reader must be positioned after
+/// the `JMnedict` root element.
+pub fn import_jmnedict<R: io::BufRead>(mut reader: XmlReader<R>) -> io::Result<Dict> {
+	let mut dict = Dict::new(JMNEDICT_TITLE);
+
+	let mut kanji: Vec<String> = Vec::new();
+	let mut readings: Vec<Reading> = Vec::new();
+	let mut types: Vec<String> = Vec::new();
+	let mut translations: Vec<String> = Vec::new();
+
+	while let Some(node) = reader.read()? {
+		let name = match node {
+			Node::Open { name, .. } => name,
+			Node::Close(name) => {
+				if name == "entry" {
+					append_entry(&mut dict.names, &kanji, &readings, &types, &translations);
+					kanji.clear();
+					readings.clear();
+					types.clear();
+					translations.clear();
+				}
+				continue;
+			}
+			Node::Text(_) => continue,
+		};
+
+		match name.as_str() {
+			"keb" => kanji.push(reader.text()?.trim().nfc().collect()),
+			"r_ele" => readings.push(Reading::default()),
+			"reb" => {
+				let text = reader.text()?.trim().nfc().collect();
+				if let Some(reading) = readings.last_mut() {
+					reading.text = text;
+				}
+			}
+			"re_restr" => {
+				let text = reader.text()?.trim().nfc().collect();
+				if let Some(reading) = readings.last_mut() {
+					reading.restrict.push(text);
+				}
+			}
+			"name_type" => {
+				// Name types are given as DTD entities (e.g. `&surname;`), we
+				// use the entity name as the tag, like Yomichan does.
+				let text = reader.text()?;
+				let text = text.trim().trim_start_matches('&').trim_end_matches(';');
+				if !types.iter().any(|x| x == text) {
+					types.push(text.to_string());
+				}
+			}
+			"trans_det" => {
+				let text: String = reader.text()?.trim().nfc().collect();
+				if !text.is_empty() {
+					translations.push(text);
+				}
+			}
+			_ => {}
+		}
+	}
+
+	Ok(dict)
+}
+
+/// Appends the names for a single JMnedict entry. An entry generates one name
+/// for each combination of kanji and reading elements.
+fn append_entry(
+	names: &mut Vec<Name>,
+	kanji: &[String],
+	readings: &[Reading],
+	types: &[String],
+	translations: &[String],
+) {
+	let mut push = |expression: &str, reading: &str| {
+		names.push(Name {
+			expression: expression.to_string(),
+			reading: reading.to_string(),
+			types: types.to_vec(),
+			translations: translations.to_vec(),
+		});
+	};
+
+	for reading in readings.iter() {
+		if kanji.is_empty() {
+			push(&reading.text, &reading.text);
+		} else {
+			for expression in kanji.iter() {
+				if reading.restrict.is_empty() || reading.restrict.contains(expression) {
+					push(expression, &reading.text);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,144 @@
+//! Import of the KANJIDIC2 XML file.
+//!
+//! See http://www.edrdg.org/wiki/index.php/KANJIDIC_Project for the format.
+
+use std::collections::HashMap;
+use std::io;
+
+use unicode_normalization::UnicodeNormalization;
+
+use dict::*;
+use xml::{Node, XmlReader};
+
+/// Source name for the imported kanji.
+const KANJIDIC_TITLE: &str = "KANJIDIC2";
+
+/// Imports the kanji from a KANJIDIC2 file. The reader must be positioned
+/// after the `kanjidic2` root element.
+pub fn import_kanjidic<R: io::BufRead>(mut reader: XmlReader<R>) -> io::Result<Dict> {
+	let mut dict = Dict::new(KANJIDIC_TITLE);
+
+	let mut current: Option<Kanji> = None;
+	while let Some(node) = reader.read()? {
+		let (name, attrs) = match node {
+			Node::Open { name, attrs } => (name, attrs),
+			Node::Close(name) => {
+				if name == "character" {
+					if let Some(kanji) = current.take() {
+						dict.kanji.push(kanji);
+					}
+				}
+				continue;
+			}
+			Node::Text(_) => continue,
+		};
+
+		if name == "character" {
+			current = Some(Kanji {
+				character: '\0',
+				onyomi: Vec::new(),
+				kunyomi: Vec::new(),
+				tags: Vec::new(),
+				meanings: Vec::new(),
+				stats: HashMap::new(),
+				source: dict.title.clone(),
+				frequency: 0,
+				info: KanjiInfo::default(),
+			});
+			continue;
+		} else if name == "database_version" {
+			dict.revision = reader.text()?;
+			continue;
+		}
+
+		let kanji = if let Some(kanji) = current.as_mut() {
+			kanji
+		} else {
+			continue;
+		};
+
+		let attr = |key: &str| attrs.get(key).map(|x| x.as_str()).unwrap_or_default();
+		match name.as_str() {
+			"literal" => {
+				let text = reader.text()?;
+				kanji.character = text.chars().next().unwrap_or_default();
+			}
+			"cp_value" => {
+				let text = reader.text()?;
+				kanji.stats.insert(attr("cp_type").to_string(), text);
+			}
+			"rad_value" => {
+				let value = reader.text()?.parse::<u32>().ok();
+				match attr("rad_type") {
+					"classical" => kanji.info.radical = value,
+					"nelson_c" => kanji.info.radical_nelson = value,
+					_ => {}
+				}
+			}
+			"grade" | "jlpt" | "freq" => {
+				let text = reader.text()?;
+				kanji.info.set_value(&name, &text);
+				kanji.stats.insert(name, text);
+			}
+			"stroke_count" => {
+				// Additional stroke counts are common miscounts.
+				let text = reader.text()?;
+				if kanji.info.strokes.is_none() {
+					kanji.info.set_value("strokes", &text);
+					kanji.stats.insert("strokes".to_string(), text);
+				}
+			}
+			"variant" => {
+				let text = reader.text()?;
+				let variant = format!("{}:{}", attr("var_type"), text);
+				kanji.info.variants.push(variant);
+			}
+			"dic_ref" => {
+				let key = attr("dr_type").to_string();
+				let text = reader.text()?;
+				kanji.info.set_value(&key, &text);
+				kanji.stats.insert(key, text);
+			}
+			"q_code" => {
+				let key = attr("qc_type").to_string();
+				let text = reader.text()?;
+				if !attrs.contains_key("skip_misclass") {
+					kanji.stats.insert(key, text);
+				}
+			}
+			"reading" => {
+				let text = reader.text()?.nfc().collect::<String>();
+				match attr("r_type") {
+					"ja_on" => kanji.onyomi.push(text),
+					"ja_kun" => kanji.kunyomi.push(text),
+					"pinyin" => kanji.info.pinyin.push(text),
+					"korean_r" => kanji.info.korean_romanized.push(text),
+					"korean_h" => kanji.info.korean.push(text),
+					_ => {}
+				}
+			}
+			"meaning" => {
+				let text = reader.text()?.nfc().collect::<String>();
+				if !attrs.contains_key("m_lang") {
+					kanji.meanings.push(text);
+				}
+			}
+			"nanori" => {
+				let text = reader.text()?.nfc().collect::<String>();
+				kanji.info.nanori.push(text);
+			}
+			_ => {}
+		}
+	}
+
+	for kanji in dict.kanji.iter_mut() {
+		match kanji.info.grade {
+			Some(1..=8) => kanji.tags.push("jouyou".to_string()),
+			Some(9..=10) => kanji.tags.push("jinmeiyou".to_string()),
+			_ => {}
+		}
+	}
+
+	dict.kanji.retain(|x| x.character != '\0');
+	Ok(dict)
+}
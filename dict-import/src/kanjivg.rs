@@ -0,0 +1,160 @@
+//! Import of the KanjiVG stroke order data.
+//!
+//! See https://kanjivg.tagaini.net/ for the format. Both the single XML file
+//! release (`kanjivg-YYYYMMDD.xml`) and the archive with one SVG file per
+//! kanji (`kanjivg-YYYYMMDD-main.zip`) are supported.
+
+use std::io;
+
+use dict::*;
+use xml::{Node, XmlReader};
+
+/// Source name for the imported stroke data.
+const KANJIVG_TITLE: &str = "KanjiVG";
+
+/// Imports the strokes from a KanjiVG XML file. The reader must be positioned
+/// after the `kanjivg` root element.
+pub fn import_kanjivg<R: io::BufRead>(mut reader: XmlReader<R>) -> io::Result<Dict> {
+	let mut dict = Dict::new(KANJIVG_TITLE);
+	while let Some(node) = reader.read()? {
+		if let Node::Open { name, attrs } = node {
+			if name != "kanji" {
+				continue;
+			}
+
+			// Kanji elements have ids in the `kvg:kanji_XXXXX` format.
+			let id = attrs.get("id").map(|x| x.as_str()).unwrap_or_default();
+			let code = id.trim_start_matches("kvg:kanji_");
+			let strokes = read_strokes(&mut reader)?;
+			if let Some(character) = parse_code(code) {
+				dict.strokes.push(KanjiStrokes {
+					character,
+					strokes: strokes.0,
+					groups: strokes.1,
+				});
+			}
+		}
+	}
+	Ok(dict)
+}
+
+/// Returns true if the archive is a KanjiVG archive (i.e. it contains SVG
+/// files instead of Yomichan data).
+pub fn is_kanjivg_archive<R: io::Read + io::Seek>(archive: &mut zip::ZipArchive<R>) -> bool {
+	for i in 0..archive.len() {
+		if let Ok(file) = archive.by_index(i) {
+			if file.name() == "index.json" {
+				return false;
+			} else if file.name().ends_with(".svg") {
+				return true;
+			}
+		}
+	}
+	false
+}
+
+/// Imports the strokes from a KanjiVG archive, with a `kanji/XXXXX.svg` file
+/// for each kanji.
+pub fn import_kanjivg_archive<R: io::Read + io::Seek>(
+	archive: &mut zip::ZipArchive<R>,
+) -> io::Result<Dict> {
+	let mut dict = Dict::new(KANJIVG_TITLE);
+	for i in 0..archive.len() {
+		let file = archive.by_index(i)?;
+		if !file.is_file() {
+			continue;
+		}
+
+		// Variant files (e.g. `XXXXX-Kaisho.svg`) are not imported since they
+		// don't use the default stroke order.
+		let path = file.mangled_name();
+		let character = match path.extension() {
+			Some(ext) if ext == "svg" => {
+				let code = path.file_stem().unwrap_or_default().to_string_lossy();
+				parse_code(&code)
+			}
+			_ => None,
+		};
+
+		if let Some(character) = character {
+			let mut reader = XmlReader::new(io::BufReader::new(file));
+			while let Some(node) = reader.read()? {
+				if let Node::Open { name, attrs } = node {
+					let id = attrs.get("id").map(|x| x.as_str()).unwrap_or_default();
+					if name == "g" && id.starts_with("kvg:StrokePaths_") {
+						let strokes = read_strokes(&mut reader)?;
+						dict.strokes.push(KanjiStrokes {
+							character,
+							strokes: strokes.0,
+							groups: strokes.1,
+						});
+						break;
+					}
+				}
+			}
+		}
+	}
+	Ok(dict)
+}
+
+/// Reads the strokes and stroke groups up to the end of the current element.
+fn read_strokes<R: io::BufRead>(
+	reader: &mut XmlReader<R>,
+) -> io::Result<(Vec<Stroke>, Vec<StrokeGroup>)> {
+	let mut strokes = Vec::new();
+	let mut groups: Vec<StrokeGroup> = Vec::new();
+
+	// Stack of the currently open groups, as indexes in `groups`.
+	let mut stack = Vec::new();
+
+	let mut depth = 0;
+	while let Some(node) = reader.read()? {
+		match node {
+			Node::Open { name, attrs } => {
+				depth += 1;
+				let attr = |key: &str| attrs.get(key).cloned().unwrap_or_default();
+				if name == "g" {
+					stack.push(groups.len());
+					groups.push(StrokeGroup {
+						level: stack.len() - 1,
+						first: strokes.len() + 1,
+						count: 0,
+						element: attr("kvg:element"),
+						radical: attr("kvg:radical"),
+						position: attr("kvg:position"),
+						part: attr("kvg:part"),
+					});
+				} else if name == "path" {
+					strokes.push(Stroke {
+						kind: attr("kvg:type"),
+						path: attr("d"),
+					});
+				}
+			}
+			Node::Close(name) => {
+				if depth == 0 {
+					break;
+				}
+				depth -= 1;
+				if name == "g" {
+					if let Some(index) = stack.pop() {
+						let group = &mut groups[index];
+						group.count = strokes.len() + 1 - group.first;
+					}
+				}
+			}
+			Node::Text(_) => {}
+		}
+	}
+
+	Ok((strokes, groups))
+}
+
+/// Parses the hexadecimal code point used in KanjiVG ids and file names.
+///
+/// Returns `None` for variants (e.g. `04e00-Kaisho`).
+fn parse_code(code: &str) -> Option<char> {
+	u32::from_str_radix(code, 16)
+		.ok()
+		.and_then(std::char::from_u32)
+}
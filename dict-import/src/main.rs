@@ -1,4 +1,5 @@
 extern crate deunicode;
+extern crate quick_xml;
 extern crate regex;
 extern crate serde;
 extern crate serde_json;
@@ -13,20 +14,25 @@ extern crate lazy_static;
 extern crate kana;
 
 use std::fs;
-use unicase::UniCase;
 
 mod dict;
+mod examples;
 mod import;
+mod jmnedict;
+mod kanjidic;
+mod kanjivg;
 mod writer;
+mod xml;
 
-use import::import_file;
+use examples::import_examples;
+use import::{import_file, import_xml_file};
 use writer::Writer;
 
 /// Directory with the data to be imported, relative to `Cargo.toml`.
-const IMPORT_DATA_DIRECTORY: &'static str = "../data/source";
+const IMPORT_DATA_DIRECTORY: &str = "../data/source";
 
 /// Output directory for the generated data, relative to `Cargo.toml`.
-const OUTPUT_DATA_DIRECTORY: &'static str = "../build/imported";
+const OUTPUT_DATA_DIRECTORY: &str = "../build/imported";
 
 fn main() {
 	let start = std::time::Instant::now();
@@ -66,9 +72,9 @@ fn import<P: AsRef<std::path::Path>>(import_dir: P) -> std::io::Result<()> {
 		if entry.file_type()?.is_file() {
 			let fullpath = entry.path();
 			if let Some(ext) = fullpath.extension() {
-				let ext = ext.to_string_lossy();
-				if UniCase::new(ext) == UniCase::new("zip") {
-					entries.push(fullpath);
+				let ext = ext.to_string_lossy().to_lowercase();
+				if ext == "zip" || ext == "xml" || ext == "tsv" {
+					entries.push((fullpath, ext));
 				}
 			}
 		}
@@ -76,8 +82,12 @@ fn import<P: AsRef<std::path::Path>>(import_dir: P) -> std::io::Result<()> {
 
 	println!("Found {} file(s) to import...", entries.len());
 	let mut writer = Writer::default();
-	for fs in entries {
-		let dict = import_file(fs)?;
+	for (fs, ext) in entries {
+		let dict = match ext.as_str() {
+			"xml" => import_xml_file(fs)?,
+			"tsv" => import_examples(fs)?,
+			_ => import_file(fs)?,
+		};
 		writer.append_dict(dict);
 	}
 
@@ -11,7 +11,7 @@ use serde::Serialize;
 use unicase::UniCase;
 use unicode_segmentation::UnicodeSegmentation;
 
-use crate::dict::{Dict, Kanji, Tag, Term};
+use crate::dict::{Dict, Example, Kanji, KanjiStrokes, Name, Tag, Term};
 
 #[derive(Default)]
 pub struct Writer {
@@ -27,13 +27,36 @@ pub struct Writer {
 	/// List of kanji from all dictionaries.
 	kanji: Vec<Kanji>,
 
+	/// Map of kanji character to its index in `kanji`.
+	kanji_map: HashMap<char, usize>,
+
+	/// List of proper names from all dictionaries.
+	names: Vec<Name>,
+
+	/// List of example sentences.
+	examples: Vec<Example>,
+
+	/// Stroke order data for kanji.
+	strokes: Vec<KanjiStrokes>,
+
 	/// Set of tags from all dictionaries by name.
 	tag_map: HashMap<String, Tag>,
+
+	/// Title and revision of the imported dictionaries, in import order.
+	dictionaries: Vec<DictionaryData>,
 }
 
 impl Writer {
 	/// Append dictionary data into the dictionary.
 	pub fn append_dict(&mut self, dict: Dict) {
+		let info = DictionaryData {
+			title: dict.title.clone(),
+			revision: dict.revision.clone(),
+		};
+		if !self.dictionaries.contains(&info) {
+			self.dictionaries.push(info);
+		}
+
 		for it in dict.tags {
 			self.append_tag(it);
 		}
@@ -53,10 +76,21 @@ impl Writer {
 			self.terms.push(it);
 		}
 
+		self.names.extend(dict.names);
+		self.examples.extend(dict.examples);
+		self.strokes.extend(dict.strokes);
+
+		// Kanji from multiple sources (e.g. KANJIDIC2 and the Yomichan
+		// kanjidic) are merged into a single entry.
 		for it in dict.kanji {
 			self.map_tags(it.tags.clone());
 			self.map_tags(it.stats.keys().cloned().collect());
-			self.kanji.push(it);
+			if let Some(&index) = self.kanji_map.get(&it.character) {
+				self.kanji[index].merge(it);
+			} else {
+				self.kanji_map.insert(it.character, self.kanji.len());
+				self.kanji.push(it);
+			}
 		}
 	}
 
@@ -95,7 +129,7 @@ impl Writer {
 		all_tags.sort_by(|a, b| UniCase::new(&a.0).cmp(&UniCase::new(&b.0)));
 		for (index, (key, tag)) in all_tags.into_iter().enumerate() {
 			let tag = TagData {
-				index: index,
+				index,
 				name: tag.name,
 				category: tag.category,
 				order: tag.order,
@@ -112,7 +146,7 @@ impl Writer {
 		let sort_tag = |a: &usize, b: &usize| {
 			let tag_a = &tag_order[a];
 			let tag_b = &tag_order[b];
-			tag_a.cmp(&tag_b)
+			tag_a.cmp(tag_b)
 		};
 
 		// Helper to map tag name to index.
@@ -163,16 +197,16 @@ impl Writer {
 		let mut english: HashMap<String, HashSet<usize>> = HashMap::new();
 
 		for term in self.terms.iter() {
-			if term.source.len() > 0 {
+			if !term.source.is_empty() {
 				sources.insert(term.source.as_str());
 			}
-			if term.expression.len() > 0 {
+			if !term.expression.is_empty() {
 				terms.insert(term.expression.as_str());
 			}
-			if term.reading.len() > 0 {
+			if !term.reading.is_empty() {
 				terms.insert(term.reading.as_str());
 			}
-			if term.search_key.len() > 0 {
+			if !term.search_key.is_empty() {
 				search.insert(term.search_key.as_str());
 			}
 			for it in term.glossary.iter() {
@@ -220,7 +254,7 @@ impl Writer {
 		let start = Instant::now();
 
 		let get_index =
-			|m: &HashMap<&str, usize>, key: &str| if key.len() == 0 { 0 } else { m[key] + 1 };
+			|m: &HashMap<&str, usize>, key: &str| if key.is_empty() { 0 } else { m[key] + 1 };
 
 		// First index dictionary terms by `(expression, reading)`. This already
 		// interns the strings and maps tags:
@@ -231,8 +265,8 @@ impl Writer {
 			let read = get_index(&terms_map, &term.reading);
 			let key = (expr, read);
 			let data = term_map.entry(key).or_insert_with(|| TermData {
-				expr: expr,
-				read: read,
+				expr,
+				read,
 				look: get_index(&search_map, &term.search_key),
 				freq: freq_terms
 					.get(&term.expression)
@@ -260,7 +294,7 @@ impl Writer {
 			if term_a.freq != term_b.freq {
 				term_b.freq.cmp(&term_a.freq)
 			} else {
-				idx_a.cmp(&idx_b)
+				idx_a.cmp(idx_b)
 			}
 		});
 
@@ -304,11 +338,11 @@ impl Writer {
 			let entry_index = entry_index + 1;
 			let entry_expr = terms_index.entry(term.expr).or_default();
 			entry_expr.insert(entry_index);
-			append_char_index(&mut chars_index, entry_index, &terms[term.expr - 1]);
+			append_char_index(&mut chars_index, entry_index, terms[term.expr - 1]);
 			if term.read != 0 {
 				let entry_read = terms_index.entry(term.read).or_default();
 				entry_read.insert(entry_index);
-				append_char_index(&mut chars_index, entry_index, &terms[term.read - 1]);
+				append_char_index(&mut chars_index, entry_index, terms[term.read - 1]);
 			}
 			if term.look != 0 {
 				let entry_look = search_index.entry(term.look).or_default();
@@ -325,7 +359,7 @@ impl Writer {
 					(term_index, entries)
 				})
 				.collect();
-			index.sort_by(|(index_a, _), (index_b, _)| index_a.cmp(&index_b));
+			index.sort_by_key(|(index_a, _)| *index_a);
 			let index: Vec<_> = index.into_iter().map(|x| x.1).collect();
 			index
 		};
@@ -342,7 +376,7 @@ impl Writer {
 				(chr, indexes)
 			})
 			.collect();
-		chars_index.sort_by(|(_, a), (_, b)| a.len().cmp(&b.len()));
+		chars_index.sort_by_key(|(_, a)| a.len());
 
 		println!("... - compiled dictionary indexes ({:?})", start.elapsed());
 
@@ -374,7 +408,7 @@ impl Writer {
 				})
 				.collect();
 			// Sort the list by the reversed string.
-			reverse_list.sort_by(|(_, a), (_, b)| a.cmp(&b));
+			reverse_list.sort_by(|(_, a), (_, b)| a.cmp(b));
 			// Take only the indexes.
 			let reverse_list: Vec<_> = reverse_list.into_iter().map(|x| x.0).collect();
 			reverse_list
@@ -385,6 +419,49 @@ impl Writer {
 		let search_index_reverse = build_reverse_list(&search);
 		println!("... - build reverse indexes ({:?})", start.elapsed());
 
+		//
+		// Names:
+		//
+
+		let start = Instant::now();
+
+		let mut names_strings: HashSet<&str> = HashSet::new();
+		for it in self.names.iter() {
+			if !it.expression.is_empty() {
+				names_strings.insert(it.expression.as_str());
+			}
+			if !it.reading.is_empty() {
+				names_strings.insert(it.reading.as_str());
+			}
+		}
+
+		let (names_strings, names_map) = string_hash_to_list(names_strings);
+
+		// Names are sorted by expression and reading, which are the interned
+		// string indexes.
+		let mut names: Vec<_> = self
+			.names
+			.iter()
+			.map(|it| {
+				let expr = get_index(&names_map, &it.expression);
+				let read = get_index(&names_map, &it.reading);
+				(expr, read, it)
+			})
+			.collect();
+		names.sort_by_key(|a| (a.0, a.1));
+
+		let mut names_index: HashMap<usize, HashSet<usize>> = HashMap::new();
+		for (index, (expr, read, _)) in names.iter().enumerate() {
+			names_index.entry(*expr).or_default().insert(index + 1);
+			if *read != 0 {
+				names_index.entry(*read).or_default().insert(index + 1);
+			}
+		}
+
+		let names_index = build_index(names_index);
+		let names_index_reverse = build_reverse_list(&names_strings);
+		println!("... - compiled names ({:?})", start.elapsed());
+
 		//
 		// English index:
 		//
@@ -393,8 +470,9 @@ impl Writer {
 		for (index, term) in dictionary.iter().enumerate() {
 			for def in term.defs.iter() {
 				for it in def.text.iter() {
-					for sub in RE_SPLIT_ENGLISH.split(&glossary[*it - 1]) {
-						if sub.len() > 0 && !kana::is_japanese(sub.chars().next().unwrap(), true) {
+					for sub in RE_SPLIT_ENGLISH.split(glossary[*it - 1]) {
+						if !sub.is_empty() && !kana::is_japanese(sub.chars().next().unwrap(), true)
+						{
 							let key = deunicode::deunicode(sub).to_lowercase();
 							let entry = english.entry(key).or_default();
 							entry.insert(index + 1);
@@ -411,7 +489,7 @@ impl Writer {
 		}
 
 		let mut english = english.iter().collect::<Vec<_>>();
-		english.sort_by(|a, b| a.0.cmp(&b.0));
+		english.sort_by(|a, b| a.0.cmp(b.0));
 		println!("... - compiled english index ({:?})", start.elapsed());
 
 		//====================================================================//
@@ -433,6 +511,16 @@ impl Writer {
 		let tags_file = BufWriter::new(fs::File::create(tags_path)?);
 		serde_json::to_writer_pretty(tags_file, &tags)?;
 
+		//--------------------------------------------------------------------//
+		// dictionaries.json
+		//--------------------------------------------------------------------//
+
+		println!("... writing dictionaries.json");
+		let mut dictionaries_path = data_dir.clone();
+		dictionaries_path.push("dictionaries.json");
+		let dictionaries_file = BufWriter::new(fs::File::create(dictionaries_path)?);
+		serde_json::to_writer_pretty(dictionaries_file, &self.dictionaries)?;
+
 		//--------------------------------------------------------------------//
 		// kanji.json
 		//--------------------------------------------------------------------//
@@ -458,16 +546,12 @@ impl Writer {
 		let mut dictionary_main_file = BufWriter::new(fs::File::create(dictionary_main_path)?);
 		let mut dictionary_data_file = BufWriter::new(fs::File::create(dictionary_data_path)?);
 		for it in dictionary.iter() {
-			write!(
+			writeln!(
 				dictionary_main_file,
-				"{},{},{},{}\n",
+				"{},{},{},{}",
 				it.expr, it.read, it.look, it.freq,
 			)?;
-			write!(
-				dictionary_data_file,
-				"{}\n",
-				serde_json::to_string(&it.defs)?
-			)?;
+			writeln!(dictionary_data_file, "{}", serde_json::to_string(&it.defs)?)?;
 		}
 
 		//--------------------------------------------------------------------//
@@ -479,7 +563,7 @@ impl Writer {
 		sources_path.push("sources.txt");
 		let mut sources_file = BufWriter::new(fs::File::create(sources_path)?);
 		for it in sources.iter() {
-			write!(sources_file, "{}\n", it)?;
+			writeln!(sources_file, "{}", it)?;
 		}
 
 		//--------------------------------------------------------------------//
@@ -492,7 +576,7 @@ impl Writer {
 
 		let mut terms_file = BufWriter::new(fs::File::create(terms_path)?);
 		for it in terms.iter() {
-			write!(terms_file, "{}\n", it)?;
+			writeln!(terms_file, "{}", it)?;
 		}
 
 		//--------------------------------------------------------------------//
@@ -505,7 +589,7 @@ impl Writer {
 
 		let mut glossary_file = BufWriter::new(fs::File::create(glossary_path)?);
 		for it in glossary.iter() {
-			write!(glossary_file, "{}\n", it)?;
+			writeln!(glossary_file, "{}", it)?;
 		}
 
 		//--------------------------------------------------------------------//
@@ -524,7 +608,7 @@ impl Writer {
 			for it in indexes {
 				write!(english_file, ",{}", it)?;
 			}
-			write!(english_file, "\n")?;
+			writeln!(english_file)?;
 		}
 
 		//--------------------------------------------------------------------//
@@ -537,7 +621,7 @@ impl Writer {
 
 		let mut search_file = BufWriter::new(fs::File::create(search_path)?);
 		for it in search.iter() {
-			write!(search_file, "{}\n", it)?;
+			writeln!(search_file, "{}", it)?;
 		}
 
 		//--------------------------------------------------------------------//
@@ -555,7 +639,7 @@ impl Writer {
 					for (n, it) in indexes.iter().enumerate() {
 						write!(output, "{}{}", if n > 0 { "," } else { "" }, it)?;
 					}
-					write!(output, "\n")?;
+					writeln!(output)?;
 				}
 				Ok(())
 			};
@@ -575,7 +659,7 @@ impl Writer {
 		for (chr, indexes) in chars_index {
 			write!(chars_index_file, "{},{}", chr, indexes.len())?;
 			output_csv_range(&mut chars_index_file, indexes)?;
-			write!(chars_index_file, "\n")?;
+			writeln!(chars_index_file)?;
 		}
 
 		//--------------------------------------------------------------------//
@@ -590,7 +674,7 @@ impl Writer {
 				let mut output = BufWriter::new(fs::File::create(index_path)?);
 				println!("... writing {}", name);
 				for it in index_data {
-					write!(output, "{}\n", it)?;
+					writeln!(output, "{}", it)?;
 				}
 				Ok(())
 			};
@@ -606,13 +690,116 @@ impl Writer {
 			search_index_reverse,
 		)?;
 
+		//--------------------------------------------------------------------//
+		// Names
+		//--------------------------------------------------------------------//
+
+		println!("... writing names ({} entries)", names.len());
+
+		let mut names_path = data_dir.clone();
+		names_path.push("names.txt");
+
+		let mut names_file = BufWriter::new(fs::File::create(names_path)?);
+		for it in names_strings.iter() {
+			writeln!(names_file, "{}", it)?;
+		}
+
+		let mut names_main_path = data_dir.clone();
+		names_main_path.push("names_main.txt");
+
+		let mut names_glossary_path = data_dir.clone();
+		names_glossary_path.push("names_glossary.txt");
+
+		let mut names_main_file = BufWriter::new(fs::File::create(names_main_path)?);
+		let mut names_glossary_file = BufWriter::new(fs::File::create(names_glossary_path)?);
+		for (expr, read, it) in names.iter() {
+			writeln!(names_main_file, "{},{},{}", expr, read, it.types.join(" "))?;
+			let translations: Vec<_> = it
+				.translations
+				.iter()
+				.map(|x| x.replace(['\t', '\n'], " "))
+				.collect();
+			writeln!(names_glossary_file, "{}", translations.join("\t"))?;
+		}
+
+		write_index(data_dir.clone(), "names_index.txt", names_index)?;
+		write_reverse_index(
+			data_dir.clone(),
+			"names_index_reverse.txt",
+			names_index_reverse,
+		)?;
+
+		//--------------------------------------------------------------------//
+		// examples.txt
+		//--------------------------------------------------------------------//
+
+		println!("... writing examples.txt ({} sentences)", self.examples.len());
+		let mut examples_path = data_dir.clone();
+		examples_path.push("examples.txt");
+
+		let clean = |text: &str| text.replace(['\t', '\n'], " ");
+		let mut examples_file = BufWriter::new(fs::File::create(examples_path)?);
+		for it in self.examples.iter() {
+			let words: Vec<_> = it.words.iter().map(|x| x.to_string()).collect();
+			writeln!(
+				examples_file,
+				"{}\t{}\t{}",
+				clean(&it.japanese),
+				clean(&it.english),
+				words.join(" ")
+			)?;
+		}
+
+		//--------------------------------------------------------------------//
+		// strokes.txt / stroke_groups.txt
+		//--------------------------------------------------------------------//
+
+		println!("... writing strokes.txt ({} kanji)", self.strokes.len());
+		let mut strokes = self.strokes;
+		strokes.sort_by_key(|a| a.character);
+		strokes.dedup_by(|a, b| a.character == b.character);
+
+		let mut strokes_path = data_dir.clone();
+		strokes_path.push("strokes.txt");
+
+		let mut groups_path = data_dir.clone();
+		groups_path.push("stroke_groups.txt");
+
+		let mut strokes_file = BufWriter::new(fs::File::create(strokes_path)?);
+		let mut groups_file = BufWriter::new(fs::File::create(groups_path)?);
+		for it in strokes.iter() {
+			for stroke in it.strokes.iter() {
+				writeln!(
+					strokes_file,
+					"{}\t{}\t{}",
+					it.character,
+					clean(&stroke.kind),
+					clean(&stroke.path)
+				)?;
+			}
+			for group in it.groups.iter() {
+				writeln!(
+					groups_file,
+					"{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+					it.character,
+					group.level,
+					group.first,
+					group.count,
+					clean(&group.element),
+					clean(&group.radical),
+					clean(&group.position),
+					clean(&group.part)
+				)?;
+			}
+		}
+
 		Ok(())
 	}
 
 	fn append_tag(&mut self, tag: Tag) {
 		if let Some(mut old_tag) = self.tag_map.get_mut(&tag.name) {
-			if tag.notes.len() > 0 && tag.notes != old_tag.notes {
-				if old_tag.notes.len() > 0 {
+			if !tag.notes.is_empty() && tag.notes != old_tag.notes {
+				if !old_tag.notes.is_empty() {
 					old_tag.notes = format!("{} / {}", old_tag.notes, tag.notes);
 				} else {
 					old_tag.notes = tag.notes;
@@ -635,7 +822,7 @@ impl Writer {
 	fn map_tags(&mut self, tags: Vec<String>) {
 		for name in tags {
 			self.append_tag(Tag {
-				name: name,
+				name,
 				category: String::new(),
 				order: 0,
 				notes: String::new(),
@@ -663,6 +850,12 @@ struct DefinitionData {
 	score: i32,
 }
 
+#[derive(PartialEq, Serialize)]
+struct DictionaryData {
+	title: String,
+	revision: String,
+}
+
 #[derive(Serialize)]
 struct TagData {
 	index: usize,
@@ -0,0 +1,128 @@
+//! Minimal streaming reader for the XML source files (e.g. KANJIDIC2).
+//!
+//! This wraps `quick_xml` into a simplified stream of `Node` values, which is
+//! enough for the flat, record based formats we import.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::BufRead;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// Simplified XML event.
+pub enum Node {
+	/// Start of an element, with its attributes. Empty elements generate an
+	/// `Open` followed by a `Close`.
+	Open {
+		name: String,
+		attrs: HashMap<String, String>,
+	},
+
+	/// Text content for the current element.
+	///
+	/// Entity references that cannot be resolved (e.g. DTD defined entities
+	/// such as `&surname;`) are kept verbatim.
+	Text(String),
+
+	/// End of an element.
+	Close(String),
+}
+
+pub struct XmlReader<R: BufRead> {
+	reader: Reader<R>,
+	buffer: Vec<u8>,
+}
+
+impl XmlReader<io::BufReader<fs::File>> {
+	/// Opens the XML file at the given path.
+	pub fn open<P: AsRef<std::path::Path>>(path: P) -> io::Result<Self> {
+		let file = fs::File::open(path)?;
+		Ok(XmlReader::new(io::BufReader::new(file)))
+	}
+}
+
+impl<R: BufRead> XmlReader<R> {
+	pub fn new(input: R) -> XmlReader<R> {
+		let mut reader = Reader::from_reader(input);
+		reader.trim_text(true);
+		reader.expand_empty_elements(true);
+		XmlReader {
+			reader,
+			buffer: Vec::new(),
+		}
+	}
+
+	/// Returns the next node from the input or `None` at the end of the file.
+	pub fn read(&mut self) -> io::Result<Option<Node>> {
+		loop {
+			self.buffer.clear();
+			let node = match self.reader.read_event(&mut self.buffer) {
+				Ok(Event::Start(el)) => {
+					let name = decode(&self.reader, el.name())?;
+					let mut attrs = HashMap::new();
+					for attr in el.attributes() {
+						let attr = attr.map_err(xml_error)?;
+						let key = decode(&self.reader, attr.key)?;
+						let value = attr
+							.unescape_and_decode_value(&self.reader)
+							.map_err(xml_error)?;
+						attrs.insert(key, value);
+					}
+					Node::Open { name, attrs }
+				}
+				Ok(Event::End(el)) => Node::Close(decode(&self.reader, el.name())?),
+				Ok(Event::Text(text)) | Ok(Event::CData(text)) => {
+					match text.unescape_and_decode(&self.reader) {
+						Ok(text) => Node::Text(text),
+						Err(_) => Node::Text(decode(&self.reader, &text)?),
+					}
+				}
+				Ok(Event::Eof) => return Ok(None),
+				Ok(_) => continue,
+				Err(err) => return Err(xml_error(err)),
+			};
+			return Ok(Some(node));
+		}
+	}
+
+	/// Reads the name of the root element, skipping any prolog.
+	pub fn root(&mut self) -> io::Result<Option<String>> {
+		while let Some(node) = self.read()? {
+			if let Node::Open { name, .. } = node {
+				return Ok(Some(name));
+			}
+		}
+		Ok(None)
+	}
+
+	/// Reads the whole text content of the current element up to its closing
+	/// tag. Text from nested elements is concatenated.
+	pub fn text(&mut self) -> io::Result<String> {
+		let mut depth = 0;
+		let mut output = String::new();
+		while let Some(node) = self.read()? {
+			match node {
+				Node::Open { .. } => depth += 1,
+				Node::Text(text) => output.push_str(&text),
+				Node::Close(_) => {
+					if depth == 0 {
+						break;
+					}
+					depth -= 1;
+				}
+			}
+		}
+		Ok(output)
+	}
+}
+
+fn decode<R: BufRead>(reader: &Reader<R>, bytes: &[u8]) -> io::Result<String> {
+	let text = reader.decode(bytes).map_err(xml_error)?;
+	Ok(text.to_string())
+}
+
+fn xml_error(err: quick_xml::Error) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, format!("XML error: {}", err))
+}
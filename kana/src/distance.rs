@@ -0,0 +1,171 @@
+//! Edit distance between romaji search keys.
+
+/// Cost of inserting or deleting a long vowel (e.g. `kyo` and `kyou`), or of
+/// replacing one long vowel spelling with another (e.g. `too` and `tou`).
+pub const LONG_VOWEL_COST: f32 = 0.25;
+
+/// Cost of inserting or deleting one `n` from `nn` (e.g. `konichiwa`).
+pub const DOUBLE_N_COST: f32 = 0.25;
+
+/// Cost of inserting or deleting the first consonant of a double consonant
+/// (e.g. `kite` and `kitte`, `macha` and `matcha`).
+pub const DOUBLE_CONSONANT_COST: f32 = 0.5;
+
+/// Cost of inserting or deleting the letters that differ between romanization
+/// systems (e.g. `si` and `shi`, `tu` and `tsu`).
+pub const ROMANIZATION_COST: f32 = 0.25;
+
+/// Cost of replacing a letter with a similar sounding one (e.g. the particle
+/// `ha` read as `wa`, `zi` and `ji`, `l` and `r`).
+pub const SIMILAR_COST: f32 = 0.5;
+
+/// Cost of any other insertion, deletion, replacement or transposition of two
+/// adjacent letters.
+pub const EDIT_COST: f32 = 1.0;
+
+/// Returns the edit distance between two romaji search keys (as returned by
+/// `to_search_key`), using Japanese-aware costs for common romaji variations.
+///
+/// The distance is symmetric and zero only if the keys are equal.
+pub fn romaji_distance<S: AsRef<str>, T: AsRef<str>>(a: S, b: T) -> f32 {
+	let a: Vec<char> = a.as_ref().chars().collect();
+	let b: Vec<char> = b.as_ref().chars().collect();
+
+	// `row[i][j]` is the distance between the first `i` characters of `a` and
+	// the first `j` characters of `b`.
+	let mut row = vec![vec![0.0; b.len() + 1]; a.len() + 1];
+	for i in 1..=a.len() {
+		row[i][0] = row[i - 1][0] + edit_cost(&a, i - 1);
+	}
+	for j in 1..=b.len() {
+		row[0][j] = row[0][j - 1] + edit_cost(&b, j - 1);
+	}
+
+	for i in 1..=a.len() {
+		for j in 1..=b.len() {
+			let delete = row[i - 1][j] + edit_cost(&a, i - 1);
+			let insert = row[i][j - 1] + edit_cost(&b, j - 1);
+			let replace = row[i - 1][j - 1] + replace_cost(&a, i - 1, &b, j - 1);
+			let mut cost = delete.min(insert).min(replace);
+			if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+				cost = cost.min(row[i - 2][j - 2] + EDIT_COST);
+			}
+			row[i][j] = cost;
+		}
+	}
+	row[a.len()][b.len()]
+}
+
+/// Cost of inserting or deleting the character at `index` in `key`.
+fn edit_cost(key: &[char], index: usize) -> f32 {
+	let chr = key[index];
+	let prev = if index > 0 { key[index - 1] } else { '\0' };
+	let next = key.get(index + 1).cloned().unwrap_or('\0');
+	if is_long_vowel(key, index) {
+		LONG_VOWEL_COST
+	} else if chr == 'n' && (prev == 'n' || next == 'n') {
+		DOUBLE_N_COST
+	} else if is_romaji_consonant(chr) && (next == chr || (chr == 't' && next == 'c')) {
+		DOUBLE_CONSONANT_COST
+	} else if is_romanization_variant(chr, prev, next) {
+		ROMANIZATION_COST
+	} else {
+		EDIT_COST
+	}
+}
+
+/// Cost of replacing the character at `ia` in `a` with the one at `ib` in `b`.
+fn replace_cost(a: &[char], ia: usize, b: &[char], ib: usize) -> f32 {
+	let (chr_a, chr_b) = (a[ia], b[ib]);
+	if chr_a == chr_b {
+		0.0
+	} else if is_long_vowel(a, ia) && is_long_vowel(b, ib) {
+		LONG_VOWEL_COST
+	} else if is_similar(chr_a, chr_b) || is_similar(chr_b, chr_a) {
+		SIMILAR_COST
+	} else {
+		EDIT_COST
+	}
+}
+
+/// Returns true if the vowel at `index` extends the previous vowel (e.g. the
+/// second vowel in `aa`, `ii`, `uu`, `ee`, `oo`, `ou` and `ei`).
+fn is_long_vowel(key: &[char], index: usize) -> bool {
+	if index == 0 {
+		return false;
+	}
+	matches!(
+		(key[index - 1], key[index]),
+		('a', 'a') | ('i', 'i') | ('u', 'u') | ('e', 'e') | ('o', 'o') | ('o', 'u') | ('e', 'i')
+	)
+}
+
+fn is_similar(a: char, b: char) -> bool {
+	matches!((a, b), ('w', 'h') | ('j', 'z') | ('f', 'h') | ('l', 'r'))
+}
+
+/// True for the letters that differ between the romanizations of the same
+/// kana, as the `h` in `shi` / `si` and `chi` / `ci`, and the `s` in `tsu` /
+/// `tu`.
+fn is_romanization_variant(chr: char, prev: char, next: char) -> bool {
+	match chr {
+		'h' => prev == 's' || prev == 'c',
+		's' => prev == 't' && next == 'u',
+		_ => false,
+	}
+}
+
+fn is_romaji_consonant(chr: char) -> bool {
+	super::util::is_consonant(chr, false)
+}
+
+// spell-checker: disable
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_romaji_distance() {
+		fn check(expected: f32, a: &str, b: &str) {
+			assert_eq!(expected, romaji_distance(a, b), "`{}` to `{}`", a, b);
+			assert_eq!(expected, romaji_distance(b, a), "`{}` to `{}`", b, a);
+		}
+
+		check(0.0, "", "");
+		check(0.0, "taberu", "taberu");
+		check(6.0, "", "taberu");
+
+		// Long vowels
+		check(0.25, "kyo", "kyou");
+		check(0.5, "tokyo", "toukyou");
+		check(0.25, "oneesan", "onesan");
+		check(0.5, "tookyoo", "toukyou");
+
+		// Double consonants and `nn`
+		check(0.5, "kite", "kitte");
+		check(0.5, "macha", "matcha");
+		check(0.25, "konichiha", "konnichiha");
+		check(0.75, "konichiwa", "konnichiha");
+
+		// Romanization
+		check(0.25, "sinbun", "shinbun");
+		check(0.25, "tukue", "tsukue");
+		check(0.5, "zisho", "jisho");
+		check(0.5, "hujisan", "fujisan");
+
+		// Typos
+		check(1.0, "nrko", "neko");
+		check(1.0, "tabreu", "taberu");
+		check(1.0, "tabru", "taberu");
+	}
+
+	#[test]
+	fn test_romaji_distance_order() {
+		assert!(romaji_distance("kyo", "kyou") < romaji_distance("kyo", "kyoto"));
+		assert!(romaji_distance("tokyo", "toukyou") < romaji_distance("tokyo", "kyouto"));
+		assert!(
+			romaji_distance("konichiwa", "konnichiha") < romaji_distance("konichiwa", "konnichi")
+		);
+	}
+}
@@ -35,6 +35,9 @@ pub use to::*;
 mod kind;
 pub use kind::*;
 
+mod distance;
+pub use distance::*;
+
 /// Expand hepburn style long vowels and katakana long marks `-` in the romaji
 /// string.
 pub fn expand_romaji<S: AsRef<str>>(input: S) -> String {
@@ -68,6 +71,32 @@ pub fn expand_romaji<S: AsRef<str>>(input: S) -> String {
 	}
 }
 
+/// Returns the romaji search key for a term.
+///
+/// The search key is an ASCII only string derived from the romaji for the
+/// term. Using romaji allows a single key to match katakana, hiragana and
+/// romaji searches, including incomplete syllables in romaji input.
+pub fn to_search_key<S: AsRef<str>>(term: S) -> String {
+	match term.as_ref() {
+		// spell-checker: disable
+		"ヽ" | "ヾ" | "ゝ" | "ゞ" => "odoriji".to_string(),
+		"ー" => "chooonpu".to_string(),
+		// spell-checker: enable
+		term => {
+			let key = to_romaji(term).to_lowercase();
+
+			// Eliminate hepburn style conversions derived from `ー`
+			let key = expand_romaji(key);
+
+			// Remove other symbol characters that can be generated from the
+			// romanization
+			key.chars()
+				.filter(|c| !"-,'‘’/~".contains(*c))
+				.collect()
+		}
+	}
+}
+
 // spell-checker: disable
 
 #[cfg(test)]
@@ -353,4 +382,15 @@ mod tests {
 			expand_romaji("a-i-u-e-o- / a---e--- / āīūēō / âîûêô / ā-ī-ū-ē-ō- / â-î-û-ê-ô-")
 		);
 	}
+
+	#[test]
+	fn test_to_search_key() {
+		assert_eq!("taberu", to_search_key("たべる"));
+		assert_eq!("taberu", to_search_key("タベル"));
+		assert_eq!("taberu", to_search_key("taberu"));
+		assert_eq!("konpyuutaa", to_search_key("コンピューター"));
+		assert_eq!("kinyuu", to_search_key("きにゅう"));
+		assert_eq!("odoriji", to_search_key("ゝ"));
+		assert_eq!("chooonpu", to_search_key("ー"));
+	}
 }
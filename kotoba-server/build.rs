@@ -0,0 +1,74 @@
+//! Checks the GraphiQL assets in `static/graphiql` against the checksums in
+//! `static/graphiql/SHA256SUMS`, which pin the bundled versions. The assets
+//! are embedded in the server (see `server::graphiql_asset`), so the build
+//! fails if any of them is missing or does not match its checksum.
+//!
+//! The assets are downloaded again with `make graphiql-assets`.
+
+extern crate sha2;
+
+use std::fs;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+const ASSETS_DIR: &str = "static/graphiql";
+
+/// Asset files, see `server::graphiql_asset`.
+const ASSETS: &[&str] = &[
+	"react.production.min.js",
+	"react-dom.production.min.js",
+	"graphiql.min.js",
+	"graphiql.min.css",
+];
+
+fn main() {
+	let dir = Path::new(ASSETS_DIR);
+	let checksums = dir.join("SHA256SUMS");
+	println!("cargo:rerun-if-changed={}", checksums.display());
+	for name in ASSETS {
+		println!("cargo:rerun-if-changed={}", dir.join(name).display());
+	}
+
+	let checksums = match fs::read_to_string(&checksums) {
+		Ok(text) => text,
+		Err(err) => panic!("could not read {}: {}", checksums.display(), err),
+	};
+	for name in ASSETS {
+		let expected = checksums
+			.lines()
+			.filter_map(|line| {
+				let mut parts = line.split_whitespace();
+				match (parts.next(), parts.next()) {
+					(Some(hash), Some(file)) if file.trim_start_matches('*') == *name => Some(hash),
+					_ => None,
+				}
+			})
+			.next();
+		let expected = match expected {
+			Some(hash) => hash.to_lowercase(),
+			None => panic!("{} has no checksum in SHA256SUMS", name),
+		};
+
+		let path = dir.join(name);
+		let data = fs::read(&path).unwrap_or_else(|err| {
+			panic!(
+				"could not read {}: {} (run `make graphiql-assets` to download it)",
+				path.display(),
+				err
+			)
+		});
+		let actual: String = Sha256::digest(&data)
+			.iter()
+			.map(|x| format!("{:02x}", x))
+			.collect();
+		if actual != expected {
+			panic!(
+				"checksum mismatch for {}: expected {}, found {} (run `make graphiql-assets` to download it again)",
+				path.display(),
+				expected,
+				actual
+			);
+		}
+	}
+}
@@ -0,0 +1,33 @@
+use rocket::http::Status;
+use rocket::State;
+use rocket_contrib::json::Json;
+
+use dict_data::Analysis;
+
+use crate::app::App;
+use crate::health::Ready;
+
+/// Request body for `analyze`.
+#[derive(Deserialize)]
+pub struct AnalyzeRequest {
+	text: String,
+}
+
+/// Splits a Japanese text into sentences and words, with the dictionary form,
+/// reading and candidate entries for each word (see `Dictionary::analyze`).
+///
+/// Responds with `500` if the dictionary could not be read.
+#[post("/analyze", format = "json", data = "<request>")]
+pub fn analyze(
+	app: State<&App>,
+	_ready: Ready,
+	request: Json<AnalyzeRequest>,
+) -> Result<Json<Analysis>, Status> {
+	match app.db().dictionary().analyze(&request.text) {
+		Ok(analysis) => Ok(Json(analysis)),
+		Err(err) => {
+			eprintln!("error analyzing text: {}", err);
+			Err(Status::InternalServerError)
+		}
+	}
+}
@@ -1,15 +1,102 @@
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use dict_data::Database;
+
+use crate::config::Config;
+use crate::metrics::Metrics;
+
 /// Maintains the global application state for the application.
-pub struct App {}
+pub struct App {
+	pub config: Config,
+
+	/// Time the application started.
+	pub started: Instant,
+
+	pub metrics: Metrics,
+
+	/// Dictionary database, set once loaded.
+	database: OnceLock<Database>,
+
+	status: Mutex<Status>,
+}
+
+/// Loading status of the database, see `App::load`.
+#[derive(Clone, PartialEq)]
+pub enum Status {
+	Loading,
+
+	/// The database is being loaded and its checksums checked.
+	Verifying,
+
+	Ready,
+
+	/// The database failed to load or failed the integrity check.
+	Failed(String),
+}
 
 impl App {
-	/// Initializes the application state and returns the static [App] instance.
-	pub fn get() -> &'static App {
-		lazy_static! {
-			static ref APP: App = {
-				let app = App {};
-				app
-			};
-		}
-		&APP
+	/// Creates the static [App] instance, which lives until the server exits.
+	/// The database must be loaded with `load`.
+	pub fn new(config: Config) -> &'static App {
+		let app = App {
+			config,
+			started: Instant::now(),
+			metrics: Metrics::new(),
+			database: OnceLock::new(),
+			status: Mutex::new(Status::Loading),
+		};
+		Box::leak(Box::new(app))
+	}
+
+	/// Loads the database for the configuration, checking its integrity if
+	/// `verify_database` is enabled. This can take a while, so the server is
+	/// started before and reports the status in the health endpoints.
+	pub fn load(&self) {
+		let path = &self.config.database;
+		let result = if self.config.verify_database {
+			self.set_status(Status::Verifying);
+			Database::open(path)
+		} else {
+			Database::open_unverified(path)
+		};
+		let mut db = match result {
+			Ok(db) => db,
+			Err(err) => {
+				let error = format!("failed to load database from {}: {}", path, err);
+				eprintln!("\n{}", error);
+				self.set_status(Status::Failed(error));
+				return;
+			}
+		};
+		db.dictionary_mut().set_cache_size(self.config.cache_size);
+		self.database.get_or_init(|| db);
+		self.set_status(Status::Ready);
+	}
+
+	pub fn status(&self) -> Status {
+		self.status.lock().unwrap().clone()
+	}
+
+	fn set_status(&self, status: Status) {
+		*self.status.lock().unwrap() = status;
+	}
+
+	/// True if the database is loaded and passed the integrity check.
+	pub fn is_ready(&self) -> bool {
+		self.status() == Status::Ready
+	}
+
+	/// Returns the database, which may still be under the integrity check.
+	pub fn try_db(&self) -> Option<&Database> {
+		self.database.get()
+	}
+
+	/// Returns the dictionary database.
+	///
+	/// Panics if the database is not loaded, so this can only be used by the
+	/// routes with the `Ready` guard.
+	pub fn db(&self) -> &Database {
+		self.database.get().expect("database is not loaded")
 	}
 }
@@ -0,0 +1,169 @@
+//! Server configuration, read from the extra keys in `Rocket.toml` for the
+//! active environment. Each key can also be set with a `ROCKET_` environment
+//! variable (e.g. `ROCKET_CACHE_SIZE=0`).
+//!
+//! The keys are:
+//!
+//! - `database`: path to the database file or directory. The `KOTOBA_DATABASE`
+//!   environment variable takes precedence.
+//! - `verify_database`: checks the database checksums when loading, before
+//!   the server is ready. Disabling it only checks the part sizes.
+//! - `cache_size`: size in bytes of the dictionary page cache (zero disables
+//!   the cache).
+//! - `graphiql`: enables the GraphiQL IDE at `/api/graphiql`.
+//! - `cors_origins`: list of origins allowed for cross-origin requests (e.g.
+//!   `["https://example.com"]`), where `*` allows any origin.
+//! - `max_depth`: maximum nesting of fields in a GraphQL query.
+//! - `max_complexity`: maximum complexity of a GraphQL query, or the total
+//!   for a batch of queries, see `limits`.
+//! - `max_aliases`: maximum number of aliased fields in a GraphQL query.
+//! - `limits.graphql`: maximum size in bytes of a GraphQL request body. The
+//!   JSON endpoints use the Rocket `limits.json` key.
+
+use rocket::config::ConfigError;
+
+use dict_data::DEFAULT_CACHE_SIZE;
+
+/// Environment variable with the path to the database file or directory.
+const DATABASE_PATH_VAR: &'static str = "KOTOBA_DATABASE";
+
+/// Default path for the database directory, relative to the working directory.
+const DEFAULT_DATABASE_PATH: &'static str = "../data/database";
+
+/// Default for `max_depth`.
+const DEFAULT_MAX_DEPTH: i64 = 12;
+
+/// Default for `max_complexity`.
+const DEFAULT_MAX_COMPLEXITY: i64 = 1000;
+
+/// Default for `max_aliases`.
+const DEFAULT_MAX_ALIASES: i64 = 20;
+
+/// Default for `limits.graphql`.
+const DEFAULT_GRAPHQL_LIMIT: u64 = 64 * 1024;
+
+/// Configuration for the server, see the module documentation.
+pub struct Config {
+	pub database: String,
+	pub verify_database: bool,
+	pub cache_size: usize,
+	pub graphiql: bool,
+	pub cors_origins: Vec<String>,
+	pub max_depth: usize,
+	pub max_complexity: usize,
+	pub max_aliases: usize,
+	pub graphql_limit: u64,
+}
+
+impl Config {
+	/// Reads the configuration from the Rocket configuration, returning all
+	/// the invalid keys as errors.
+	pub fn load(config: &rocket::Config) -> Result<Config, Vec<String>> {
+		let mut errors = Vec::new();
+
+		let database = match std::env::var(DATABASE_PATH_VAR) {
+			Ok(path) => path,
+			Err(_) => get(config.get_string("database"), &mut errors)
+				.unwrap_or_else(|| DEFAULT_DATABASE_PATH.to_string()),
+		};
+		if !std::path::Path::new(&database).exists() {
+			errors.push(format!("database path `{}` does not exist", database));
+		}
+
+		let verify_database = get(config.get_bool("verify_database"), &mut errors).unwrap_or(true);
+
+		let cache_size =
+			get(config.get_int("cache_size"), &mut errors).unwrap_or(DEFAULT_CACHE_SIZE as i64);
+		if cache_size < 0 {
+			errors.push(String::from("`cache_size` cannot be negative"));
+		}
+
+		let graphiql = get(config.get_bool("graphiql"), &mut errors).unwrap_or(true);
+
+		let mut cors_origins = Vec::new();
+		if let Some(list) = get(config.get_slice("cors_origins"), &mut errors) {
+			for item in list.iter() {
+				match item.as_str() {
+					Some(origin) if is_origin(origin) => cors_origins.push(origin.to_string()),
+					Some(origin) => errors.push(format!(
+						"invalid origin `{}` in `cors_origins`, expected `*` or a URL \
+						 such as `https://example.com`",
+						origin
+					)),
+					None => errors.push(String::from("`cors_origins` must be a list of strings")),
+				}
+			}
+		}
+
+		let max_depth = get(config.get_int("max_depth"), &mut errors).unwrap_or(DEFAULT_MAX_DEPTH);
+		if max_depth <= 0 {
+			errors.push(String::from("`max_depth` must be positive"));
+		}
+
+		let max_complexity =
+			get(config.get_int("max_complexity"), &mut errors).unwrap_or(DEFAULT_MAX_COMPLEXITY);
+		if max_complexity <= 0 {
+			errors.push(String::from("`max_complexity` must be positive"));
+		}
+
+		let max_aliases =
+			get(config.get_int("max_aliases"), &mut errors).unwrap_or(DEFAULT_MAX_ALIASES);
+		if max_aliases < 0 {
+			errors.push(String::from("`max_aliases` cannot be negative"));
+		}
+
+		let graphql_limit = config
+			.limits
+			.get("graphql")
+			.unwrap_or(DEFAULT_GRAPHQL_LIMIT);
+		if graphql_limit == 0 {
+			errors.push(String::from("`limits.graphql` must be positive"));
+		}
+		if config.limits.get("json") == Some(0) {
+			errors.push(String::from("`limits.json` must be positive"));
+		}
+
+		if !errors.is_empty() {
+			return Err(errors);
+		}
+		Ok(Config {
+			database,
+			verify_database,
+			cache_size: cache_size as usize,
+			graphiql,
+			cors_origins,
+			max_depth: max_depth as usize,
+			max_complexity: max_complexity as usize,
+			max_aliases: max_aliases as usize,
+			graphql_limit,
+		})
+	}
+}
+
+/// Returns the value of an optional key, adding an error if it has the wrong
+/// type.
+fn get<T>(value: Result<T, ConfigError>, errors: &mut Vec<String>) -> Option<T> {
+	match value {
+		Ok(value) => Some(value),
+		Err(ConfigError::Missing(_)) => None,
+		Err(err) => {
+			errors.push(err.to_string());
+			None
+		}
+	}
+}
+
+/// Returns true for `*` or an origin such as `https://example.com:8080`.
+fn is_origin(origin: &str) -> bool {
+	if origin == "*" {
+		return true;
+	}
+	let host = if origin.starts_with("http://") {
+		&origin["http://".len()..]
+	} else if origin.starts_with("https://") {
+		&origin["https://".len()..]
+	} else {
+		return false;
+	};
+	!host.is_empty() && !host.contains('/')
+}
@@ -0,0 +1,310 @@
+//! Relay-style cursor pagination for the GraphQL list fields.
+//!
+//! Cursors are opaque strings with the position of an item in the list and a
+//! stable key for the item (e.g. the entry index). Paging with a cursor uses
+//! the position directly, and the key checks that the item is still at that
+//! position (e.g. the cursor is not from a different list).
+
+use std::ops::Range;
+
+use juniper::{FieldError, FieldResult};
+
+use crate::graph::{DEFAULT_LIMIT, MAX_LIMIT};
+
+/// Pagination arguments for a connection field.
+pub struct PageArgs {
+	pub first: Option<i32>,
+	pub after: Option<String>,
+	pub last: Option<i32>,
+	pub before: Option<String>,
+}
+
+/// Information about the returned page of a connection.
+#[derive(juniper::GraphQLObject)]
+pub struct PageInfo {
+	/// True if there are more items after the page.
+	has_next_page: bool,
+
+	/// True if there are more items before the page.
+	has_previous_page: bool,
+
+	/// Cursor of the first item in the page.
+	start_cursor: Option<String>,
+
+	/// Cursor of the last item in the page.
+	end_cursor: Option<String>,
+}
+
+/// Page of a list, returned by `paginate`.
+pub struct Page {
+	/// Range of the page in the list.
+	pub range: Range<usize>,
+
+	/// Cursor for each item in the page.
+	pub cursors: Vec<String>,
+
+	pub info: PageInfo,
+}
+
+/// Returns the page of a list for the pagination arguments, where `keys` has
+/// the key for each item in the list and `kind` identifies the type of key.
+///
+/// The page has the `first` items after the `after` cursor, or the `last`
+/// items before the `before` cursor (or both). Without `first` and `last`, the
+/// page has the first `DEFAULT_LIMIT` items. At most `MAX_LIMIT` items are
+/// returned.
+pub fn paginate(kind: &str, keys: &[usize], args: PageArgs) -> FieldResult<Page> {
+	let mut range = 0..keys.len();
+	if let Some(after) = args.after {
+		range.start = find_cursor(kind, keys, &after)? + 1;
+	}
+	if let Some(before) = args.before {
+		range.end = find_cursor(kind, keys, &before)?;
+	}
+	range.end = range.end.max(range.start);
+
+	let first = match (args.first, args.last) {
+		(None, None) => Some(DEFAULT_LIMIT),
+		(first, _) => first,
+	};
+	if let Some(first) = first {
+		range.end = range.end.min(range.start + page_count("first", first)?);
+	}
+	if let Some(last) = args.last {
+		let last = page_count("last", last)?;
+		range.start = range.start.max(range.end.saturating_sub(last));
+	}
+
+	let cursors: Vec<String> = range
+		.clone()
+		.map(|position| encode_cursor(kind, position, keys[position]))
+		.collect();
+	let info = PageInfo {
+		has_next_page: range.end < keys.len(),
+		has_previous_page: range.start > 0,
+		start_cursor: cursors.first().cloned(),
+		end_cursor: cursors.last().cloned(),
+	};
+	Ok(Page {
+		range,
+		cursors,
+		info,
+	})
+}
+
+fn page_count(name: &str, count: i32) -> FieldResult<usize> {
+	if count < 0 {
+		let message = format!("`{}` cannot be negative", name);
+		return Err(FieldError::from(message));
+	}
+	Ok(count.min(MAX_LIMIT) as usize)
+}
+
+/// Returns the position in `keys` of the item for a cursor.
+fn find_cursor(kind: &str, keys: &[usize], cursor: &str) -> FieldResult<usize> {
+	let (position, key) = decode_cursor(kind, cursor)?;
+	match keys.get(position) {
+		Some(&x) if x == key => Ok(position),
+		_ => Err(FieldError::from("cursor is not in the list")),
+	}
+}
+
+/// Returns the cursor for the item at `position` in a list, with its key (e.g.
+/// `entry:4:123`, hex encoded).
+pub fn encode_cursor(kind: &str, position: usize, key: usize) -> String {
+	let cursor = format!("{}:{}:{}", kind, position, key);
+	cursor.bytes().map(|x| format!("{:02x}", x)).collect()
+}
+
+/// Returns the item position and key for a cursor from `encode_cursor`.
+pub fn decode_cursor(kind: &str, cursor: &str) -> FieldResult<(usize, usize)> {
+	let invalid = || FieldError::from(format!("invalid cursor `{}`", cursor));
+	if !cursor.len().is_multiple_of(2) || !cursor.is_ascii() {
+		return Err(invalid());
+	}
+	let bytes = (0..cursor.len())
+		.step_by(2)
+		.map(|n| u8::from_str_radix(&cursor[n..n + 2], 16))
+		.collect::<Result<Vec<u8>, _>>()
+		.map_err(|_| invalid())?;
+	let cursor = String::from_utf8(bytes).map_err(|_| invalid())?;
+	let mut parts = cursor.splitn(3, ':');
+	match (parts.next(), parts.next(), parts.next()) {
+		(Some(prefix), Some(position), Some(key)) if prefix == kind => {
+			let position = position.parse().map_err(|_| invalid())?;
+			let key = key.parse().map_err(|_| invalid())?;
+			Ok((position, key))
+		}
+		_ => Err(invalid()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn args(
+		first: Option<i32>,
+		after: Option<&str>,
+		last: Option<i32>,
+		before: Option<&str>,
+	) -> PageArgs {
+		PageArgs {
+			first,
+			after: after.map(String::from),
+			last,
+			before: before.map(String::from),
+		}
+	}
+
+	fn keys(count: usize) -> Vec<usize> {
+		(0..count).map(|x| x * 10 + 7).collect()
+	}
+
+	fn error(result: FieldResult<Page>) -> String {
+		match result {
+			Ok(page) => panic!("expected an error, got the page {:?}", page.range),
+			Err(err) => err.message().to_string(),
+		}
+	}
+
+	#[test]
+	fn test_cursor() {
+		let cursor = encode_cursor("entry", 4, 123);
+		assert_eq!("656e7472793a343a313233", cursor);
+		assert_eq!((4, 123), decode_cursor("entry", &cursor).unwrap());
+
+		for cursor in [
+			"",
+			"6",
+			"zz",
+			"656e7472793a34",
+			"656e7472793a343a78",
+			"\u{e9}e",
+		]
+		.iter()
+		{
+			let message = format!("invalid cursor `{}`", cursor);
+			match decode_cursor("entry", cursor) {
+				Err(err) => assert_eq!(message, err.message()),
+				Ok(result) => panic!("cursor `{}`: unexpected {:?}", cursor, result),
+			}
+		}
+	}
+
+	#[test]
+	fn test_first_after() {
+		let keys = keys(50);
+		let page = paginate("entry", &keys, args(None, None, None, None)).unwrap();
+		assert_eq!(0..DEFAULT_LIMIT as usize, page.range);
+		assert!(page.info.has_next_page);
+		assert!(!page.info.has_previous_page);
+		assert_eq!(page.cursors.first(), page.info.start_cursor.as_ref());
+		assert_eq!(page.cursors.last(), page.info.end_cursor.as_ref());
+
+		let page = paginate("entry", &keys, args(Some(5), None, None, None)).unwrap();
+		assert_eq!(0..5, page.range);
+		let after = page.info.end_cursor.unwrap();
+		let page = paginate("entry", &keys, args(Some(5), Some(&after), None, None)).unwrap();
+		assert_eq!(5..10, page.range);
+		assert!(page.info.has_next_page);
+		assert!(page.info.has_previous_page);
+		assert_eq!(encode_cursor("entry", 5, keys[5]), page.cursors[0]);
+
+		// Counts are capped.
+		let keys = self::keys(MAX_LIMIT as usize + 10);
+		let page = paginate("entry", &keys, args(Some(1000), None, None, None)).unwrap();
+		assert_eq!(0..MAX_LIMIT as usize, page.range);
+		let page = paginate("entry", &keys, args(Some(0), None, None, None)).unwrap();
+		assert_eq!(0..0, page.range);
+		assert_eq!(None, page.info.start_cursor);
+	}
+
+	#[test]
+	fn test_last_before() {
+		let keys = keys(10);
+		let page = paginate("entry", &keys, args(None, None, Some(3), None)).unwrap();
+		assert_eq!(7..10, page.range);
+		assert!(!page.info.has_next_page);
+		assert!(page.info.has_previous_page);
+
+		let before = page.info.start_cursor.unwrap();
+		let page = paginate("entry", &keys, args(None, None, Some(5), Some(&before))).unwrap();
+		assert_eq!(2..7, page.range);
+		let before = page.info.start_cursor.unwrap();
+		let page = paginate("entry", &keys, args(None, None, Some(5), Some(&before))).unwrap();
+		assert_eq!(0..2, page.range);
+		assert!(!page.info.has_previous_page);
+
+		// Both `first` and `last` in the range between the cursors.
+		let after = encode_cursor("entry", 1, keys[1]);
+		let before = encode_cursor("entry", 8, keys[8]);
+		let args = args(Some(4), Some(&after), Some(2), Some(&before));
+		assert_eq!(4..6, paginate("entry", &keys, args).unwrap().range);
+	}
+
+	#[test]
+	fn test_has_next_page() {
+		let keys = keys(10);
+		let page = paginate("entry", &keys, args(Some(10), None, None, None)).unwrap();
+		assert_eq!(0..10, page.range);
+		assert!(!page.info.has_next_page);
+		let page = paginate("entry", &keys, args(Some(9), None, None, None)).unwrap();
+		assert!(page.info.has_next_page);
+
+		let after = encode_cursor("entry", 4, keys[4]);
+		let page = paginate("entry", &keys, args(Some(5), Some(&after), None, None)).unwrap();
+		assert_eq!(5..10, page.range);
+		assert!(!page.info.has_next_page);
+		let page = paginate("entry", &keys, args(Some(4), Some(&after), None, None)).unwrap();
+		assert!(page.info.has_next_page);
+
+		// The last item has no next page.
+		let after = encode_cursor("entry", 9, keys[9]);
+		let page = paginate("entry", &keys, args(Some(5), Some(&after), None, None)).unwrap();
+		assert_eq!(10..10, page.range);
+		assert!(!page.info.has_next_page);
+		assert!(page.info.has_previous_page);
+
+		let page = paginate("entry", &[], args(None, None, None, None)).unwrap();
+		assert_eq!(0..0, page.range);
+		assert!(!page.info.has_next_page);
+		assert!(!page.info.has_previous_page);
+	}
+
+	#[test]
+	fn test_errors() {
+		let keys = keys(10);
+		let check = |args: PageArgs, expected: &str| {
+			assert_eq!(expected, error(paginate("entry", &keys, args)));
+		};
+
+		check(args(None, Some("zz"), None, None), "invalid cursor `zz`");
+		check(
+			args(Some(-1), None, None, None),
+			"`first` cannot be negative",
+		);
+		check(
+			args(None, None, Some(-1), None),
+			"`last` cannot be negative",
+		);
+
+		// A cursor from a different kind of list.
+		let cursor = encode_cursor("kanji", 2, keys[2]);
+		let message = format!("invalid cursor `{}`", cursor);
+		check(args(None, Some(&cursor), None, None), &message);
+
+		// A cursor for an item not at that position, or out of the list.
+		for &(position, key) in [(2, keys[3]), (10, keys[9]), (100, 7)].iter() {
+			let cursor = encode_cursor("entry", position, key);
+			check(
+				args(None, Some(&cursor), None, None),
+				"cursor is not in the list",
+			);
+			check(
+				args(None, None, None, Some(&cursor)),
+				"cursor is not in the list",
+			);
+		}
+	}
+}
@@ -0,0 +1,280 @@
+//! Kana conversion and character inspection, using the same `kana` functions
+//! as the dictionary searches.
+
+use rocket_contrib::json::Json;
+
+/// Target for `convert`.
+#[derive(juniper::GraphQLEnum, Deserialize, Clone, Copy)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConvertTarget {
+	/// Converts romaji and katakana to hiragana.
+	Hiragana,
+
+	/// Converts romaji and hiragana to katakana.
+	Katakana,
+
+	/// Converts kana to romaji.
+	Romaji,
+}
+
+/// Options for `convert`. Those only apply to `ROMAJI`.
+#[derive(juniper::GraphQLInputObject, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ConvertOptions {
+	/// Spells long vowels with a double vowel (e.g. `koohii` instead of
+	/// `ko-hi-` for `コーヒー`).
+	expand_long_vowels: Option<bool>,
+
+	/// Returns the romaji search key used by the dictionary searches, which is
+	/// lowercase and has expanded long vowels.
+	search_key: Option<bool>,
+}
+
+/// Kind of a character, see `inspect`.
+#[derive(juniper::GraphQLEnum, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CharKind {
+	/// Any character that is neither Japanese nor romaji.
+	None,
+
+	/// Full-width hiragana characters, not including `ー`.
+	Hiragana,
+
+	/// Full-width katakana characters, not including `ー`.
+	Katakana,
+
+	/// Half-width katakana characters.
+	KatakanaHalfWidth,
+
+	Kanji,
+
+	/// The prolonged sound mark (`ー` and the half-width `ｰ`).
+	BarLine,
+
+	/// Japanese punctuation (e.g. `、`, `。`, `「`), including the ideographic
+	/// space.
+	JapanesePunctuation,
+
+	/// Japanese repetition and iteration marks (e.g. `々`, `ゝ`).
+	JapaneseMark,
+
+	/// Other Japanese symbols.
+	JapaneseSymbol,
+
+	/// Full-width digits (`０` to `９`).
+	RomanDigit,
+
+	/// Full-width letters (`Ａ` to `ｚ`).
+	RomanLetter,
+
+	/// Full-width and half-width roman punctuation (e.g. `：`, `＜`).
+	RomanPunctuation,
+
+	/// ASCII punctuation, including spaces.
+	PunctuationAscii,
+
+	/// ASCII letters and digits.
+	Romaji,
+}
+
+impl From<kana::CharKind> for CharKind {
+	fn from(kind: kana::CharKind) -> CharKind {
+		match kind {
+			kana::CharKind::None => CharKind::None,
+			kana::CharKind::Hiragana => CharKind::Hiragana,
+			kana::CharKind::Katakana => CharKind::Katakana,
+			kana::CharKind::KatakanaHalfWidth => CharKind::KatakanaHalfWidth,
+			kana::CharKind::Kanji => CharKind::Kanji,
+			kana::CharKind::BarLine => CharKind::BarLine,
+			kana::CharKind::JapanesePunctuation => CharKind::JapanesePunctuation,
+			kana::CharKind::JapaneseMark => CharKind::JapaneseMark,
+			kana::CharKind::JapaneseSymbol => CharKind::JapaneseSymbol,
+			kana::CharKind::RomanDigit => CharKind::RomanDigit,
+			kana::CharKind::RomanLetter => CharKind::RomanLetter,
+			kana::CharKind::RomanPunctuation => CharKind::RomanPunctuation,
+			kana::CharKind::PunctuationASCII => CharKind::PunctuationAscii,
+			kana::CharKind::Romaji => CharKind::Romaji,
+		}
+	}
+}
+
+/// Information for a single character, see `inspect`.
+#[derive(juniper::GraphQLObject, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CharInfo {
+	character: String,
+
+	kind: CharKind,
+
+	/// Unicode code point.
+	code_point: i32,
+
+	/// True for kana, kanji, Japanese punctuation, marks and symbols.
+	is_japanese: bool,
+
+	/// Same as `isJapanese`, but also true for full-width roman letters,
+	/// digits and punctuation.
+	is_japanese_or_roman: bool,
+}
+
+/// Converts `text` to hiragana, katakana or romaji. Characters that cannot
+/// be converted are kept unchanged.
+pub fn convert(text: &str, to: ConvertTarget, options: &ConvertOptions) -> String {
+	match to {
+		ConvertTarget::Hiragana => kana::to_hiragana(text),
+		ConvertTarget::Katakana => kana::to_katakana(text),
+		ConvertTarget::Romaji => {
+			if options.search_key.unwrap_or(false) {
+				kana::to_search_key(text)
+			} else if options.expand_long_vowels.unwrap_or(false) {
+				kana::expand_romaji(kana::to_romaji(text))
+			} else {
+				kana::to_romaji(text)
+			}
+		}
+	}
+}
+
+/// Returns the information for each character in `text`.
+pub fn inspect(text: &str) -> Vec<CharInfo> {
+	text.chars()
+		.map(|chr| CharInfo {
+			character: chr.to_string(),
+			kind: CharKind::from(kana::get_kind(chr)),
+			code_point: chr as i32,
+			is_japanese: kana::is_japanese(chr, false),
+			is_japanese_or_roman: kana::is_japanese(chr, true),
+		})
+		.collect()
+}
+
+/// Request body for `convert_text`.
+#[derive(Deserialize)]
+pub struct ConvertRequest {
+	text: String,
+	to: ConvertTarget,
+	#[serde(default)]
+	options: ConvertOptions,
+}
+
+/// Request body for `inspect_text`.
+#[derive(Deserialize)]
+pub struct InspectRequest {
+	text: String,
+}
+
+/// Response for `convert_text`.
+#[derive(Serialize)]
+pub struct ConvertResponse {
+	text: String,
+}
+
+/// Converts a text to hiragana, katakana or romaji, see `convert`.
+#[post("/convert", format = "json", data = "<request>")]
+pub fn convert_text(request: Json<ConvertRequest>) -> Json<ConvertResponse> {
+	Json(ConvertResponse {
+		text: convert(&request.text, request.to, &request.options),
+	})
+}
+
+/// Returns the information for each character in a text, see `inspect`.
+#[post("/inspect", format = "json", data = "<request>")]
+pub fn inspect_text(request: Json<InspectRequest>) -> Json<Vec<CharInfo>> {
+	Json(inspect(&request.text))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn romaji(text: &str, expand_long_vowels: bool, search_key: bool) -> String {
+		let options = ConvertOptions {
+			expand_long_vowels: Some(expand_long_vowels),
+			search_key: Some(search_key),
+		};
+		convert(text, ConvertTarget::Romaji, &options)
+	}
+
+	#[test]
+	fn test_convert() {
+		let none = ConvertOptions::default();
+		let hiragana = |text| convert(text, ConvertTarget::Hiragana, &none);
+		let katakana = |text| convert(text, ConvertTarget::Katakana, &none);
+		assert_eq!("ひらがな", hiragana("hiragana"));
+		assert_eq!("ひらがな", hiragana("ヒラガナ"));
+		assert_eq!("カタカナ", katakana("katakana"));
+		assert_eq!("カタカナ", katakana("かたかな"));
+		assert_eq!("カタ漢字", katakana("かた漢字"));
+
+		// Romaji punctuation is converted with the romaji.
+		assert_eq!("カタ！", katakana("kata!"));
+	}
+
+	#[test]
+	fn test_convert_romaji() {
+		let none = ConvertOptions::default();
+		assert_eq!(
+			"hiragana",
+			convert("ひらがな", ConvertTarget::Romaji, &none)
+		);
+		assert_eq!("ko-hi-", romaji("コーヒー", false, false));
+		assert_eq!("koohii", romaji("コーヒー", true, false));
+		assert_eq!("toukyou", romaji("とうきょう", false, false));
+		assert_eq!("kana漢字", romaji("かな漢字", false, false));
+
+		// The search key takes precedence.
+		let key = kana::to_search_key("コーヒー");
+		assert_eq!(key, romaji("コーヒー", false, true));
+		assert_eq!(key, romaji("コーヒー", true, true));
+	}
+
+	fn kinds(text: &str) -> Vec<CharKind> {
+		inspect(text).into_iter().map(|x| x.kind).collect()
+	}
+
+	#[test]
+	fn test_inspect() {
+		let info = inspect("あ漢a");
+		assert_eq!(3, info.len());
+		assert_eq!("あ", info[0].character);
+		assert_eq!(0x3042, info[0].code_point);
+		assert_eq!(0x6F22, info[1].code_point);
+		assert_eq!(0x61, info[2].code_point);
+		assert!(info[0].is_japanese && info[0].is_japanese_or_roman);
+		assert!(info[1].is_japanese && info[1].is_japanese_or_roman);
+		assert!(!info[2].is_japanese && !info[2].is_japanese_or_roman);
+
+		assert_eq!(
+			vec![CharKind::Hiragana, CharKind::Katakana, CharKind::BarLine],
+			kinds("あアー")
+		);
+		assert_eq!(
+			vec![CharKind::KatakanaHalfWidth, CharKind::BarLine],
+			kinds("ｱｰ")
+		);
+		assert_eq!(vec![CharKind::Kanji, CharKind::JapaneseMark], kinds("人々"));
+		assert_eq!(
+			vec![CharKind::JapanesePunctuation, CharKind::JapanesePunctuation],
+			kinds("。　")
+		);
+		assert_eq!(
+			vec![
+				CharKind::Romaji,
+				CharKind::Romaji,
+				CharKind::PunctuationAscii,
+				CharKind::PunctuationAscii
+			],
+			kinds("a1 !")
+		);
+		assert_eq!(
+			vec![CharKind::RomanLetter, CharKind::RomanDigit],
+			kinds("Ａ１")
+		);
+		assert_eq!(vec![CharKind::None], kinds("é"));
+
+		// Full-width roman characters are only Japanese with the roman flag.
+		let info = inspect("Ａ");
+		assert!(!info[0].is_japanese && info[0].is_japanese_or_roman);
+		assert!(inspect("").is_empty());
+	}
+}
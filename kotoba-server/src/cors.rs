@@ -0,0 +1,63 @@
+//! Fairing for cross-origin requests from the configured `cors_origins`.
+
+use std::io::Cursor;
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Header, Method, Status};
+use rocket::{Request, Response};
+
+/// Methods allowed for cross-origin requests.
+const ALLOW_METHODS: &'static str = "GET, POST, OPTIONS";
+
+/// Time in seconds that browsers can cache a preflight response.
+const MAX_AGE: &'static str = "86400";
+
+/// Adds the CORS headers to the responses for requests from an allowed
+/// origin, and responds to the `OPTIONS` preflight requests.
+pub struct Cors {
+	origins: Vec<String>,
+}
+
+impl Cors {
+	/// Creates the fairing for a list of allowed origins, where `*` allows any
+	/// origin.
+	pub fn new(origins: Vec<String>) -> Cors {
+		Cors { origins: origins }
+	}
+
+	fn is_allowed(&self, origin: &str) -> bool {
+		self.origins.iter().any(|x| x == "*" || x == origin)
+	}
+}
+
+impl Fairing for Cors {
+	fn info(&self) -> Info {
+		Info {
+			name: "CORS",
+			kind: Kind::Response,
+		}
+	}
+
+	fn on_response(&self, request: &Request, response: &mut Response) {
+		let origin = match request.headers().get_one("Origin") {
+			Some(origin) if self.is_allowed(origin) => origin.to_string(),
+			_ => return,
+		};
+		response.set_header(Header::new("Access-Control-Allow-Origin", origin));
+		response.set_header(Header::new("Vary", "Origin"));
+
+		// There are no `OPTIONS` routes, so preflight requests are not found.
+		if request.method() == Method::Options && response.status() == Status::NotFound {
+			let headers = request
+				.headers()
+				.get_one("Access-Control-Request-Headers")
+				.unwrap_or("Content-Type")
+				.to_string();
+			response.set_status(Status::NoContent);
+			response.set_sized_body(Cursor::new(""));
+			response.set_header(Header::new("Access-Control-Allow-Methods", ALLOW_METHODS));
+			response.set_header(Header::new("Access-Control-Allow-Headers", headers));
+			response.set_header(Header::new("Access-Control-Max-Age", MAX_AGE));
+		}
+	}
+}
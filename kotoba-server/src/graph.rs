@@ -1,5 +1,24 @@
+use juniper::{FieldError, FieldResult};
+
 use crate::app::App;
 use crate::common;
+use crate::connection::{paginate, PageArgs, PageInfo};
+use crate::convert::{self, CharInfo, ConvertOptions, ConvertTarget};
+
+/// Default number of entries returned by list queries.
+pub const DEFAULT_LIMIT: i32 = 20;
+
+/// Maximum number of entries returned by list queries.
+pub const MAX_LIMIT: i32 = 100;
+
+/// Number of "did you mean" suggestions returned by `lookup`.
+pub const SUGGESTION_LIMIT: usize = 5;
+
+/// Number of kanji returned by `Kanji.similarKanji`.
+pub const SIMILAR_KANJI_LIMIT: usize = 10;
+
+/// Maximum number of kanji returned by `Kanji.components`.
+pub const COMPONENTS_LIMIT: usize = 20;
 
 pub struct Context {
 	pub app: &'static App,
@@ -16,6 +35,188 @@ impl Query {
 	fn version() -> &'static str {
 		common::PKG_VERSION
 	}
+
+	/// Looks up dictionary entries by their exact expression or reading, or
+	/// by the start of their romaji reading.
+	///
+	/// If there are no entries, `didYouMean` lists the similar romaji search
+	/// keys (e.g. `toukyou` for `tokyo`).
+	fn lookup(
+		context: &Context,
+		text: String,
+		first: Option<i32>,
+		after: Option<String>,
+		last: Option<i32>,
+		before: Option<String>,
+	) -> FieldResult<Lookup> {
+		let dict = context.app.db().dictionary();
+		let mut indexes = dict.search_indexes(dict_data::SearchMode::Exact, &text)?;
+		if indexes.is_empty() {
+			indexes = dict.search_indexes(dict_data::SearchMode::Romaji, &text)?;
+		}
+		context.app.metrics.observe_search("lookup", indexes.len());
+
+		let did_you_mean = if indexes.is_empty() {
+			dict.search_romaji_fuzzy(&text, SUGGESTION_LIMIT)?
+		} else {
+			Vec::new()
+		};
+
+		let args = PageArgs {
+			first,
+			after,
+			last,
+			before,
+		};
+		Ok(Lookup {
+			entries: EntryConnection::new(context, indexes, args)?,
+			did_you_mean: did_you_mean.into_iter().map(Suggestion::from).collect(),
+		})
+	}
+
+	/// Splits a Japanese text into sentences and words, with the dictionary
+	/// form, reading and candidate entries for each word.
+	fn analyze(context: &Context, text: String) -> FieldResult<Analysis> {
+		let analysis = context.app.db().dictionary().analyze(&text)?;
+		Ok(Analysis::from(analysis))
+	}
+
+	/// Converts `text` to hiragana, katakana or romaji. Characters that cannot
+	/// be converted are kept unchanged.
+	fn convert(text: String, to: ConvertTarget, options: Option<ConvertOptions>) -> String {
+		convert::convert(&text, to, &options.unwrap_or_default())
+	}
+
+	/// Returns the kind, code point and Japanese flags for each character in
+	/// `text`.
+	fn inspect(text: String) -> Vec<CharInfo> {
+		convert::inspect(&text)
+	}
+
+	/// Searches dictionary entries for `query`, using the given search `mode`
+	/// (by default `AUTO`).
+	fn search(
+		context: &Context,
+		query: String,
+		mode: Option<SearchMode>,
+		first: Option<i32>,
+		after: Option<String>,
+		last: Option<i32>,
+		before: Option<String>,
+	) -> FieldResult<EntryConnection> {
+		let dict = context.app.db().dictionary();
+		let mode = mode.unwrap_or(SearchMode::Auto);
+		let indexes = dict
+			.search_indexes(mode.into(), &query)
+			.map_err(query_error)?;
+		context
+			.app
+			.metrics
+			.observe_search(mode.name(), indexes.len());
+		let args = PageArgs {
+			first,
+			after,
+			last,
+			before,
+		};
+		EntryConnection::new(context, indexes, args)
+	}
+
+	/// Searches dictionary entries with the query language, which combines
+	/// term, English, tag, source and frequency clauses, e.g.
+	/// `食 tag:v5 freq>5000 source:JMdict -tag:arch "to eat"`.
+	///
+	/// The query must have at least one term, English or romaji clause that
+	/// is not negated, since the entries are searched by those. Other queries
+	/// (e.g. `tag:v5` or `-食`) return an error.
+	///
+	/// Invalid queries return an error with the character `position` of the
+	/// problem in the error extensions.
+	fn query(
+		context: &Context,
+		query: String,
+		first: Option<i32>,
+		after: Option<String>,
+		last: Option<i32>,
+		before: Option<String>,
+	) -> FieldResult<EntryConnection> {
+		let dict = context.app.db().dictionary();
+		let indexes = dict
+			.search_indexes(dict_data::SearchMode::Query, &query)
+			.map_err(query_error)?;
+		context.app.metrics.observe_search("query", indexes.len());
+		let args = PageArgs {
+			first,
+			after,
+			last,
+			before,
+		};
+		EntryConnection::new(context, indexes, args)
+	}
+
+	/// Looks up a kanji by its character.
+	fn kanji(context: &Context, character: String) -> Option<Kanji> {
+		let mut chars = character.chars();
+		match (chars.next(), chars.next()) {
+			(Some(chr), None) => Kanji::get(context, chr),
+			_ => None,
+		}
+	}
+
+	/// Searches kanji by reading (in romaji or kana), English meaning and
+	/// stroke count, sorted by frequency. All the given filters must match.
+	fn kanji_search(
+		context: &Context,
+		reading: Option<String>,
+		meaning: Option<String>,
+		strokes: Option<i32>,
+		first: Option<i32>,
+		after: Option<String>,
+		last: Option<i32>,
+		before: Option<String>,
+	) -> FieldResult<KanjiConnection> {
+		let list = context.app.db().kanji().search(
+			reading.as_deref(),
+			meaning.as_deref(),
+			strokes.map(|x| x.max(0) as u32),
+		);
+		context.app.metrics.observe_search("kanji", list.len());
+		let keys: Vec<usize> = list.iter().map(|x| x.character as usize).collect();
+		let args = PageArgs {
+			first,
+			after,
+			last,
+			before,
+		};
+		let page = paginate("kanji", &keys, args)?;
+		let edges = list[page.range]
+			.iter()
+			.zip(page.cursors)
+			.map(|(kanji, cursor)| KanjiEdge {
+				node: Kanji {
+					character: kanji.character,
+				},
+				cursor,
+			})
+			.collect();
+		Ok(KanjiConnection {
+			edges,
+			page_info: page.info,
+			total_count: keys.len() as i32,
+		})
+	}
+}
+
+/// Converts a query error, with the position of syntax errors as an extension.
+fn query_error(err: dict_data::Error) -> FieldError {
+	match err {
+		dict_data::Error::QuerySyntax { position, .. } => {
+			let mut extensions = juniper::Object::with_capacity(1);
+			extensions.add_field("position", juniper::Value::scalar(position as i32));
+			FieldError::new(err, juniper::Value::Object(extensions))
+		}
+		err => err.into(),
+	}
 }
 
 /// Root Mutation for the GraphQL schema.
@@ -31,3 +232,530 @@ impl Mutation {
 
 /// Root schema for GraphQL.
 pub type Schema = juniper::RootNode<'static, Query, Mutation>;
+
+/// Kind of search for the `search` query.
+#[derive(juniper::GraphQLEnum, Clone, Copy)]
+pub enum SearchMode {
+	/// Uses `PATTERN` if the query has wildcards. Otherwise, Japanese queries
+	/// use `EXACT` and then `PREFIX`, and other queries use `ROMAJI` and then
+	/// `ENGLISH`.
+	Auto,
+
+	/// Entries with an expression or reading equal to the query.
+	Exact,
+
+	/// Entries with an expression or reading starting with the query.
+	Prefix,
+
+	/// Entries with a romaji reading starting with the query, which can be in
+	/// romaji or kana.
+	Romaji,
+
+	/// Entries with all the English words in the query in their definitions.
+	English,
+
+	/// Entries with an expression or reading containing all the Japanese
+	/// characters in the query, in any order.
+	Chars,
+
+	/// Entries matching a wildcard pattern, e.g. `東*`, `?きょう` or
+	/// `[漢字]語`. The pattern must have at least one literal character.
+	Pattern,
+
+	/// Entries matching a query in the query language, see the `query` field.
+	Query,
+}
+
+impl SearchMode {
+	/// Name of the mode for the search metrics.
+	fn name(self) -> &'static str {
+		match self {
+			SearchMode::Auto => "auto",
+			SearchMode::Exact => "exact",
+			SearchMode::Prefix => "prefix",
+			SearchMode::Romaji => "romaji",
+			SearchMode::English => "english",
+			SearchMode::Chars => "chars",
+			SearchMode::Pattern => "pattern",
+			SearchMode::Query => "query",
+		}
+	}
+}
+
+impl From<SearchMode> for dict_data::SearchMode {
+	fn from(mode: SearchMode) -> dict_data::SearchMode {
+		match mode {
+			SearchMode::Auto => dict_data::SearchMode::Auto,
+			SearchMode::Exact => dict_data::SearchMode::Exact,
+			SearchMode::Prefix => dict_data::SearchMode::Prefix,
+			SearchMode::Romaji => dict_data::SearchMode::Romaji,
+			SearchMode::English => dict_data::SearchMode::English,
+			SearchMode::Chars => dict_data::SearchMode::Chars,
+			SearchMode::Pattern => dict_data::SearchMode::Pattern,
+			SearchMode::Query => dict_data::SearchMode::Query,
+		}
+	}
+}
+
+/// Result for the `lookup` query.
+#[derive(juniper::GraphQLObject)]
+#[graphql(Context = Context, Scalar = juniper::DefaultScalarValue)]
+pub struct Lookup {
+	/// Entries found, in order of frequency.
+	entries: EntryConnection,
+
+	/// Suggestions for the input when no entries are found, with the closest
+	/// first. There are at most five suggestions, with their entries paginated.
+	did_you_mean: Vec<Suggestion>,
+}
+
+/// Paginated list of dictionary entries.
+#[derive(juniper::GraphQLObject)]
+pub struct EntryConnection {
+	edges: Vec<EntryEdge>,
+
+	page_info: PageInfo,
+
+	/// Total number of entries in the list.
+	total_count: i32,
+}
+
+impl EntryConnection {
+	/// Returns the page of the list of entry `indexes` for the arguments.
+	fn new(context: &Context, indexes: Vec<usize>, args: PageArgs) -> FieldResult<EntryConnection> {
+		let dict = context.app.db().dictionary();
+		let page = paginate("entry", &indexes, args)?;
+		let mut edges = Vec::with_capacity(page.cursors.len());
+		for (&index, cursor) in indexes[page.range].iter().zip(page.cursors) {
+			if let Some(entry) = dict.get_entry(index)? {
+				edges.push(EntryEdge {
+					node: Entry::from(entry),
+					cursor,
+				});
+			}
+		}
+		Ok(EntryConnection {
+			edges,
+			page_info: page.info,
+			total_count: indexes.len() as i32,
+		})
+	}
+}
+
+/// Entry in an `EntryConnection`.
+#[derive(juniper::GraphQLObject)]
+pub struct EntryEdge {
+	node: Entry,
+
+	/// Cursor for the entry, for the `after` and `before` arguments.
+	cursor: String,
+}
+
+/// Dictionary entry.
+#[derive(juniper::GraphQLObject)]
+pub struct Entry {
+	/// Index of the entry in the dictionary.
+	index: i32,
+
+	expression: String,
+
+	/// Kana reading for the entry, empty if not available.
+	reading: String,
+
+	/// Romaji for the reading (or for the expression, if there is no reading).
+	romaji: String,
+
+	/// Frequency of the entry in the reference corpus. This is zero for user
+	/// dictionary entries.
+	frequency: i32,
+
+	definitions: Vec<Definition>,
+}
+
+impl From<dict_data::Entry> for Entry {
+	fn from(entry: dict_data::Entry) -> Entry {
+		Entry {
+			romaji: entry.romaji(),
+			index: entry.index as i32,
+			expression: entry.expression,
+			reading: entry.reading,
+			frequency: entry.frequency as i32,
+			definitions: entry
+				.definitions
+				.into_iter()
+				.map(Definition::from)
+				.collect(),
+		}
+	}
+}
+
+/// Single definition for a dictionary entry.
+#[derive(juniper::GraphQLObject)]
+pub struct Definition {
+	/// Name of the dictionary source for the definition.
+	source: String,
+
+	/// English glossary.
+	glossary: Vec<String>,
+
+	/// Names of the term and definition tags.
+	tags: Vec<String>,
+
+	/// Names of the deinflection rules for the term (e.g. `v5`).
+	rules: Vec<String>,
+}
+
+impl From<dict_data::Definition> for Definition {
+	fn from(definition: dict_data::Definition) -> Definition {
+		Definition {
+			source: definition.source,
+			glossary: definition.glossary,
+			tags: definition.tags,
+			rules: definition.rules,
+		}
+	}
+}
+
+/// Kanji from the kanji dictionary.
+pub struct Kanji {
+	character: char,
+}
+
+impl Kanji {
+	fn get(context: &Context, character: char) -> Option<Kanji> {
+		context
+			.app
+			.db()
+			.kanji()
+			.get(character)
+			.map(|_| Kanji { character })
+	}
+
+	fn data<'a>(&self, context: &'a Context) -> &'a dict_data::Kanji {
+		context.app.db().kanji().get(self.character).unwrap()
+	}
+}
+
+#[juniper::object(Context = Context)]
+impl Kanji {
+	fn character(&self) -> String {
+		self.character.to_string()
+	}
+
+	/// Onyomi (chinese) readings, in katakana.
+	fn onyomi(&self, context: &Context) -> &Vec<String> {
+		&self.data(context).onyomi
+	}
+
+	/// Kunyomi (japanese) readings, in hiragana. The okurigana is separated
+	/// by a `.` (e.g. `た.べる`).
+	fn kunyomi(&self, context: &Context) -> &Vec<String> {
+		&self.data(context).kunyomi
+	}
+
+	/// Readings used only in names.
+	fn nanori(&self, context: &Context) -> &Vec<String> {
+		&self.data(context).info.nanori
+	}
+
+	/// English meanings.
+	fn meanings(&self, context: &Context) -> &Vec<String> {
+		&self.data(context).meanings
+	}
+
+	fn tags(&self, context: &Context) -> &Vec<String> {
+		&self.data(context).tags
+	}
+
+	/// Additional kanji information from the source, sorted by name.
+	fn stats(&self, context: &Context) -> Vec<KanjiStat> {
+		let mut stats: Vec<KanjiStat> = self
+			.data(context)
+			.stats
+			.iter()
+			.map(|(name, value)| KanjiStat {
+				name: name.clone(),
+				value: value.clone(),
+			})
+			.collect();
+		stats.sort_by(|a, b| a.name.cmp(&b.name));
+		stats
+	}
+
+	/// Name of the source dictionary.
+	fn source(&self, context: &Context) -> &str {
+		&self.data(context).source
+	}
+
+	/// Number of occurrences in the reference corpus, zero if not available.
+	fn frequency(&self, context: &Context) -> i32 {
+		self.data(context).frequency as i32
+	}
+
+	/// Frequency rank among the 2500 most used kanji in newspapers.
+	fn frequency_rank(&self, context: &Context) -> Option<i32> {
+		self.data(context).info.frequency_rank.map(|x| x as i32)
+	}
+
+	fn strokes(&self, context: &Context) -> Option<i32> {
+		self.data(context).info.strokes.map(|x| x as i32)
+	}
+
+	/// School grade in which the kanji is taught (1-6), or `8` for the
+	/// remaining jouyou kanji, `9` and `10` for jinmeiyou kanji.
+	fn grade(&self, context: &Context) -> Option<i32> {
+		self.data(context).info.grade.map(|x| x as i32)
+	}
+
+	/// JLPT level (pre-2010, from `4` to `1`).
+	fn jlpt(&self, context: &Context) -> Option<i32> {
+		self.data(context).info.jlpt.map(|x| x as i32)
+	}
+
+	/// Classical (KangXi Zidian) radical number.
+	fn radical(&self, context: &Context) -> Option<i32> {
+		self.data(context).info.radical.map(|x| x as i32)
+	}
+
+	/// Dictionary entries with the kanji in their expression or reading.
+	fn words(
+		&self,
+		context: &Context,
+		first: Option<i32>,
+		after: Option<String>,
+		last: Option<i32>,
+		before: Option<String>,
+	) -> FieldResult<EntryConnection> {
+		let dict = context.app.db().dictionary();
+		let text = self.character.to_string();
+		let indexes = dict.search_indexes(dict_data::SearchMode::Chars, &text)?;
+		context.app.metrics.observe_search("chars", indexes.len());
+		let args = PageArgs {
+			first,
+			after,
+			last,
+			before,
+		};
+		EntryConnection::new(context, indexes, args)
+	}
+
+	/// Components of the kanji, from the stroke order data. Components that
+	/// are not in the kanji dictionary are not included.
+	fn components(&self, context: &Context) -> FieldResult<Vec<Kanji>> {
+		let components = context.app.db().strokes().components(self.character)?;
+		Ok(components
+			.into_iter()
+			.filter_map(|x| Kanji::get(context, x))
+			.take(COMPONENTS_LIMIT)
+			.collect())
+	}
+
+	/// Kanji sharing the most components with this kanji.
+	fn similar_kanji(&self, context: &Context) -> FieldResult<Vec<Kanji>> {
+		let similar = context
+			.app
+			.db()
+			.similar_kanji(self.character, SIMILAR_KANJI_LIMIT)?;
+		Ok(similar
+			.into_iter()
+			.filter_map(|x| Kanji::get(context, x))
+			.collect())
+	}
+}
+
+/// Additional information for a kanji.
+#[derive(juniper::GraphQLObject)]
+pub struct KanjiStat {
+	name: String,
+	value: String,
+}
+
+/// Paginated list of kanji.
+#[derive(juniper::GraphQLObject)]
+#[graphql(Context = Context, Scalar = juniper::DefaultScalarValue)]
+pub struct KanjiConnection {
+	edges: Vec<KanjiEdge>,
+
+	page_info: PageInfo,
+
+	/// Total number of kanji in the list.
+	total_count: i32,
+}
+
+/// Kanji in a `KanjiConnection`.
+#[derive(juniper::GraphQLObject)]
+#[graphql(Context = Context, Scalar = juniper::DefaultScalarValue)]
+pub struct KanjiEdge {
+	node: Kanji,
+
+	/// Cursor for the kanji, for the `after` and `before` arguments.
+	cursor: String,
+}
+
+/// Result for the `analyze` query.
+///
+/// All positions are character offsets in the analyzed text, with `end` being
+/// exclusive.
+#[derive(juniper::GraphQLObject)]
+pub struct Analysis {
+	sentences: Vec<Sentence>,
+
+	/// Runs of Japanese text without any dictionary match.
+	unknown: Vec<Span>,
+}
+
+impl From<dict_data::Analysis> for Analysis {
+	fn from(analysis: dict_data::Analysis) -> Analysis {
+		Analysis {
+			sentences: analysis.sentences.into_iter().map(Sentence::from).collect(),
+			unknown: analysis.unknown.into_iter().map(Span::from).collect(),
+		}
+	}
+}
+
+/// Sentence in the analyzed text, including its final punctuation.
+#[derive(juniper::GraphQLObject)]
+pub struct Sentence {
+	start: i32,
+	end: i32,
+	text: String,
+
+	/// Words found in the sentence, in order.
+	tokens: Vec<Token>,
+}
+
+impl From<dict_data::Sentence> for Sentence {
+	fn from(sentence: dict_data::Sentence) -> Sentence {
+		Sentence {
+			start: sentence.start as i32,
+			end: sentence.end as i32,
+			text: sentence.text,
+			tokens: sentence.tokens.into_iter().map(Token::from).collect(),
+		}
+	}
+}
+
+/// Word in the analyzed text, matched to the dictionary.
+#[derive(juniper::GraphQLObject)]
+pub struct Token {
+	start: i32,
+	end: i32,
+	text: String,
+
+	/// Dictionary form for the token (e.g. `食べる` for `食べた`).
+	base: String,
+
+	/// Hiragana reading for the token text.
+	reading: String,
+
+	/// Token text split into kanji and kana runs, with the reading for the
+	/// kanji runs.
+	furigana: Vec<Furigana>,
+
+	/// Inflections from the base form to the token text (e.g. `past`).
+	inflections: Vec<String>,
+
+	/// Candidate entries for the base form.
+	entries: Vec<Entry>,
+}
+
+impl From<dict_data::Token> for Token {
+	fn from(token: dict_data::Token) -> Token {
+		Token {
+			start: token.start as i32,
+			end: token.end as i32,
+			text: token.text,
+			base: token.base,
+			reading: token.reading,
+			furigana: token
+				.furigana
+				.into_iter()
+				.map(|x| Furigana {
+					text: x.text,
+					reading: x.reading,
+				})
+				.collect(),
+			inflections: token.inflections,
+			entries: token.entries.into_iter().map(Entry::from).collect(),
+		}
+	}
+}
+
+/// Part of a token text, with the reading for kanji.
+#[derive(juniper::GraphQLObject)]
+pub struct Furigana {
+	text: String,
+
+	/// Hiragana reading, empty for kana.
+	reading: String,
+}
+
+/// Span of the analyzed text.
+#[derive(juniper::GraphQLObject)]
+pub struct Span {
+	start: i32,
+	end: i32,
+	text: String,
+}
+
+impl From<dict_data::Span> for Span {
+	fn from(span: dict_data::Span) -> Span {
+		Span {
+			start: span.start as i32,
+			end: span.end as i32,
+			text: span.text,
+		}
+	}
+}
+
+/// "Did you mean" suggestion for a romaji input.
+pub struct Suggestion {
+	key: String,
+	distance: f64,
+
+	/// Indexes of the entries for the key, which are loaded by page.
+	indexes: Vec<usize>,
+}
+
+#[juniper::object(Context = Context)]
+impl Suggestion {
+	/// Suggested romaji search key.
+	fn key(&self) -> &str {
+		&self.key
+	}
+
+	/// Distance from the input, where lower is closer. Common romaji variations
+	/// (e.g. long vowels, `n` and `nn`, `si` and `shi`) have a lower distance
+	/// than other edits, which have a distance of one.
+	fn distance(&self) -> f64 {
+		self.distance
+	}
+
+	/// Entries for the suggested key.
+	fn entries(
+		&self,
+		context: &Context,
+		first: Option<i32>,
+		after: Option<String>,
+		last: Option<i32>,
+		before: Option<String>,
+	) -> FieldResult<EntryConnection> {
+		let args = PageArgs {
+			first,
+			after,
+			last,
+			before,
+		};
+		EntryConnection::new(context, self.indexes.clone(), args)
+	}
+}
+
+impl From<dict_data::FuzzyMatch> for Suggestion {
+	fn from(item: dict_data::FuzzyMatch) -> Suggestion {
+		Suggestion {
+			key: item.key,
+			distance: item.distance as f64,
+			indexes: item.indexes,
+		}
+	}
+}
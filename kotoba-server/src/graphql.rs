@@ -1,26 +1,136 @@
-use rocket::response::content::Html;
-use rocket::State;
+use std::io::Read;
+use std::time::Instant;
+
+use juniper::http::{GraphQLRequest, GraphQLResponse};
+use juniper::InputValue;
+use rocket::http::{ContentType, Status};
+use rocket::response::content::{Content, Html};
+use rocket::response::status::Custom;
+use rocket::{Data, State};
 
 use crate::app::App;
 use crate::graph;
+use crate::health::Ready;
+use crate::limits;
 
 #[get("/graphiql")]
 pub fn ide() -> Html<String> {
 	Html(graphiql_source("Kotoba - GraphiQL", "/api/graphql"))
 }
 
-#[post("/graphql", data = "<request>")]
+/// Body for `query`, with a single request or a batch of requests.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GraphQLBatch {
+	Single(QueryRequest),
+	Batch(Vec<QueryRequest>),
+}
+
+/// Same as `GraphQLRequest`, which does not expose the query and variables
+/// for `limits::check`.
+#[derive(Deserialize)]
+struct QueryRequest {
+	query: String,
+	#[serde(rename = "operationName")]
+	operation_name: Option<String>,
+	variables: Option<InputValue>,
+}
+
+/// Runs a GraphQL request, or a batch of requests, from a JSON body.
+///
+/// Fails with `413` if the body is larger than the configured
+/// `limits.graphql` and with `400` if it is not a valid request. Otherwise,
+/// responds with `400` if any of the requests has errors or exceeds the
+/// query limits, see `limits::check`.
+#[post("/graphql", data = "<data>")]
 pub fn query(
 	app: State<&App>,
-	request: juniper_rocket::GraphQLRequest,
+	_ready: Ready,
+	data: Data,
 	schema: State<graph::Schema>,
-) -> juniper_rocket::GraphQLResponse {
+) -> Result<Custom<Content<String>>, Status> {
+	let limit = app.config.graphql_limit;
+	let mut body = String::new();
+	if data
+		.open()
+		.take(limit + 1)
+		.read_to_string(&mut body)
+		.is_err()
+	{
+		return Err(Status::BadRequest);
+	}
+	if body.len() as u64 > limit {
+		return Err(Status::PayloadTooLarge);
+	}
+	let batch: GraphQLBatch = serde_json::from_str(&body).map_err(|_| Status::BadRequest)?;
+
+	let (requests, is_batch) = match batch {
+		GraphQLBatch::Single(request) => (vec![request], false),
+		GraphQLBatch::Batch(requests) => (requests, true),
+	};
+	// The complexity of a batch is the total for its requests, so that it
+	// cannot be used to run more than a single request.
+	let mut total = 0;
+	let checks: Vec<_> = requests
+		.iter()
+		.map(|x| {
+			let name = x.operation_name.as_ref().map(|x| x.as_str());
+			let complexity = limits::check(&app.config, &x.query, name, x.variables.as_ref())?;
+			total += complexity;
+			limits::check_batch(&app.config, total)
+		})
+		.collect();
+	let requests: Vec<_> = requests
+		.into_iter()
+		.map(|x| GraphQLRequest::new(x.query, x.operation_name, x.variables))
+		.collect();
+
 	let context = graph::Context { app: &app };
-	request.execute(&schema, &context)
+	let limited = checks.iter().any(|x| x.is_err());
+	let responses: Vec<_> = requests
+		.iter()
+		.zip(checks)
+		.map(|(request, check)| match check {
+			Ok(()) => execute(&app, &schema, &context, request),
+			Err(err) => GraphQLResponse::error(err),
+		})
+		.collect();
+	let ok = !limited && responses.iter().all(|x| x.is_ok());
+	let output = if is_batch {
+		serde_json::to_string(&responses)
+	} else {
+		serde_json::to_string(&responses[0])
+	};
+	let output = output.map_err(|_| Status::InternalServerError)?;
+	let status = if ok { Status::Ok } else { Status::BadRequest };
+	Ok(Custom(status, Content(ContentType::JSON, output)))
+}
+
+/// Executes a single request, recording its time in the metrics.
+fn execute<'a>(
+	app: &App,
+	schema: &'a graph::Schema,
+	context: &graph::Context,
+	request: &'a GraphQLRequest,
+) -> GraphQLResponse<'a> {
+	let start = Instant::now();
+	let response = request.execute(schema, context);
+	app.metrics
+		.observe_operation(request.operation_name(), start.elapsed());
+	response
 }
 
 // spell-checker: disable
 
+/// URLs for the React, ReactDOM, GraphiQL script and GraphiQL style assets,
+/// served by `server::graphiql_asset`.
+const ASSETS: [&'static str; 4] = [
+	"/static/graphiql/react.production.min.js",
+	"/static/graphiql/react-dom.production.min.js",
+	"/static/graphiql/graphiql.min.js",
+	"/static/graphiql/graphiql.min.css",
+];
+
 fn graphiql_source(title: &str, url: &str) -> String {
 	return format!(
 		r#"
@@ -29,12 +139,10 @@ fn graphiql_source(title: &str, url: &str) -> String {
 				<head>
 					<title>{title}</title>
 					{style}
-					<script src="https://cdn.jsdelivr.net/es6-promise/4.0.5/es6-promise.auto.min.js"></script>
-					<script src="https://cdn.jsdelivr.net/fetch/0.9.0/fetch.min.js"></script>
-					<script src="https://cdn.jsdelivr.net/react/15.4.2/react.min.js"></script>
-					<script src="https://cdn.jsdelivr.net/react/15.4.2/react-dom.min.js"></script>
-					<link rel="stylesheet" href="https://cdnjs.cloudflare.com/ajax/libs/graphiql/0.11.11/graphiql.min.css" />
-					<script src="https://cdnjs.cloudflare.com/ajax/libs/graphiql/0.11.11/graphiql.min.js"></script>
+					<script src="{react}"></script>
+					<script src="{react_dom}"></script>
+					<link rel="stylesheet" href="{graphiql_css}" />
+					<script src="{graphiql_js}"></script>
 				</head>
 				<body>
 				<div id="graphiql">Loading...</div>
@@ -45,6 +153,10 @@ fn graphiql_source(title: &str, url: &str) -> String {
 		"#,
 		title = title,
 		url = url,
+		react = ASSETS[0],
+		react_dom = ASSETS[1],
+		graphiql_js = ASSETS[2],
+		graphiql_css = ASSETS[3],
 		style = STYLE,
 		script = SCRIPT,
 	);
@@ -66,32 +178,23 @@ const STYLE: &'static str = r#"
 
 const SCRIPT: &'static str = r#"
 	<script>
-		/**
-		 * This GraphiQL example illustrates how to use some of GraphiQL's props
-		 * in order to enable reading and updating the URL parameters, making
-		 * link sharing of queries a little bit easier.
-		 *
-		 * This is only one example of this kind of feature, GraphiQL exposes
-		 * various React params to enable interesting integrations.
-		 */
-		// Parse the search string to get url parameters.
-		var search = window.location.search;
+		// Parse the search string to get url parameters, so that links to the
+		// IDE can include the query.
 		var parameters = {};
-		search.substr(1).split('&').forEach(function (entry) {
+		window.location.search.substr(1).split('&').forEach(function (entry) {
 			var eq = entry.indexOf('=');
 			if (eq >= 0) {
 				parameters[decodeURIComponent(entry.slice(0, eq))] =
-				decodeURIComponent(entry.slice(eq + 1));
+					decodeURIComponent(entry.slice(eq + 1));
 			}
 		});
 		// If variables was provided, try to format it.
 		if (parameters.variables) {
 			try {
 				parameters.variables =
-				JSON.stringify(JSON.parse(parameters.variables), null, 2);
+					JSON.stringify(JSON.parse(parameters.variables), null, 2);
 			} catch (e) {
-				// Do nothing, we want to display the invalid JSON as a string, rather
-				// than present an error.
+				// Keep the invalid JSON as a string, rather than present an error.
 			}
 		}
 		// When the query and variables string is edited, update the URL bar so
@@ -113,53 +216,22 @@ const SCRIPT: &'static str = r#"
 				return Boolean(parameters[key]);
 			}).map(function (key) {
 				return encodeURIComponent(key) + '=' +
-				encodeURIComponent(parameters[key]);
+					encodeURIComponent(parameters[key]);
 			}).join('&');
 			history.replaceState(null, null, newSearch);
 		}
-		// Defines a GraphQL fetcher using the fetch API. You're not required to
-		// use fetch, and could instead implement graphQLFetcher however you like,
-		// as long as it returns a Promise or Observable.
-		function graphQLFetcher(graphQLParams) {
-			// When working locally, the example expects a GraphQL server at the path /graphql.
-			// In a PR preview, it connects to the Star Wars API externally.
-			// Change this to point wherever you host your GraphQL server.
-			const isDev = !window.location.hostname.match(/(^|\.)netlify\.com$|(^|\.)graphql\.org$/)
-			const api = GRAPHQL_URL
-			return fetch(api, {
-				method: 'post',
-				headers: {
-				'Accept': 'application/json',
-				'Content-Type': 'application/json',
-				},
-				body: JSON.stringify(graphQLParams),
-				credentials: 'include',
-			}).then(function (response) {
-				return response.text();
-			}).then(function (responseBody) {
-				try {
-				return JSON.parse(responseBody);
-				} catch (error) {
-				return responseBody;
-				}
-			});
-		}
-		// Render <GraphiQL /> into the body.
-		// See the README in the top level of this module to learn more about
-		// how you can customize GraphiQL by providing different values or
-		// additional child elements.
-		ReactDOM.render(
+		var root = ReactDOM.createRoot(document.getElementById('graphiql'));
+		root.render(
 			React.createElement(GraphiQL, {
-				fetcher: graphQLFetcher,
+				fetcher: GraphiQL.createFetcher({ url: GRAPHQL_URL }),
 				query: parameters.query,
 				variables: parameters.variables,
 				operationName: parameters.operationName,
 				onEditQuery: onEditQuery,
 				onEditVariables: onEditVariables,
-				defaultVariableEditorOpen: true,
-				onEditOperationName: onEditOperationName
-			}),
-			document.getElementById('graphiql')
+				onEditOperationName: onEditOperationName,
+				defaultEditorToolsVisibility: true,
+			})
 		);
 	</script>
 "#;
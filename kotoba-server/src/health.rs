@@ -0,0 +1,115 @@
+//! Liveness and readiness endpoints for the container orchestrator.
+
+use rocket::http::Status;
+use rocket::request::{self, FromRequest, Request};
+use rocket::response::status::Custom;
+use rocket::{Outcome, State};
+use rocket_contrib::json::Json;
+
+use dict_data::{CacheStats, Manifest};
+
+use crate::app::{self, App};
+use crate::common;
+
+/// Request guard for the routes that need the database, which fails with
+/// `503` until the database is loaded and checked.
+pub struct Ready;
+
+impl<'a, 'r> FromRequest<'a, 'r> for Ready {
+	type Error = ();
+
+	fn from_request(request: &'a Request<'r>) -> request::Outcome<Ready, ()> {
+		let app = request.guard::<State<&App>>()?;
+		if app.is_ready() {
+			Outcome::Success(Ready)
+		} else {
+			Outcome::Failure((Status::ServiceUnavailable, ()))
+		}
+	}
+}
+
+#[derive(Serialize)]
+pub struct Liveness {
+	status: &'static str,
+
+	/// Time since the server started, in seconds.
+	uptime: u64,
+}
+
+#[derive(Serialize)]
+pub struct Readiness {
+	/// One of `loading`, `verifying`, `ready` or `failed`.
+	status: &'static str,
+
+	/// Error message if the status is `failed`.
+	error: Option<String>,
+
+	version: &'static str,
+
+	/// Time since the server started, in seconds.
+	uptime: u64,
+
+	/// Manifest of the loaded database.
+	database: Option<&'static Manifest>,
+
+	/// Number of entries in the loaded database.
+	counts: Option<Counts>,
+
+	/// Statistics for the dictionary page cache.
+	cache: Option<CacheStats>,
+}
+
+#[derive(Serialize)]
+pub struct Counts {
+	/// Dictionary entries, including user dictionaries.
+	entries: usize,
+	kanji: usize,
+	names: usize,
+	examples: usize,
+	strokes: usize,
+}
+
+/// Responds with `200` while the server is running, even if the database is
+/// still loading.
+#[get("/live")]
+pub fn live(app: State<&App>) -> Json<Liveness> {
+	Json(Liveness {
+		status: "alive",
+		uptime: app.started.elapsed().as_secs(),
+	})
+}
+
+/// Responds with `200` once the database is loaded and passed the integrity
+/// check, and with `503` while loading or if loading failed.
+#[get("/ready")]
+pub fn ready(app: State<&'static App>) -> Custom<Json<Readiness>> {
+	let app: &'static App = *app.inner();
+	let (status, error) = match app.status() {
+		app::Status::Loading => ("loading", None),
+		app::Status::Verifying => ("verifying", None),
+		app::Status::Ready => ("ready", None),
+		app::Status::Failed(error) => ("failed", Some(error)),
+	};
+	let db = app.try_db();
+	let out = Readiness {
+		status,
+		error,
+		version: common::PKG_VERSION,
+		uptime: app.started.elapsed().as_secs(),
+		database: db.map(|db| db.info()),
+		counts: db.map(|db| Counts {
+			entries: db.dictionary().count(),
+			kanji: db.kanji().count(),
+			names: db.names().count(),
+			examples: db.examples().count(),
+			strokes: db.strokes().count(),
+		}),
+		cache: db.map(|db| db.dictionary().cache_stats()),
+	};
+	let code = if app.is_ready() {
+		Status::Ok
+	} else {
+		Status::ServiceUnavailable
+	};
+	Custom(code, Json(out))
+}
@@ -0,0 +1,852 @@
+//! Depth, complexity and alias limits for GraphQL queries, checked before
+//! executing them since a single nested query (e.g. kanji → words → kanji)
+//! can be very expensive.
+//!
+//! The complexity of a query is the sum of the cost of its fields, where each
+//! field costs one plus the complexity of its selection multiplied by the
+//! number of items it returns:
+//!
+//! - Fields with a `first` or `last` argument return that many items, and
+//!   connections without those return `DEFAULT_LIMIT` items. Variables
+//!   without a value count as `MAX_LIMIT`.
+//! - Lists without pagination return up to the maximum in `LIST_SIZES`.
+//! - `analyze` returns at most one sentence and one token for each character
+//!   of its `text`.
+//!
+//! The introspection fields `__schema` and `__type` have a fixed cost.
+//!
+//! Queries that cannot be parsed are rejected here, as are queries nested
+//! deeper than twice the maximum depth, so that those never reach the
+//! executor.
+
+use std::collections::HashMap;
+
+use juniper::parser::{Lexer, ScalarToken, SourcePosition, Token};
+use juniper::{FieldError, InputValue};
+
+use crate::config::Config;
+use crate::graph::{
+	COMPONENTS_LIMIT, DEFAULT_LIMIT, MAX_LIMIT, SIMILAR_KANJI_LIMIT, SUGGESTION_LIMIT,
+};
+
+/// Arguments with the number of items returned by a field.
+const PAGE_ARGUMENTS: &[&str] = &["first", "last"];
+
+/// Maximum number of items for the list fields without pagination arguments,
+/// by field name. Lists of scalars or of values loaded with the parent (e.g.
+/// `definitions`) are not included, and count as a single item.
+const LIST_SIZES: &[(&str, usize)] = &[
+	("similarKanji", SIMILAR_KANJI_LIMIT),
+	("components", COMPONENTS_LIMIT),
+	("didYouMean", SUGGESTION_LIMIT),
+	// Candidate entries for a `Token`. Connections named `entries` select
+	// `edges` and count as such.
+	("entries", dict_data::MAX_TOKEN_ENTRIES),
+	("furigana", dict_data::MAX_TOKEN_LENGTH),
+];
+
+/// Cost of the `__schema` and `__type` introspection fields, including their
+/// selection.
+const INTROSPECTION_COST: usize = 100;
+
+/// Checks a query against the `max_depth`, `max_complexity` and `max_aliases`
+/// configuration, returning its complexity. Only the given operation is
+/// checked if it has a name.
+pub fn check(
+	config: &Config,
+	query: &str,
+	operation_name: Option<&str>,
+	variables: Option<&InputValue>,
+) -> Result<usize, FieldError> {
+	let max_nesting = config.max_depth.saturating_mul(2);
+	let document = match parse(query, variables, max_nesting) {
+		Ok(document) => document,
+		Err(ParseError::Syntax(message, position)) => {
+			return Err(syntax_error(message, position));
+		}
+		Err(ParseError::Nesting) => return Err(nesting_error(config)),
+	};
+	if document.aliases > config.max_aliases {
+		return Err(limit_error(
+			"aliases",
+			document.aliases,
+			config.max_aliases,
+			format!(
+				"query has {} aliases, the maximum is {}",
+				document.aliases, config.max_aliases
+			),
+		));
+	}
+
+	let mut measure = Measure {
+		fragments: &document.fragments,
+		cache: HashMap::new(),
+		nesting: 0,
+		max_nesting,
+		exceeded: false,
+	};
+	let mut complexity = 0;
+	for &(name, ref selections) in document.operations.iter() {
+		if operation_name.is_some() && name != operation_name {
+			continue;
+		}
+		let cost = measure.selections(selections);
+		if measure.exceeded {
+			return Err(nesting_error(config));
+		}
+		if cost.depth > config.max_depth {
+			return Err(limit_error(
+				"depth",
+				cost.depth,
+				config.max_depth,
+				format!(
+					"query depth of {} exceeds the maximum of {}",
+					cost.depth, config.max_depth
+				),
+			));
+		}
+		check_complexity(config, cost.complexity)?;
+		complexity = complexity.max(cost.complexity);
+	}
+	Ok(complexity)
+}
+
+/// Checks the total complexity of a batch of queries, from `check`.
+pub fn check_batch(config: &Config, complexity: usize) -> Result<(), FieldError> {
+	if complexity > config.max_complexity {
+		return Err(limit_error(
+			"complexity",
+			complexity,
+			config.max_complexity,
+			format!(
+				"batch complexity of {} exceeds the maximum of {}, send fewer queries \
+				 in the batch",
+				complexity, config.max_complexity
+			),
+		));
+	}
+	Ok(())
+}
+
+fn check_complexity(config: &Config, complexity: usize) -> Result<(), FieldError> {
+	if complexity > config.max_complexity {
+		return Err(limit_error(
+			"complexity",
+			complexity,
+			config.max_complexity,
+			format!(
+				"query complexity of {} exceeds the maximum of {}, request fewer \
+				 items with `first` or fewer nested fields",
+				complexity, config.max_complexity
+			),
+		));
+	}
+	Ok(())
+}
+
+/// Returns the error for an exceeded limit, with the limit name, the query
+/// value and the maximum as extensions.
+fn limit_error(limit: &str, value: usize, max: usize, message: String) -> FieldError {
+	let mut extensions = juniper::Object::with_capacity(3);
+	extensions.add_field("limit", juniper::Value::scalar(limit));
+	extensions.add_field("value", juniper::Value::scalar(to_i32(value)));
+	extensions.add_field("maximum", juniper::Value::scalar(to_i32(max)));
+	FieldError::new(message, juniper::Value::Object(extensions))
+}
+
+/// Returns the depth error for queries that are not measured because they
+/// are nested too deep. The value is the least depth that is rejected.
+fn nesting_error(config: &Config) -> FieldError {
+	limit_error(
+		"depth",
+		config.max_depth.saturating_add(1),
+		config.max_depth,
+		format!(
+			"query nesting exceeds the maximum depth of {}",
+			config.max_depth
+		),
+	)
+}
+
+/// Returns the error for a query that cannot be parsed, with the one-based
+/// line and column as extensions.
+fn syntax_error(message: String, position: SourcePosition) -> FieldError {
+	let mut extensions = juniper::Object::with_capacity(2);
+	extensions.add_field("line", juniper::Value::scalar(to_i32(position.line() + 1)));
+	extensions.add_field(
+		"column",
+		juniper::Value::scalar(to_i32(position.column() + 1)),
+	);
+	FieldError::new(message, juniper::Value::Object(extensions))
+}
+
+fn to_i32(value: usize) -> i32 {
+	value.min(i32::MAX as usize) as i32
+}
+
+/// Parsed query, with only what is needed for the limits.
+struct Document<'a> {
+	/// Operations by name.
+	operations: Vec<(Option<&'a str>, Vec<Selection<'a>>)>,
+
+	fragments: HashMap<&'a str, Vec<Selection<'a>>>,
+
+	/// Number of aliased fields in the whole document.
+	aliases: usize,
+}
+
+enum Selection<'a> {
+	Field {
+		name: &'a str,
+
+		/// Value of the page size argument, if any.
+		page_size: Option<usize>,
+
+		/// Length in characters of the `text` argument, if any.
+		text_length: Option<usize>,
+
+		selections: Vec<Selection<'a>>,
+	},
+
+	/// Named fragment spread.
+	Spread(&'a str),
+
+	/// Inline fragment.
+	Inline(Vec<Selection<'a>>),
+}
+
+enum ParseError {
+	/// Unexpected token, with the error message and position.
+	Syntax(String, SourcePosition),
+
+	/// Selections nested deeper than the maximum.
+	Nesting,
+}
+
+type ParseResult<T> = Result<T, ParseError>;
+
+/// Parses a query, failing if it is not valid or its selections are nested
+/// deeper than `max_nesting`.
+fn parse<'a>(
+	query: &'a str,
+	variables: Option<&InputValue>,
+	max_nesting: usize,
+) -> ParseResult<Document<'a>> {
+	let mut tokens = Vec::new();
+	let mut end = SourcePosition::new_origin();
+	for token in Lexer::new(query) {
+		match token {
+			Ok(token) => {
+				end = token.end;
+				tokens.push((token.item, token.start));
+			}
+			Err(err) => return Err(ParseError::Syntax(err.item.to_string(), err.start)),
+		}
+	}
+	let mut parser = Parser {
+		tokens,
+		end,
+		index: 0,
+		variables,
+		aliases: 0,
+		nesting: 0,
+		max_nesting,
+	};
+	parser.document()
+}
+
+/// Parser for the query tokens. This only handles the syntax and leaves the
+/// validation to the executor.
+struct Parser<'a, 'b> {
+	/// Tokens with their start position.
+	tokens: Vec<(Token<'a>, SourcePosition)>,
+
+	/// End of the query, for errors at the end of the tokens.
+	end: SourcePosition,
+
+	index: usize,
+	variables: Option<&'b InputValue>,
+	aliases: usize,
+
+	/// Number of selection sets around the current token.
+	nesting: usize,
+	max_nesting: usize,
+}
+
+impl<'a, 'b> Parser<'a, 'b> {
+	fn peek(&self) -> Token<'a> {
+		self.tokens
+			.get(self.index)
+			.map(|x| x.0)
+			.unwrap_or(Token::EndOfFile)
+	}
+
+	fn next(&mut self) -> Token<'a> {
+		let token = self.peek();
+		self.index += 1;
+		token
+	}
+
+	/// Returns the error for the last token from `next`.
+	fn unexpected<T>(&self) -> ParseResult<T> {
+		let index = self.index.saturating_sub(1);
+		let error = match self.tokens.get(index) {
+			Some(&(token, position)) if token != Token::EndOfFile => {
+				let message = format!("Unexpected \"{}\"", token);
+				ParseError::Syntax(message, position)
+			}
+			_ => {
+				let message = String::from("Unexpected end of input");
+				ParseError::Syntax(message, self.end)
+			}
+		};
+		Err(error)
+	}
+
+	fn expect(&mut self, token: Token<'a>) -> ParseResult<()> {
+		if self.next() == token {
+			Ok(())
+		} else {
+			self.unexpected()
+		}
+	}
+
+	fn name(&mut self) -> ParseResult<&'a str> {
+		match self.next() {
+			Token::Name(name) => Ok(name),
+			_ => self.unexpected(),
+		}
+	}
+
+	fn document(&mut self) -> ParseResult<Document<'a>> {
+		let mut operations = Vec::new();
+		let mut fragments = HashMap::new();
+		loop {
+			match self.peek() {
+				Token::EndOfFile => break,
+				Token::CurlyOpen => operations.push((None, self.selection_set()?)),
+				Token::Name("fragment") => {
+					self.next();
+					let name = self.name()?;
+					self.expect(Token::Name("on"))?;
+					self.name()?;
+					self.skip_directives()?;
+					fragments.insert(name, self.selection_set()?);
+				}
+				Token::Name(_) => {
+					// Operation type, then the optional name and variables.
+					self.next();
+					let name = match self.peek() {
+						Token::Name(name) => {
+							self.next();
+							Some(name)
+						}
+						_ => None,
+					};
+					if self.peek() == Token::ParenOpen {
+						self.skip_group()?;
+					}
+					self.skip_directives()?;
+					operations.push((name, self.selection_set()?));
+				}
+				_ => {
+					self.next();
+					return self.unexpected();
+				}
+			}
+		}
+		if operations.is_empty() && fragments.is_empty() {
+			return self.unexpected();
+		}
+		Ok(Document {
+			operations,
+			fragments,
+			aliases: self.aliases,
+		})
+	}
+
+	fn selection_set(&mut self) -> ParseResult<Vec<Selection<'a>>> {
+		self.expect(Token::CurlyOpen)?;
+		if self.nesting >= self.max_nesting {
+			return Err(ParseError::Nesting);
+		}
+		self.nesting += 1;
+		let mut selections = Vec::new();
+		while self.peek() != Token::CurlyClose {
+			selections.push(self.selection()?);
+		}
+		self.next();
+		self.nesting -= 1;
+		Ok(selections)
+	}
+
+	fn selection(&mut self) -> ParseResult<Selection<'a>> {
+		match self.next() {
+			Token::Ellipsis => match self.peek() {
+				Token::Name(name) if name != "on" => {
+					self.next();
+					self.skip_directives()?;
+					Ok(Selection::Spread(name))
+				}
+				_ => {
+					if self.peek() == Token::Name("on") {
+						self.next();
+						self.name()?;
+					}
+					self.skip_directives()?;
+					Ok(Selection::Inline(self.selection_set()?))
+				}
+			},
+			Token::Name(mut name) => {
+				if self.peek() == Token::Colon {
+					self.next();
+					self.aliases += 1;
+					name = self.name()?;
+				}
+				let (page_size, text_length) = if self.peek() == Token::ParenOpen {
+					self.arguments()?
+				} else {
+					(None, None)
+				};
+				self.skip_directives()?;
+				let selections = if self.peek() == Token::CurlyOpen {
+					self.selection_set()?
+				} else {
+					Vec::new()
+				};
+				Ok(Selection::Field {
+					name,
+					page_size,
+					text_length,
+					selections,
+				})
+			}
+			_ => self.unexpected(),
+		}
+	}
+
+	/// Parses the field arguments, returning the page size and the length of
+	/// the `text` argument.
+	fn arguments(&mut self) -> ParseResult<(Option<usize>, Option<usize>)> {
+		self.expect(Token::ParenOpen)?;
+		let mut page_size = None;
+		let mut text_length = None;
+		while self.peek() != Token::ParenClose {
+			let name = self.name()?;
+			self.expect(Token::Colon)?;
+			let value = self.value()?;
+			if PAGE_ARGUMENTS.contains(&name) {
+				let size = match value {
+					Value::Int(value) => value,
+					_ => MAX_LIMIT as i64,
+				};
+				page_size = Some(size.max(0).min(MAX_LIMIT as i64) as usize);
+			} else if name == "text" {
+				if let Value::String(length) = value {
+					text_length = Some(length);
+				}
+			}
+		}
+		self.next();
+		Ok((page_size, text_length))
+	}
+
+	/// Parses a value, resolving the variables.
+	fn value(&mut self) -> ParseResult<Value> {
+		match self.peek() {
+			Token::BracketOpen | Token::CurlyOpen => {
+				self.skip_group()?;
+				return Ok(Value::Other);
+			}
+			_ => {}
+		}
+		match self.next() {
+			Token::Dollar => {
+				let name = self.name()?;
+				let variables = self.variables.and_then(|x| x.to_object_value());
+				let value = match variables.and_then(|x| x.get(name).cloned()) {
+					Some(value) => value,
+					None => return Ok(Value::Other),
+				};
+				if let Some(&value) = value.as_scalar_value::<i32>() {
+					Ok(Value::Int(value as i64))
+				} else if let Some(value) = value.as_scalar_value::<String>() {
+					Ok(Value::String(value.chars().count()))
+				} else {
+					Ok(Value::Other)
+				}
+			}
+			Token::Scalar(ScalarToken::Int(text)) => match text.parse() {
+				Ok(value) => Ok(Value::Int(value)),
+				Err(_) => Ok(Value::Other),
+			},
+			// The length with escapes is at least the length of the string.
+			Token::Scalar(ScalarToken::String(text)) => Ok(Value::String(text.chars().count())),
+			Token::Scalar(_) | Token::Name(_) => Ok(Value::Other),
+			_ => self.unexpected(),
+		}
+	}
+
+	fn skip_directives(&mut self) -> ParseResult<()> {
+		while self.peek() == Token::At {
+			self.next();
+			self.name()?;
+			if self.peek() == Token::ParenOpen {
+				self.skip_group()?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Skips a group of tokens in parentheses, brackets or braces.
+	fn skip_group(&mut self) -> ParseResult<()> {
+		let mut level = 0;
+		loop {
+			match self.next() {
+				Token::ParenOpen | Token::BracketOpen | Token::CurlyOpen => {
+					if level >= self.max_nesting {
+						return Err(ParseError::Nesting);
+					}
+					level += 1;
+				}
+				Token::ParenClose | Token::BracketClose | Token::CurlyClose => {
+					level -= 1;
+					if level == 0 {
+						return Ok(());
+					}
+				}
+				Token::EndOfFile => return self.unexpected(),
+				_ => {}
+			}
+		}
+	}
+}
+
+/// Argument value, see `Parser::value`.
+enum Value {
+	Int(i64),
+
+	/// String value, with its length in characters.
+	String(usize),
+
+	Other,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Cost {
+	depth: usize,
+	complexity: usize,
+}
+
+/// Computes the cost of the selections, caching the cost of the fragments
+/// since those can be spread any number of times.
+struct Measure<'a, 'd> {
+	fragments: &'d HashMap<&'a str, Vec<Selection<'a>>>,
+	cache: HashMap<&'a str, Cost>,
+
+	/// Number of fields, fragments and inline fragments around the current
+	/// selections. Fragments can spread other fragments, so this is limited
+	/// to `max_nesting` as in the parser, setting `exceeded` if not.
+	nesting: usize,
+	max_nesting: usize,
+	exceeded: bool,
+}
+
+impl<'a, 'd> Measure<'a, 'd> {
+	fn selections(&mut self, selections: &[Selection<'a>]) -> Cost {
+		if self.nesting >= self.max_nesting {
+			self.exceeded = true;
+			return Cost::default();
+		}
+		self.nesting += 1;
+		let mut total = Cost::default();
+		for selection in selections {
+			let cost = match *selection {
+				Selection::Field { name, .. } if name == "__schema" || name == "__type" => Cost {
+					depth: 1,
+					complexity: INTROSPECTION_COST,
+				},
+				Selection::Field { name, .. } if name.starts_with("__") => continue,
+				Selection::Field {
+					name,
+					page_size,
+					text_length,
+					ref selections,
+				} => {
+					let inner = self.selections(selections);
+					let count = page_size.unwrap_or_else(|| {
+						if is_connection(selections) {
+							DEFAULT_LIMIT as usize
+						} else if name == "analyze" {
+							text_length.unwrap_or(0)
+						} else {
+							list_size(name)
+						}
+					});
+					Cost {
+						depth: inner.depth + 1,
+						complexity: inner.complexity.saturating_mul(count).saturating_add(1),
+					}
+				}
+				Selection::Spread(name) => self.fragment(name),
+				Selection::Inline(ref selections) => self.selections(selections),
+			};
+			total.depth = total.depth.max(cost.depth);
+			total.complexity = total.complexity.saturating_add(cost.complexity);
+		}
+		self.nesting -= 1;
+		total
+	}
+
+	fn fragment(&mut self, name: &'a str) -> Cost {
+		if let Some(&cost) = self.cache.get(name) {
+			return cost;
+		}
+		// Fragment cycles are rejected by the executor, so those count as zero.
+		self.cache.insert(name, Cost::default());
+		let fragments = self.fragments;
+		let cost = match fragments.get(name) {
+			Some(selections) => self.selections(selections),
+			None => Cost::default(),
+		};
+		self.cache.insert(name, cost);
+		cost
+	}
+}
+
+/// True if a field selects `edges`, which is a connection.
+fn is_connection(selections: &[Selection]) -> bool {
+	selections.iter().any(|x| match *x {
+		Selection::Field { name, .. } => name == "edges",
+		_ => false,
+	})
+}
+
+/// Returns the maximum number of items for a field without pagination, see
+/// `LIST_SIZES`.
+fn list_size(name: &str) -> usize {
+	LIST_SIZES.iter().find(|x| x.0 == name).map_or(1, |x| x.1)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn config() -> Config {
+		Config {
+			database: String::new(),
+			verify_database: false,
+			cache_size: 0,
+			graphiql: false,
+			cors_origins: Vec::new(),
+			max_depth: 4,
+			max_complexity: 1000,
+			max_aliases: 2,
+			graphql_limit: 0,
+		}
+	}
+
+	fn complexity(query: &str) -> usize {
+		match check(&config(), query, None, None) {
+			Ok(complexity) => complexity,
+			Err(err) => panic!("query `{}`: {}", query, err.message()),
+		}
+	}
+
+	/// Returns the error message and the `limit` extension, if any.
+	fn error(query: &str, variables: Option<&InputValue>) -> (String, Option<String>) {
+		match check(&config(), query, None, variables) {
+			Ok(complexity) => panic!("query `{}`: expected an error, got {}", query, complexity),
+			Err(err) => {
+				let limit = err
+					.extensions()
+					.as_object_value()
+					.and_then(|x| x.get_field_value("limit"))
+					.and_then(|x| x.as_scalar_value::<String>())
+					.cloned();
+				(err.message().to_string(), limit)
+			}
+		}
+	}
+
+	fn limit(query: &str) -> String {
+		let (message, limit) = error(query, None);
+		match limit {
+			Some(limit) => limit,
+			None => panic!(
+				"query `{}`: expected a limit error, got `{}`",
+				query, message
+			),
+		}
+	}
+
+	#[test]
+	fn test_depth() {
+		assert_eq!(1, complexity("{ version }"));
+		complexity("{ a { b { c { d } } } }");
+		assert_eq!("depth", limit("{ a { b { c { d { e } } } } }"));
+
+		// Inline fragments and spreads do not count, but are limited to twice
+		// the maximum depth.
+		complexity("{ a { ... on A { b { ... { c { d } } } } } }");
+		complexity("{ a { ...F } } fragment F on A { b { c { d } } }");
+		assert_eq!(
+			"depth",
+			limit("{ a { ...F } } fragment F on A { b { c { d { e } } } }")
+		);
+		assert_eq!(
+			"depth",
+			limit("{ ... { ... { ... { ... { ... { ... { ... { ... { a } } } } } } } } }")
+		);
+		assert_eq!("depth", limit("{ a(x: [[[[[[[[[[]]]]]]]]]]) }"));
+
+		let chain: Vec<String> = (0..10)
+			.map(|x| format!("fragment F{} on Query {{ ...F{} }}", x, x + 1))
+			.collect();
+		let query = format!(
+			"{{ ...F0 }} {} fragment F10 on Query {{ version }}",
+			chain.join(" ")
+		);
+		assert_eq!("depth", limit(&query));
+	}
+
+	#[test]
+	fn test_complexity() {
+		let search = |args: &str| {
+			let query = "{ search(query: \"x\"ARGS) { edges { node { expression } } } }";
+			complexity(&query.replace("ARGS", args))
+		};
+		assert_eq!(1 + 20 * 3, search(""));
+		assert_eq!(1 + 10 * 3, search(", first: 10"));
+		assert_eq!(1 + 5 * 3, search(", last: 5"));
+		assert_eq!(1, search(", first: 0"));
+		assert_eq!(1 + 100 * 3, search(", first: 1000"));
+		assert_eq!(1 + 100 * 3, search(", first: \"x\""));
+
+		// Connections without `edges` count as a single item.
+		assert_eq!(2, complexity("{ search(query: \"x\") { totalCount } }"));
+
+		// Nested lists multiply.
+		let query = "{ kanji(character: \"x\") { similarKanji { components { character } } } }";
+		assert_eq!(1 + (1 + 10 * (1 + 20)), complexity(query));
+		let query = "{ lookup(text: \"x\") { didYouMean { key } } }";
+		assert_eq!(1 + (1 + 5), complexity(query));
+
+		// Analysis lists count once for each character of the text.
+		let query = "{ analyze(text: \"食べる\") { sentences { tokens { text } } } }";
+		assert_eq!(1 + 3 * (1 + (1 + 1)), complexity(query));
+		assert_eq!(1 + (1 + 5), complexity("{ a { entries { index } } }"));
+
+		assert_eq!(
+			"complexity",
+			limit("{ a(first: 100) { b(first: 100) { c } } }")
+		);
+	}
+
+	#[test]
+	fn test_introspection() {
+		assert_eq!(0, complexity("{ __typename }"));
+		assert_eq!(
+			100,
+			complexity("{ __schema { types { name fields { name } } } }")
+		);
+		assert_eq!(
+			101,
+			complexity("{ version __type(name: \"Query\") { name fields { name } } }")
+		);
+	}
+
+	#[test]
+	fn test_variables() {
+		let query = "query Search($n: Int) { search(query: \"x\", first: $n) { edges { node { index } } } }";
+		let variables =
+			InputValue::object(vec![("n", InputValue::scalar(5))].into_iter().collect());
+		let result = check(&config(), query, None, Some(&variables));
+		assert_eq!(1 + 5 * 3, result.ok().unwrap());
+
+		// Variables without a value count as the maximum page size.
+		let result = check(&config(), query, None, None);
+		assert_eq!(1 + 100 * 3, result.ok().unwrap());
+
+		let query = "query($text: String) { analyze(text: $text) { sentences { text } } }";
+		let variables = InputValue::object(
+			vec![("text", InputValue::scalar("abcd"))]
+				.into_iter()
+				.collect(),
+		);
+		let result = check(&config(), query, None, Some(&variables));
+		assert_eq!(1 + 4 * 2, result.ok().unwrap());
+	}
+
+	#[test]
+	fn test_fragments() {
+		let fragment = "fragment F on Query { search(query: \"x\", first: 10) { totalCount } }";
+		assert_eq!(22, complexity(&format!("{{ ...F ...F }} {}", fragment)));
+		assert_eq!(
+			11,
+			complexity(&format!("{{ ... on Query {{ ...F }} }} {}", fragment))
+		);
+
+		// Cycles are left for the executor to reject.
+		let query = "{ ...A } fragment A on Query { ...B version } fragment B on Query { ...A }";
+		assert_eq!(1, complexity(query));
+		assert_eq!(0, complexity("{ ...A } fragment A on Query { ...A }"));
+
+		// Unknown fragments cost nothing.
+		assert_eq!(1, complexity("{ version ...Missing }"));
+	}
+
+	#[test]
+	fn test_aliases() {
+		complexity("{ a: version b: version }");
+		assert_eq!("aliases", limit("{ a: version b: version c: version }"));
+		assert_eq!(
+			"aliases",
+			limit("{ a: version ...F } fragment F on Query { b: version c: version }")
+		);
+	}
+
+	#[test]
+	fn test_operation_name() {
+		let query = "query A { version } query B { a(first: 100) { b(first: 100) { c } } }";
+		assert_eq!(1, check(&config(), query, Some("A"), None).ok().unwrap());
+		assert!(check(&config(), query, Some("B"), None).is_err());
+		assert!(check(&config(), query, None, None).is_err());
+
+		// Unknown operations are left for the executor to reject.
+		assert_eq!(0, check(&config(), query, Some("C"), None).ok().unwrap());
+	}
+
+	#[test]
+	fn test_parse_errors() {
+		let check = |query: &str, message: &str, line: i32, column: i32| {
+			let err = super::check(&config(), query, None, None).err().unwrap();
+			assert_eq!(message, err.message(), "query `{}`", query);
+			let extensions = err.extensions().as_object_value().unwrap();
+			let position = |name| {
+				let value = extensions.get_field_value(name).unwrap();
+				*value.as_scalar_value::<i32>().unwrap()
+			};
+			assert_eq!((line, column), (position("line"), position("column")));
+		};
+		check("", "Unexpected end of input", 1, 1);
+		check("{ version", "Unexpected end of input", 1, 10);
+		check("{ version } }", "Unexpected \"}\"", 1, 13);
+		check("{\n  a(first: )\n}", "Unexpected \")\"", 2, 12);
+		check("{ a: }", "Unexpected \"}\"", 1, 6);
+		check("fragment { a }", "Unexpected \"{\"", 1, 10);
+		check("{ a(x: [1, 2) }", "Unexpected \"}\"", 1, 15);
+		check("{ a # }", "Unexpected end of input", 1, 8);
+		check("{ a(x: \"abc) }", "Unterminated string literal", 1, 15);
+		check("{ a ^ }", "Unknown character \"^\"", 1, 5);
+
+		let (message, limit) = error("{ a(x: [1, 2) }", None);
+		assert_eq!(("Unexpected \"}\"".to_string(), None), (message, limit));
+	}
+
+	#[test]
+	fn test_batch() {
+		assert!(check_batch(&config(), 1000).is_ok());
+		assert!(check_batch(&config(), 1001).is_err());
+	}
+}
@@ -9,23 +9,34 @@ extern crate lazy_static;
 extern crate serde;
 extern crate serde_json;
 
+extern crate dict_data;
+extern crate kana;
+
 #[macro_use]
 extern crate rocket;
 extern crate juniper;
-extern crate juniper_rocket;
 extern crate rocket_contrib;
 
 #[macro_use]
 extern crate rocket_include_static_resources;
 
+mod analyze;
 mod app;
 mod common;
+mod config;
+mod connection;
+mod convert;
+mod cors;
 mod graph;
 mod graphql;
+mod health;
+mod limits;
+mod metrics;
 mod server;
+mod strokes;
 
 fn main() {
 	print!("\nStarting Kotoba server...\n");
-	server::launch(app::App::get());
+	server::launch();
 	print!("\nFinished!\n");
 }
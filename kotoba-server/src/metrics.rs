@@ -0,0 +1,312 @@
+//! Prometheus metrics, served at `/metrics` in the text exposition format.
+//!
+//! Request metrics are recorded by the `RequestMetrics` fairing, with the
+//! route URI (e.g. `/api/kanji/<character>/strokes.svg`) as the label. The
+//! GraphQL and search metrics are recorded by `graphql::query` and the
+//! resolvers, using the `Metrics` in the `App`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::ContentType;
+use rocket::response::content::Content;
+use rocket::{Data, Request, Response, State};
+
+use crate::app::App;
+
+/// Buckets for the latency histograms, in seconds.
+const LATENCY_BUCKETS: &[f64] = &[
+	0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Buckets for the search result size histograms.
+const RESULT_BUCKETS: &[f64] = &[
+	0.0, 1.0, 5.0, 10.0, 20.0, 50.0, 100.0, 500.0, 1000.0, 10000.0,
+];
+
+/// Maximum number of distinct GraphQL operation names. Operation names are
+/// chosen by the clients, so any others are counted as `other`.
+const MAX_OPERATIONS: usize = 100;
+
+/// Metrics for the server, see the module documentation.
+#[derive(Default)]
+pub struct Metrics {
+	inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+	/// Request count by route, method and status.
+	requests: BTreeMap<(String, String, u16), u64>,
+
+	/// Request latency by route.
+	latency: BTreeMap<String, Histogram>,
+
+	/// GraphQL operation latency by operation name.
+	operations: BTreeMap<String, Histogram>,
+
+	/// Search result sizes by search mode. The histogram count is the number
+	/// of searches.
+	results: BTreeMap<String, Histogram>,
+}
+
+struct Histogram {
+	buckets: &'static [f64],
+
+	/// Cumulative count for each bucket.
+	counts: Vec<u64>,
+	sum: f64,
+	count: u64,
+}
+
+impl Histogram {
+	fn new(buckets: &'static [f64]) -> Histogram {
+		Histogram {
+			buckets,
+			counts: vec![0; buckets.len()],
+			sum: 0.0,
+			count: 0,
+		}
+	}
+
+	fn observe(&mut self, value: f64) {
+		for (count, &bucket) in self.counts.iter_mut().zip(self.buckets) {
+			if value <= bucket {
+				*count += 1;
+			}
+		}
+		self.sum += value;
+		self.count += 1;
+	}
+
+	fn write(&self, out: &mut String, name: &str, label: &str, value: &str) {
+		let value = escape(value);
+		for (count, bucket) in self.counts.iter().zip(self.buckets) {
+			let _ = writeln!(
+				out,
+				"{}_bucket{{{}=\"{}\",le=\"{}\"}} {}",
+				name, label, value, bucket, count
+			);
+		}
+		let _ = writeln!(
+			out,
+			"{}_bucket{{{}=\"{}\",le=\"+Inf\"}} {}",
+			name, label, value, self.count
+		);
+		let _ = writeln!(out, "{}_sum{{{}=\"{}\"}} {}", name, label, value, self.sum);
+		let _ = writeln!(
+			out,
+			"{}_count{{{}=\"{}\"}} {}",
+			name, label, value, self.count
+		);
+	}
+}
+
+impl Metrics {
+	pub fn new() -> Metrics {
+		Metrics::default()
+	}
+
+	fn observe_request(&self, route: &str, method: &str, status: u16, elapsed: Duration) {
+		let mut inner = self.inner.lock().unwrap();
+		let key = (route.to_string(), method.to_string(), status);
+		*inner.requests.entry(key).or_insert(0) += 1;
+		inner
+			.latency
+			.entry(route.to_string())
+			.or_insert_with(|| Histogram::new(LATENCY_BUCKETS))
+			.observe(seconds(elapsed));
+	}
+
+	/// Records the execution time of a GraphQL operation, by its name.
+	pub fn observe_operation(&self, name: Option<&str>, elapsed: Duration) {
+		let mut inner = self.inner.lock().unwrap();
+		let mut name = name.unwrap_or("anonymous");
+		if !inner.operations.contains_key(name) && inner.operations.len() >= MAX_OPERATIONS {
+			name = "other";
+		}
+		inner
+			.operations
+			.entry(name.to_string())
+			.or_insert_with(|| Histogram::new(LATENCY_BUCKETS))
+			.observe(seconds(elapsed));
+	}
+
+	/// Records a dictionary search with the number of results, by the search
+	/// mode (e.g. `exact` or `query`).
+	pub fn observe_search(&self, mode: &str, results: usize) {
+		let mut inner = self.inner.lock().unwrap();
+		inner
+			.results
+			.entry(mode.to_string())
+			.or_insert_with(|| Histogram::new(RESULT_BUCKETS))
+			.observe(results as f64);
+	}
+
+	/// Returns the metrics in the Prometheus text format.
+	pub fn render(&self, app: &App) -> String {
+		let inner = self.inner.lock().unwrap();
+		let mut out = String::new();
+
+		header(
+			&mut out,
+			"kotoba_http_requests_total",
+			"counter",
+			"HTTP requests.",
+		);
+		for ((route, method, status), count) in inner.requests.iter() {
+			let _ = writeln!(
+				out,
+				"kotoba_http_requests_total{{route=\"{}\",method=\"{}\",status=\"{}\"}} {}",
+				escape(route),
+				method,
+				status,
+				count
+			);
+		}
+
+		let name = "kotoba_http_request_duration_seconds";
+		header(&mut out, name, "histogram", "HTTP request latency.");
+		for (route, histogram) in inner.latency.iter() {
+			histogram.write(&mut out, name, "route", route);
+		}
+
+		let name = "kotoba_graphql_operation_duration_seconds";
+		header(&mut out, name, "histogram", "GraphQL operation latency.");
+		for (operation, histogram) in inner.operations.iter() {
+			histogram.write(&mut out, name, "operation", operation);
+		}
+
+		header(
+			&mut out,
+			"kotoba_searches_total",
+			"counter",
+			"Dictionary searches.",
+		);
+		for (mode, histogram) in inner.results.iter() {
+			let _ = writeln!(
+				out,
+				"kotoba_searches_total{{mode=\"{}\"}} {}",
+				escape(mode),
+				histogram.count
+			);
+		}
+
+		let name = "kotoba_search_results";
+		header(
+			&mut out,
+			name,
+			"histogram",
+			"Number of results for each search.",
+		);
+		for (mode, histogram) in inner.results.iter() {
+			histogram.write(&mut out, name, "mode", mode);
+		}
+
+		if let Some(db) = app.try_db() {
+			let stats = db.dictionary().cache_stats();
+			let out = &mut out;
+			let name = "kotoba_page_cache_hits_total";
+			single(out, name, "counter", "Page cache hits.", stats.hits);
+			let name = "kotoba_page_cache_misses_total";
+			single(out, name, "counter", "Page cache misses.", stats.misses);
+			let name = "kotoba_page_cache_pages";
+			single(
+				out,
+				name,
+				"gauge",
+				"Pages in the cache.",
+				stats.pages as u64,
+			);
+			let name = "kotoba_page_cache_size_bytes";
+			single(
+				out,
+				name,
+				"gauge",
+				"Size of the cached pages.",
+				stats.size as u64,
+			);
+			let name = "kotoba_page_cache_capacity_bytes";
+			single(
+				out,
+				name,
+				"gauge",
+				"Maximum size of the cache.",
+				stats.capacity as u64,
+			);
+		}
+		out
+	}
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+	let _ = writeln!(out, "# HELP {} {}", name, help);
+	let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Writes a metric without labels.
+fn single(out: &mut String, name: &str, kind: &str, help: &str, value: u64) {
+	header(out, name, kind, help);
+	let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Escapes a label value.
+fn escape(value: &str) -> String {
+	value
+		.replace('\\', "\\\\")
+		.replace('"', "\\\"")
+		.replace('\n', "\\n")
+}
+
+fn seconds(duration: Duration) -> f64 {
+	duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1e9
+}
+
+/// Start time of a request, see `RequestMetrics`.
+struct RequestStart(Option<Instant>);
+
+/// Fairing that records the count and latency of the requests.
+pub struct RequestMetrics;
+
+impl Fairing for RequestMetrics {
+	fn info(&self) -> Info {
+		Info {
+			name: "Metrics",
+			kind: Kind::Request | Kind::Response,
+		}
+	}
+
+	fn on_request(&self, request: &mut Request, _: &Data) {
+		request.local_cache(|| RequestStart(Some(Instant::now())));
+	}
+
+	fn on_response(&self, request: &Request, response: &mut Response) {
+		let app = match request.guard::<State<&App>>().succeeded() {
+			Some(app) => app,
+			None => return,
+		};
+		let start = match request.local_cache(|| RequestStart(None)).0 {
+			Some(start) => start,
+			None => return,
+		};
+		let route = match request.route() {
+			Some(route) => route.uri.to_string(),
+			None => String::from("unmatched"),
+		};
+		app.metrics.observe_request(
+			&route,
+			request.method().as_str(),
+			response.status().code,
+			start.elapsed(),
+		);
+	}
+}
+
+/// Returns the server metrics in the Prometheus text format.
+#[get("/metrics")]
+pub fn metrics(app: State<&App>) -> Content<String> {
+	Content(ContentType::Plain, app.metrics.render(&app))
+}
@@ -1,8 +1,17 @@
+use crate::analyze;
 use crate::app::App;
 use crate::common;
+use crate::config::Config;
+use crate::convert;
+use crate::cors::Cors;
 use crate::graph;
 use crate::graphql;
+use crate::health;
+use crate::metrics::{self, RequestMetrics};
+use crate::strokes;
 
+use dict_data::Manifest;
+use rocket::State;
 use rocket_contrib::json::Json;
 use rocket_include_static_resources::StaticResponse;
 
@@ -11,16 +20,20 @@ struct IndexInfo {
 	name: &'static str,
 	description: &'static str,
 	version: &'static str,
+	/// True if the database is loaded, see `health::ready`.
 	healthy: bool,
+	database: Option<&'static Manifest>,
 }
 
 #[get("/")]
-fn index() -> Json<IndexInfo> {
+fn index(app: State<&'static App>) -> Json<IndexInfo> {
+	let app: &'static App = *app.inner();
 	let out = IndexInfo {
 		name: common::PKG_NAME,
 		description: common::PKG_DESCRIPTION,
 		version: common::PKG_VERSION,
-		healthy: true,
+		healthy: app.is_ready(),
+		database: app.try_db().map(|db| db.info()),
 	};
 	Json(out)
 }
@@ -30,14 +43,73 @@ fn favicon() -> StaticResponse {
 	static_response!("favicon")
 }
 
-pub fn launch(app: &'static App) {
-	rocket::ignite()
+/// Serves the bundled GraphiQL assets for `graphql::ide`, so that the IDE
+/// works without internet access. The assets are checked by `build.rs`.
+#[get("/graphiql/<name>")]
+fn graphiql_asset(name: String) -> Option<StaticResponse> {
+	let response = match name.as_str() {
+		"react.production.min.js" => static_response!("react"),
+		"react-dom.production.min.js" => static_response!("react-dom"),
+		"graphiql.min.js" => static_response!("graphiql-js"),
+		"graphiql.min.css" => static_response!("graphiql-css"),
+		_ => return None,
+	};
+	Some(response)
+}
+
+/// Reads the configuration and launches the server, loading the database in
+/// the background.
+///
+/// Exits the process if the configuration is invalid.
+pub fn launch() {
+	let rocket = rocket::ignite();
+	let config = match Config::load(rocket.config()) {
+		Ok(config) => config,
+		Err(errors) => {
+			eprintln!("\nInvalid configuration:");
+			for err in errors {
+				eprintln!("  - {}", err);
+			}
+			std::process::exit(1);
+		}
+	};
+	let app = App::new(config);
+	std::thread::spawn(move || app.load());
+
+	let mut api = routes![
+		analyze::analyze,
+		convert::convert_text,
+		convert::inspect_text,
+		graphql::query,
+		strokes::svg
+	];
+	let mut rocket = rocket
 		.attach(StaticResponse::fairing(|resources| {
-			static_resources_initialize!(resources, "favicon", "static/favicon.ico");
+			static_resources_initialize!(
+				resources,
+				"favicon",
+				"static/favicon.ico",
+				"react",
+				"static/graphiql/react.production.min.js",
+				"react-dom",
+				"static/graphiql/react-dom.production.min.js",
+				"graphiql-js",
+				"static/graphiql/graphiql.min.js",
+				"graphiql-css",
+				"static/graphiql/graphiql.min.css"
+			);
 		}))
+		.attach(RequestMetrics)
 		.manage(app)
 		.manage(graph::Schema::new(graph::Query, graph::Mutation))
-		.mount("/", routes![index, favicon])
-		.mount("/api", routes![graphql::query, graphql::ide])
-		.launch();
+		.mount("/", routes![index, favicon, metrics::metrics])
+		.mount("/health", routes![health::live, health::ready]);
+	if app.config.graphiql {
+		api.extend(routes![graphql::ide]);
+		rocket = rocket.mount("/static", routes![graphiql_asset]);
+	}
+	if !app.config.cors_origins.is_empty() {
+		rocket = rocket.attach(Cors::new(app.config.cors_origins.clone()));
+	}
+	rocket.mount("/api", api).launch();
 }
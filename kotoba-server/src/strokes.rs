@@ -0,0 +1,183 @@
+use rocket::http::{ContentType, Status};
+use rocket::response::content::Content;
+use rocket::State;
+
+use dict_data::KanjiStrokes;
+
+use crate::app::App;
+use crate::health::Ready;
+
+/// Size of the KanjiVG coordinate system.
+const SIZE: f64 = 109.0;
+
+const STROKE_STYLE: &'static str =
+	"fill:none;stroke:#000000;stroke-width:3;stroke-linecap:round;stroke-linejoin:round;";
+
+const NUMBER_STYLE: &'static str = "font-size:8;fill:#808080;font-family:sans-serif;";
+
+/// Color for the current stroke in frame sequences.
+const CURRENT_STROKE_COLOR: &'static str = "#c00000";
+
+/// Returns the stroke order diagram for a kanji as SVG.
+///
+/// With `numbers=true` each stroke is annotated with its number. With
+/// `frames=true` the diagram is a sequence of frames, one for each stroke,
+/// with the current stroke highlighted.
+///
+/// Responds with `404` if there is no stroke data for the character and with
+/// `500` if the stroke data could not be read from the database.
+#[get("/kanji/<character>/strokes.svg?<numbers>&<frames>")]
+pub fn svg(
+	app: State<&App>,
+	_ready: Ready,
+	character: String,
+	numbers: Option<bool>,
+	frames: Option<bool>,
+) -> Result<Option<Content<String>>, Status> {
+	let mut chars = character.chars();
+	let character = match (chars.next(), chars.next()) {
+		(Some(chr), None) => chr,
+		_ => return Ok(None),
+	};
+
+	let strokes = match app.db().strokes().get(character) {
+		Ok(Some(strokes)) => strokes,
+		Ok(None) => return Ok(None),
+		Err(err) => {
+			eprintln!("error reading strokes for {}: {}", character, err);
+			return Err(Status::InternalServerError);
+		}
+	};
+	let numbers = numbers.unwrap_or(false);
+	let output = if frames.unwrap_or(false) {
+		render_frames(&strokes, numbers)
+	} else {
+		render(&strokes, numbers)
+	};
+	Ok(Some(Content(ContentType::SVG, output)))
+}
+
+/// Renders the full kanji.
+fn render(strokes: &KanjiStrokes, numbers: bool) -> String {
+	let mut output = svg_header(SIZE, SIZE);
+	output.push_str(&format!("<g style=\"{}\">\n", STROKE_STYLE));
+	for stroke in strokes.strokes.iter() {
+		output.push_str(&svg_path(&stroke.path, None));
+	}
+	output.push_str("</g>\n");
+
+	if numbers {
+		output.push_str(&format!("<g style=\"{}\">\n", NUMBER_STYLE));
+		for (index, stroke) in strokes.strokes.iter().enumerate() {
+			output.push_str(&svg_number(&stroke.path, index + 1));
+		}
+		output.push_str("</g>\n");
+	}
+
+	output.push_str("</svg>\n");
+	output
+}
+
+/// Renders a frame for each stroke, side by side. Each frame shows the kanji
+/// up to its stroke, with the stroke highlighted.
+fn render_frames(strokes: &KanjiStrokes, numbers: bool) -> String {
+	let count = strokes.strokes.len();
+	let mut output = svg_header(SIZE * (count as f64), SIZE);
+	for frame in 0..count {
+		let offset = SIZE * (frame as f64);
+		output.push_str(&format!("<g transform=\"translate({},0)\">\n", offset));
+		output.push_str(&format!(
+			"<rect x=\"0.5\" y=\"0.5\" width=\"{size}\" height=\"{size}\" style=\"fill:none;stroke:#c0c0c0;stroke-width:1;\"/>\n",
+			size = SIZE - 1.0
+		));
+		output.push_str(&format!("<g style=\"{}\">\n", STROKE_STYLE));
+		for stroke in strokes.strokes.iter().take(frame) {
+			output.push_str(&svg_path(&stroke.path, None));
+		}
+		let current = &strokes.strokes[frame];
+		output.push_str(&svg_path(&current.path, Some(CURRENT_STROKE_COLOR)));
+		output.push_str("</g>\n");
+
+		if numbers {
+			output.push_str(&format!("<g style=\"{}\">\n", NUMBER_STYLE));
+			output.push_str(&svg_number(&current.path, frame + 1));
+			output.push_str("</g>\n");
+		}
+		output.push_str("</g>\n");
+	}
+
+	output.push_str("</svg>\n");
+	output
+}
+
+fn svg_header(width: f64, height: f64) -> String {
+	format!(
+		"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+		w = width,
+		h = height
+	)
+}
+
+fn svg_path(path: &str, color: Option<&str>) -> String {
+	match color {
+		Some(color) => format!("\t<path d=\"{}\" style=\"stroke:{}\"/>\n", escape(path), color),
+		None => format!("\t<path d=\"{}\"/>\n", escape(path)),
+	}
+}
+
+/// Returns the number label for a stroke, placed near the stroke start.
+fn svg_number(path: &str, number: usize) -> String {
+	let (x, y) = start_point(path);
+	let x = (x - 6.0).max(1.0).min(SIZE - 8.0);
+	let y = (y - 2.0).max(8.0).min(SIZE - 1.0);
+	format!("\t<text x=\"{:.2}\" y=\"{:.2}\">{}</text>\n", x, y, number)
+}
+
+/// Returns the starting point of a path from its initial `M` command.
+fn start_point(path: &str) -> (f64, f64) {
+	let path = path.trim_start().trim_start_matches(|c| c == 'M' || c == 'm');
+
+	// Numbers are separated by spaces or commas, or by the sign of the
+	// following number (e.g. `M10-5`).
+	let mut values = Vec::new();
+	let mut current = String::new();
+	for chr in path.chars() {
+		match chr {
+			'0'..='9' | '.' => current.push(chr),
+			'-' if current.len() == 0 => current.push(chr),
+			'-' => {
+				values.push(current);
+				current = chr.to_string();
+			}
+			' ' | ',' if current.len() == 0 => {}
+			_ => {
+				values.push(current);
+				current = String::new();
+				if values.len() == 2 || (chr != ' ' && chr != ',') {
+					break;
+				}
+			}
+		}
+		if values.len() == 2 {
+			break;
+		}
+	}
+	if !current.is_empty() && values.len() < 2 {
+		values.push(current);
+	}
+
+	let value = |n: usize| {
+		values
+			.get(n)
+			.and_then(|x| x.parse::<f64>().ok())
+			.unwrap_or_default()
+	};
+	(value(0), value(1))
+}
+
+fn escape(text: &str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}